@@ -225,7 +225,7 @@ fn save_filtered_image(data: &[u8], width: u32, height: u32, filename: &str) ->
     png.set_rgba8_array(data.to_vec())?;
     
     // 打包PNG数据
-    let png_data = png.pack()?;
+    let png_data = png.pack(None)?;
     
     // 保存到文件
     fs::write(filename, png_data)?;
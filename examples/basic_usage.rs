@@ -107,7 +107,7 @@ fn example_png_encoding() -> Result<(), Box<dyn std::error::Error>> {
     png.set_rgba8_array(pixels)?;
     
     // 打包PNG数据
-    let png_data = png.pack()?;
+    let png_data = png.pack(None)?;
     
     // 保存到文件
     fs::write("output_basic.png", png_data)?;
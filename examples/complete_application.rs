@@ -66,7 +66,7 @@ fn example_image_processing_pipeline() -> Result<(), Box<dyn std::error::Error>>
     };
     
     let mut advanced_png = AdvancedPNG::new(options);
-    advanced_png.set_data(png.get_data().clone());
+    advanced_png.set_data(png.get_data().clone())?;
     advanced_png.convert_color_type(COLORTYPE_GRAYSCALE)?;
     println!("   步骤3: 颜色转换完成");
     
@@ -363,7 +363,7 @@ fn save_processed_image(data: &[u8], width: u32, height: u32, filename: &str) ->
     png.set_color_type(COLORTYPE_COLOR_ALPHA);
     png.set_rgba8_array(data.to_vec())?;
     
-    let png_data = png.pack()?;
+    let png_data = png.pack(None)?;
     fs::write(filename, png_data)?;
     
     Ok(())
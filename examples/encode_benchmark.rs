@@ -0,0 +1,69 @@
+//! 编码性能基准示例
+//! 比较快速模式与默认自适应滤镜模式的编码耗时，以及不同候选滤镜集合的耗时和大小
+
+use rust_png::constants::*;
+use rust_png::png_packer::PngEncoder;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 50;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("=== Rust PNG Library 编码性能基准 ===");
+
+    let (width, height) = (256, 256);
+    let data: Vec<u8> = (0..width * height).flat_map(|_| [30u8, 144, 255, 255]).collect();
+
+    let default_encoder = PngEncoder::new(width, height);
+    let fast_encoder = PngEncoder::new(width, height).fast();
+
+    let (default_time, default_size) = bench(|| default_encoder.encode(&data))?;
+    let (fast_time, fast_size) = bench(|| fast_encoder.encode(&data))?;
+
+    println!("   {}x{} 纯色图像, {} 次迭代", width, height, ITERATIONS);
+    println!("   默认模式: 平均 {:?}, 输出 {} bytes", default_time, default_size);
+    println!("   快速模式: 平均 {:?}, 输出 {} bytes", fast_time, fast_size);
+    println!("   加速比: {:.2}x", default_time.as_secs_f64() / fast_time.as_secs_f64());
+
+    // 带噪声的渐变图像上比较候选滤镜集合
+    let photo: Vec<u8> = (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = (i % width, i / width);
+            let noise = (i.wrapping_mul(2654435761) >> 28) as u8;
+            [x as u8 ^ noise, y as u8, (x + y) as u8 / 2 + noise, 255]
+        })
+        .collect();
+    let all_filters = PngEncoder::new(width, height).deterministic();
+    let subset = PngEncoder::new(width, height)
+        .deterministic()
+        .with_candidate_filters(&[FILTER_NONE, FILTER_SUB, FILTER_PAETH]);
+
+    let (all_time, all_size) = bench(|| all_filters.encode(&photo))?;
+    let (subset_time, subset_size) = bench(|| subset.encode(&photo))?;
+
+    println!("   {}x{} 带噪声渐变图像", width, height);
+    println!("   全部五种滤镜:      平均 {:?}, 输出 {} bytes", all_time, all_size);
+    println!("   None/Sub/Paeth:    平均 {:?}, 输出 {} bytes", subset_time, subset_size);
+    println!(
+        "   加速比: {:.2}x, 大小变化: {:+.1}%",
+        all_time.as_secs_f64() / subset_time.as_secs_f64(),
+        (subset_size as f64 / all_size as f64 - 1.0) * 100.0
+    );
+
+    Ok(())
+}
+
+/// 多次运行编码并返回平均耗时和输出大小
+fn bench<F>(encode: F) -> Result<(Duration, usize), String>
+where
+    F: Fn() -> Result<Vec<u8>, String>,
+{
+    // 预热
+    let size = encode()?.len();
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        encode()?;
+    }
+
+    Ok((start.elapsed() / ITERATIONS, size))
+}
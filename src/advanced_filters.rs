@@ -2,7 +2,9 @@
 //! 实现高级PNG滤镜算法和优化
 
 use crate::constants::*;
+use crate::filter::filter_scanline;
 use crate::filter_extensible::*;
+use crate::utils::clamp_u8_f64;
 
 /// 高级滤镜处理器
 pub struct AdvancedFilterProcessor {
@@ -185,122 +187,10 @@ impl AdvancedFilter for AdaptiveFilter {
 
 impl AdaptiveFilter {
     fn apply_filter(&self, data: &[u8], width: u32, height: u32, filter_type: u8) -> Result<Vec<u8>, String> {
-        let mut result = Vec::new();
-        let bytes_per_row = (width * 4) as usize;
-        
-        for y in 0..height {
-            let row_start = (y * bytes_per_row) as usize;
-            let row_end = row_start + bytes_per_row;
-            
-            if row_end > data.len() {
-                return Err("Insufficient data for row".to_string());
-            }
-            
-            let row_data = &data[row_start..row_end];
-            let mut filtered_row = row_data.to_vec();
-            
-            // 应用滤镜
-            match filter_type {
-                FILTER_NONE => {
-                    // 无滤镜
-                }
-                FILTER_SUB => {
-                    self.apply_sub_filter(&mut filtered_row, width as usize);
-                }
-                FILTER_UP => {
-                    if y > 0 {
-                        let prev_row_start = ((y - 1) * bytes_per_row) as usize;
-                        let prev_row = &data[prev_row_start..prev_row_start + bytes_per_row];
-                        self.apply_up_filter(&mut filtered_row, prev_row);
-                    }
-                }
-                FILTER_AVERAGE => {
-                    self.apply_average_filter(&mut filtered_row, width as usize, y, &data, bytes_per_row);
-                }
-                FILTER_PAETH => {
-                    self.apply_paeth_filter(&mut filtered_row, width as usize, y, &data, bytes_per_row);
-                }
-                _ => return Err("Unknown filter type".to_string()),
-            }
-            
-            result.extend_from_slice(&filtered_row);
-        }
-        
+        let mut result = Vec::with_capacity(data.len());
+        filter_image_rows(data, width, height, filter_type, |filtered_row| result.extend_from_slice(filtered_row))?;
         Ok(result)
     }
-    
-    fn apply_sub_filter(&self, data: &mut [u8], width: usize) {
-        for i in 4..data.len() {
-            data[i] = data[i].wrapping_sub(data[i - 4]);
-        }
-    }
-    
-    fn apply_up_filter(&self, data: &mut [u8], prev_row: &[u8]) {
-        for i in 0..data.len() {
-            data[i] = data[i].wrapping_sub(prev_row[i]);
-        }
-    }
-    
-    fn apply_average_filter(&self, data: &mut [u8], width: usize, y: u32, full_data: &[u8], bytes_per_row: usize) {
-        for i in 0..data.len() {
-            let left = if i >= 4 { data[i - 4] } else { 0 };
-            let up = if y > 0 {
-                let prev_row_start = ((y - 1) * bytes_per_row) as usize;
-                if prev_row_start + i < full_data.len() {
-                    full_data[prev_row_start + i]
-                } else {
-                    0
-                }
-            } else {
-                0
-            };
-            
-            data[i] = data[i].wrapping_sub((left + up) / 2);
-        }
-    }
-    
-    fn apply_paeth_filter(&self, data: &mut [u8], width: usize, y: u32, full_data: &[u8], bytes_per_row: usize) {
-        for i in 0..data.len() {
-            let left = if i >= 4 { data[i - 4] } else { 0 };
-            let up = if y > 0 {
-                let prev_row_start = ((y - 1) * bytes_per_row) as usize;
-                if prev_row_start + i < full_data.len() {
-                    full_data[prev_row_start + i]
-                } else {
-                    0
-                }
-            } else {
-                0
-            };
-            let up_left = if y > 0 && i >= 4 {
-                let prev_row_start = ((y - 1) * bytes_per_row) as usize;
-                if prev_row_start + i - 4 < full_data.len() {
-                    full_data[prev_row_start + i - 4]
-                } else {
-                    0
-                }
-            } else {
-                0
-            };
-            
-            data[i] = data[i].wrapping_sub(self.paeth_predictor(left, up, up_left));
-        }
-    }
-    
-    fn paeth_predictor(&self, a: u8, b: u8, c: u8) -> u8 {
-        let p = a as i16 + b as i16 - c as i16;
-        let pa = (p - a as i16).abs();
-        let pb = (p - b as i16).abs();
-        let pc = (p - c as i16).abs();
-        
-        if pa <= pb && pa <= pc {
-            a
-        } else if pb <= pc {
-            b
-        } else {
-            c
-        }
-    }
 }
 
 /// 分析结果
@@ -396,12 +286,12 @@ impl AdvancedFilter for EdgeDetectionFilter {
                 if edge_strength > self.sensitivity {
                     // 增强边缘
                     for j in 0..3 {
-                        pixel[j] = (pixel[j] as f64 * 1.2).min(255.0) as u8;
+                        pixel[j] = clamp_u8_f64(pixel[j] as f64 * 1.2);
                     }
                 } else {
                     // 平滑非边缘区域
                     for j in 0..3 {
-                        pixel[j] = (pixel[j] as f64 * 0.9).max(0.0) as u8;
+                        pixel[j] = clamp_u8_f64(pixel[j] as f64 * 0.9);
                     }
                 }
                 
@@ -574,111 +464,10 @@ impl FilterOptimizer {
     /// 计算滤镜分数
     fn calculate_filter_score(&self, data: &[u8], width: u32, height: u32, filter_type: u8) -> Result<f64, String> {
         let mut score = 0.0;
-        let bytes_per_row = (width * 4) as usize;
-        
-        for y in 0..height {
-            let row_start = (y * bytes_per_row) as usize;
-            let row_end = row_start + bytes_per_row;
-            
-            if row_end > data.len() {
-                return Err("Insufficient data for row".to_string());
-            }
-            
-            let row_data = &data[row_start..row_end];
-            let mut filtered_row = row_data.to_vec();
-            
-            // 应用滤镜
-            self.apply_filter_to_row(&mut filtered_row, width as usize, y, data, bytes_per_row, filter_type)?;
-            
-            // 计算分数
-            score += self.calculate_row_score(&filtered_row);
-        }
-        
+        filter_image_rows(data, width, height, filter_type, |filtered_row| score += self.calculate_row_score(filtered_row))?;
         Ok(score)
     }
     
-    fn apply_filter_to_row(&self, data: &mut [u8], width: usize, y: u32, full_data: &[u8], bytes_per_row: usize, filter_type: u8) -> Result<(), String> {
-        match filter_type {
-            FILTER_NONE => Ok(()),
-            FILTER_SUB => {
-                for i in 4..data.len() {
-                    data[i] = data[i].wrapping_sub(data[i - 4]);
-                }
-                Ok(())
-            }
-            FILTER_UP => {
-                if y > 0 {
-                    let prev_row_start = ((y - 1) * bytes_per_row) as usize;
-                    let prev_row = &full_data[prev_row_start..prev_row_start + bytes_per_row];
-                    for i in 0..data.len() {
-                        data[i] = data[i].wrapping_sub(prev_row[i]);
-                    }
-                }
-                Ok(())
-            }
-            FILTER_AVERAGE => {
-                for i in 0..data.len() {
-                    let left = if i >= 4 { data[i - 4] } else { 0 };
-                    let up = if y > 0 {
-                        let prev_row_start = ((y - 1) * bytes_per_row) as usize;
-                        if prev_row_start + i < full_data.len() {
-                            full_data[prev_row_start + i]
-                        } else {
-                            0
-                        }
-                    } else {
-                        0
-                    };
-                    data[i] = data[i].wrapping_sub((left + up) / 2);
-                }
-                Ok(())
-            }
-            FILTER_PAETH => {
-                for i in 0..data.len() {
-                    let left = if i >= 4 { data[i - 4] } else { 0 };
-                    let up = if y > 0 {
-                        let prev_row_start = ((y - 1) * bytes_per_row) as usize;
-                        if prev_row_start + i < full_data.len() {
-                            full_data[prev_row_start + i]
-                        } else {
-                            0
-                        }
-                    } else {
-                        0
-                    };
-                    let up_left = if y > 0 && i >= 4 {
-                        let prev_row_start = ((y - 1) * bytes_per_row) as usize;
-                        if prev_row_start + i - 4 < full_data.len() {
-                            full_data[prev_row_start + i - 4]
-                        } else {
-                            0
-                        }
-                    } else {
-                        0
-                    };
-                    data[i] = data[i].wrapping_sub(self.paeth_predictor(left, up, up_left));
-                }
-                Ok(())
-            }
-            _ => Err("Unknown filter type".to_string()),
-        }
-    }
-    
-    fn paeth_predictor(&self, a: u8, b: u8, c: u8) -> u8 {
-        let p = a as i16 + b as i16 - c as i16;
-        let pa = (p - a as i16).abs();
-        let pb = (p - b as i16).abs();
-        let pc = (p - c as i16).abs();
-        
-        if pa <= pb && pa <= pc {
-            a
-        } else if pb <= pc {
-            b
-        } else {
-            c
-        }
-    }
-    
     fn calculate_row_score(&self, data: &[u8]) -> f64 {
         let mut score = 0.0;
         for chunk in data.chunks_exact(4) {
@@ -688,3 +477,30 @@ impl FilterOptimizer {
         score / (data.len() / 4) as f64
     }
 }
+
+/// 用编码器的filter_scanline逐行滤镜RGBA图像，输出可由defilter_image还原
+fn filter_image_rows(data: &[u8], width: u32, height: u32, filter_type: u8, mut on_row: impl FnMut(&[u8])) -> Result<(), String> {
+    if filter_type > FILTER_PAETH {
+        return Err("Unknown filter type".to_string());
+    }
+    
+    let bytes_per_row = width as usize * 4;
+    let required = bytes_per_row.checked_mul(height as usize)
+        .ok_or("Image size overflows usize")?;
+    if data.len() < required {
+        return Err("Insufficient data for row".to_string());
+    }
+    if bytes_per_row == 0 {
+        return Ok(());
+    }
+    
+    let mut filtered_row = Vec::with_capacity(bytes_per_row);
+    let mut prev_row = None;
+    for row in data[..required].chunks_exact(bytes_per_row) {
+        filter_scanline(filter_type, row, prev_row, 4, &mut filtered_row);
+        on_row(&filtered_row);
+        prev_row = Some(row);
+    }
+    
+    Ok(())
+}
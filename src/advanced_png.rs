@@ -104,6 +104,9 @@ impl AdvancedPNG {
             (COLORTYPE_COLOR_ALPHA, COLORTYPE_GRAYSCALE) => {
                 self.rgba_to_grayscale(data)
             }
+            (COLORTYPE_COLOR_ALPHA, COLORTYPE_GRAYSCALE_ALPHA) => {
+                ColorTypeConverter::rgba_to_grayscale_alpha(data, self.bit_depth)
+            }
             _ => Err("Unsupported color type conversion".to_string()),
         }
     }
@@ -238,7 +241,7 @@ impl AdvancedPNG {
             COLORTYPE_GRAYSCALE => if self.bit_depth == 16 { 2 } else { 1 },
             COLORTYPE_COLOR => if self.bit_depth == 16 { 6 } else { 3 },
             COLORTYPE_PALETTE_COLOR => 1,
-            COLORTYPE_GRAYSCALE | COLORTYPE_ALPHA => if self.bit_depth == 16 { 4 } else { 2 },
+            COLORTYPE_GRAYSCALE_ALPHA => if self.bit_depth == 16 { 4 } else { 2 },
             COLORTYPE_COLOR_ALPHA => if self.bit_depth == 16 { 8 } else { 4 },
             _ => 4,
         }
@@ -278,9 +281,29 @@ impl AdvancedPNG {
         self.data.as_ref()
     }
     
-    /// 设置数据
-    pub fn set_data(&mut self, data: Vec<u8>) {
+    /// 原始格式每行的字节数，低于8位的样本按位打包；溢出时返回None
+    fn row_stride(&self) -> Option<usize> {
+        let channels = match self.color_type {
+            COLORTYPE_GRAYSCALE | COLORTYPE_PALETTE_COLOR => 1,
+            COLORTYPE_COLOR => 3,
+            COLORTYPE_GRAYSCALE_ALPHA => 2,
+            _ => 4,
+        };
+        let bits = (self.width as usize).checked_mul(channels * self.bit_depth as usize)?;
+        Some(bits.checked_add(7)? / 8)
+    }
+    
+    /// 设置数据，长度必须与尺寸和颜色类型一致
+    pub fn set_data(&mut self, data: Vec<u8>) -> Result<(), String> {
+        let expected_size = self.row_stride()
+            .and_then(|stride| stride.checked_mul(self.height as usize))
+            .ok_or_else(|| format!("Image size {}x{} overflows usize", self.width, self.height))?;
+        if data.len() != expected_size {
+            return Err(format!("Data size mismatch: expected {} bytes, got {}", expected_size, data.len()));
+        }
+        
         self.data = Some(data);
+        Ok(())
     }
 }
 
@@ -322,7 +345,7 @@ impl PNG16Bit {
             COLORTYPE_GRAYSCALE => 1,
             COLORTYPE_COLOR => 3,
             COLORTYPE_PALETTE_COLOR => 1,
-            COLORTYPE_GRAYSCALE | COLORTYPE_ALPHA => 2,
+            COLORTYPE_GRAYSCALE_ALPHA => 2,
             COLORTYPE_COLOR_ALPHA => 4,
             _ => 4,
         };
@@ -380,7 +403,7 @@ impl PNG16Bit {
             COLORTYPE_GRAYSCALE => 1,
             COLORTYPE_COLOR => 3,
             COLORTYPE_PALETTE_COLOR => 1,
-            COLORTYPE_GRAYSCALE | COLORTYPE_ALPHA => 2,
+            COLORTYPE_GRAYSCALE_ALPHA => 2,
             COLORTYPE_COLOR_ALPHA => 4,
             _ => 4,
         }
@@ -442,6 +465,9 @@ impl ColorTypeConverter {
             (COLORTYPE_COLOR_ALPHA, COLORTYPE_GRAYSCALE) => {
                 Self::rgba_to_grayscale(data, bit_depth)
             }
+            (COLORTYPE_COLOR_ALPHA, COLORTYPE_GRAYSCALE_ALPHA) => {
+                Self::rgba_to_grayscale_alpha(data, bit_depth)
+            }
             _ => Err("Unsupported color type conversion".to_string()),
         }
     }
@@ -560,4 +586,33 @@ impl ColorTypeConverter {
         
         Ok(output)
     }
+    
+    /// RGBA转灰度+Alpha：灰度按标准公式计算，alpha原样保留（16位时为16位alpha）
+    fn rgba_to_grayscale_alpha(data: &[u8], bit_depth: u8) -> Result<Vec<u8>, String> {
+        let mut output = Vec::new();
+        let bytes_per_pixel = if bit_depth == 16 { 8 } else { 4 };
+        
+        for chunk in data.chunks_exact(bytes_per_pixel) {
+            let (r, g, b) = if bit_depth == 16 {
+                let r = u16::from_be_bytes([chunk[0], chunk[1]]);
+                let g = u16::from_be_bytes([chunk[2], chunk[3]]);
+                let b = u16::from_be_bytes([chunk[4], chunk[5]]);
+                (r, g, b)
+            } else {
+                (chunk[0] as u16, chunk[1] as u16, chunk[2] as u16)
+            };
+            
+            let gray = (0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64) as u16;
+            
+            if bit_depth == 16 {
+                output.extend_from_slice(&gray.to_be_bytes());
+                output.extend_from_slice(&chunk[6..8]);
+            } else {
+                output.push(gray as u8);
+                output.push(chunk[3]);
+            }
+        }
+        
+        Ok(output)
+    }
 }
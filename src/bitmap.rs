@@ -1,6 +1,7 @@
 //! 位图处理模块
 //! 匹配原始pngjs库的bitmapper.js和format-normaliser.js
 
+use std::collections::HashSet;
 use crate::constants::*;
 
 /// 位深度转换函数
@@ -68,42 +69,164 @@ pub fn de_palette(input: &[u8], output: &mut [u8], width: usize, height: usize,
     }
 }
 
+/// 将打包的调色板索引（1/2/4/8位）展开为每像素一个字节
+pub fn unpack_indices(data: &[u8], width: usize, height: usize, bit_depth: u8) -> Vec<u8> {
+    let bytes_per_row = (width * bit_depth as usize + 7) / 8;
+    let mut indices = Vec::with_capacity(width * height);
+    
+    for row in data.chunks_exact(bytes_per_row).take(height) {
+        for x in 0..width {
+            indices.push(read_sample(row, x, bit_depth) as u8);
+        }
+    }
+    
+    indices
+}
+
+/// 使用调色板和调色板alpha渲染索引为RGBA
+/// 超出调色板范围的索引输出不透明黑色，缺少alpha的条目视为不透明
+pub fn render_palette(indices: &[u8], palette: &[u8], palette_alpha: &[u8]) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity(indices.len() * 4);
+    
+    for &index in indices {
+        let index = index as usize;
+        match palette.get(index * 3..index * 3 + 3) {
+            Some(rgb) => {
+                rgba.extend_from_slice(rgb);
+                rgba.push(palette_alpha.get(index).copied().unwrap_or(255));
+            }
+            None => rgba.extend_from_slice(&[0, 0, 0, 255]),
+        }
+    }
+    
+    rgba
+}
+
+/// RGBA交错数据拆分为R、G、B、A四个平面
+pub fn rgba_to_planar(rgba: &[u8]) -> [Vec<u8>; 4] {
+    let pixels = rgba.len() / 4;
+    let mut planes: [Vec<u8>; 4] = std::array::from_fn(|_| Vec::with_capacity(pixels));
+    for pixel in rgba.chunks_exact(4) {
+        for (plane, &value) in planes.iter_mut().zip(pixel) {
+            plane.push(value);
+        }
+    }
+    planes
+}
+
+/// RGBA转RGB，指定背景色时按alpha合成，否则直接丢弃alpha
+pub fn rgba_to_rgb(rgba: &[u8], background: Option<[u8; 3]>) -> Vec<u8> {
+    let mut rgb = Vec::with_capacity(rgba.len() / 4 * 3);
+    
+    for pixel in rgba.chunks_exact(4) {
+        match background {
+            Some(bg) if pixel[3] < 255 => {
+                let alpha = pixel[3] as u32;
+                for c in 0..3 {
+                    rgb.push(((pixel[c] as u32 * alpha + bg[c] as u32 * (255 - alpha) + 127) / 255) as u8);
+                }
+            }
+            _ => rgb.extend_from_slice(&pixel[..3]),
+        }
+    }
+    
+    rgb
+}
+
+/// RGBA数据中所有alpha都为255时返回true
+pub fn is_opaque(rgba: &[u8]) -> bool {
+    rgba.chunks_exact(4).all(|pixel| pixel[3] == 255)
+}
+
+/// RGBA数据中存在半透明像素（alpha介于0和255之间）时返回true
+/// 只有全透明和不透明像素的图像可以用tRNS表示
+pub fn has_translucency(rgba: &[u8]) -> bool {
+    rgba.chunks_exact(4).any(|pixel| pixel[3] != 0 && pixel[3] != 255)
+}
+
+/// 统计RGBA数据中不同颜色的个数，超过max时立即停止并返回max + 1
+/// 照片类图像很快就会超出上限，不必扫描全部像素
+pub fn distinct_color_count(rgba: &[u8], max: u32) -> u32 {
+    let mut colors = HashSet::new();
+    for pixel in rgba.chunks_exact(4) {
+        if colors.insert([pixel[0], pixel[1], pixel[2], pixel[3]]) && colors.len() > max as usize {
+            return max.saturating_add(1);
+        }
+    }
+    colors.len() as u32
+}
+
+/// RGBA数据中不同alpha值的个数
+/// 1为单一alpha（通常完全不透明），2为二值透明，大于2为平滑alpha
+pub fn alpha_levels(rgba: &[u8]) -> u32 {
+    let mut seen = [false; 256];
+    for pixel in rgba.chunks_exact(4) {
+        seen[pixel[3] as usize] = true;
+    }
+    seen.iter().filter(|&&present| present).count() as u32
+}
+
 /// 透明度颜色处理
-pub fn replace_transparent_color(input: &[u8], output: &mut [u8], width: usize, height: usize, trans_color: &[u16]) {
-    let mut px_pos = 0;
+/// 按源颜色类型和位深度以原始精度比较关键色，输出8位RGBA
+pub fn replace_transparent_color(input: &[u8], output: &mut [u8], width: usize, height: usize, color_type: u8, bit_depth: u8, trans_color: &[u16]) {
+    let channels = match color_type {
+        COLORTYPE_GRAYSCALE => 1,
+        COLORTYPE_COLOR => 3,
+        _ => return,
+    };
     
-    for _y in 0..height {
-        for _x in 0..width {
-            let mut make_trans = false;
+    if trans_color.len() != channels {
+        return;
+    }
+    
+    let max_value = ((1u32 << bit_depth) - 1) as u16;
+    let bits_per_row = width * channels * bit_depth as usize;
+    let bytes_per_row = (bits_per_row + 7) / 8;
+    let mut sample = [0u16; 3];
+    
+    for y in 0..height {
+        let row = &input[y * bytes_per_row..(y + 1) * bytes_per_row];
+        
+        for x in 0..width {
+            for c in 0..channels {
+                sample[c] = read_sample(row, x * channels + c, bit_depth);
+            }
             
-            if trans_color.len() == 1 {
-                // 灰度透明度
-                if trans_color[0] == input[px_pos] as u16 {
-                    make_trans = true;
-                }
-            } else if trans_color.len() == 3 {
-                // RGB透明度
-                if px_pos + 2 < input.len() &&
-                   trans_color[0] == input[px_pos] as u16 &&
-                   trans_color[1] == input[px_pos + 1] as u16 &&
-                   trans_color[2] == input[px_pos + 2] as u16 {
-                    make_trans = true;
-                }
+            let px_pos = (y * width + x) * 4;
+            if sample[..channels] == trans_color[..] {
+                output[px_pos..px_pos + 4].copy_from_slice(&[0, 0, 0, 0]);
+                continue;
             }
             
-            if make_trans {
-                output[px_pos * 4] = 0;
-                output[px_pos * 4 + 1] = 0;
-                output[px_pos * 4 + 2] = 0;
-                output[px_pos * 4 + 3] = 0;
+            // 缩放到8位
+            let to_u8 = |value: u16| -> u8 {
+                if bit_depth == 16 {
+                    (value >> 8) as u8
+                } else {
+                    (value as u32 * 255 / max_value as u32) as u8
+                }
+            };
+            
+            if channels == 1 {
+                let gray = to_u8(sample[0]);
+                output[px_pos..px_pos + 4].copy_from_slice(&[gray, gray, gray, 255]);
             } else {
-                output[px_pos * 4] = input[px_pos];
-                output[px_pos * 4 + 1] = if px_pos + 1 < input.len() { input[px_pos + 1] } else { input[px_pos] };
-                output[px_pos * 4 + 2] = if px_pos + 2 < input.len() { input[px_pos + 2] } else { input[px_pos] };
-                output[px_pos * 4 + 3] = 255;
+                output[px_pos..px_pos + 4].copy_from_slice(&[to_u8(sample[0]), to_u8(sample[1]), to_u8(sample[2]), 255]);
             }
-            
-            px_pos += 1;
+        }
+    }
+}
+
+/// 读取扫描线中第index个样本的原始值（支持1/2/4/8/16位）
+fn read_sample(row: &[u8], index: usize, bit_depth: u8) -> u16 {
+    match bit_depth {
+        16 => ((row[index * 2] as u16) << 8) | row[index * 2 + 1] as u16,
+        8 => row[index] as u16,
+        _ => {
+            let bit_offset = index * bit_depth as usize;
+            let byte = row[bit_offset / 8];
+            let shift = 8 - bit_depth as usize - (bit_offset % 8);
+            ((byte >> shift) & ((1u8 << bit_depth) - 1)) as u16
         }
     }
 }
@@ -122,7 +245,7 @@ pub fn convert_to_rgba(data: &[u8], width: usize, height: usize, color_type: u8,
                 rgba.push(255);  // A
             }
         }
-        (COLORTYPE_GRAYSCALE | COLORTYPE_ALPHA, 8) => {
+        (COLORTYPE_GRAYSCALE_ALPHA, 8) => {
             // 灰度 + Alpha 8-bit
             for chunk in data.chunks_exact(2) {
                 let gray = chunk[0];
@@ -162,6 +285,26 @@ pub fn convert_to_rgba(data: &[u8], width: usize, height: usize, color_type: u8,
                 }
             }
         }
+        (COLORTYPE_GRAYSCALE, 16) => {
+            // 灰度 16-bit -> 8-bit，与RGB相同取高字节
+            for chunk in data.chunks_exact(2) {
+                let gray = chunk[0];
+                rgba.push(gray); // R
+                rgba.push(gray); // G
+                rgba.push(gray); // B
+                rgba.push(255);  // A
+            }
+        }
+        (COLORTYPE_GRAYSCALE_ALPHA, 16) => {
+            // 灰度 + Alpha 16-bit -> 8-bit
+            for chunk in data.chunks_exact(4) {
+                let gray = chunk[0];
+                rgba.push(gray); // R
+                rgba.push(gray); // G
+                rgba.push(gray); // B
+                rgba.push(chunk[2]); // A (high byte)
+            }
+        }
         (COLORTYPE_COLOR, 16) => {
             // RGB 16-bit -> 8-bit
             for chunk in data.chunks_exact(6) {
@@ -189,13 +332,20 @@ pub fn convert_to_rgba(data: &[u8], width: usize, height: usize, color_type: u8,
     rgba
 }
 
+/// 将16位大端数据转换为原始精度的样本数组
+pub fn to_native_16(data: &[u8]) -> Vec<u16> {
+    data.chunks_exact(2)
+        .map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]]))
+        .collect()
+}
+
 /// 获取每像素字节数
 pub fn get_bytes_per_pixel(color_type: u8) -> usize {
     match color_type {
         COLORTYPE_GRAYSCALE => 1,
         COLORTYPE_COLOR => 3,
         COLORTYPE_PALETTE_COLOR => 1,
-        COLORTYPE_GRAYSCALE | COLORTYPE_ALPHA => 2,
+        COLORTYPE_GRAYSCALE_ALPHA => 2,
         COLORTYPE_COLOR_ALPHA => 4,
         _ => 4, // 默认RGBA
     }
@@ -72,9 +72,14 @@ impl Bitmapper {
         let mut output = vec![0; (self.width * self.height * 4) as usize];
         let mut data_offset = 0;
         
+        let bytes_per_pixel = self.get_bytes_per_pixel();
+        
         for pass in &passes {
-            let pass_data = &data[data_offset..data_offset + pass.data_size];
-            data_offset += pass.data_size;
+            // 输入为已反滤镜的像素数据，不含滤镜字节
+            let pass_size = (pass.width * pass.height) as usize * bytes_per_pixel;
+            let pass_data = data.get(data_offset..data_offset + pass_size)
+                .ok_or_else(|| "Insufficient data for interlace pass".to_string())?;
+            data_offset += pass_size;
             
             self.map_interlace_pass(pass, pass_data, &mut output)?;
         }
@@ -134,7 +139,7 @@ impl Bitmapper {
             COLORTYPE_GRAYSCALE => self.map_grayscale_pixel(pixel_data, output),
             COLORTYPE_COLOR => self.map_rgb_pixel(pixel_data, output),
             COLORTYPE_PALETTE_COLOR => self.map_palette_pixel(pixel_data, output),
-            COLORTYPE_GRAYSCALE | COLORTYPE_ALPHA => self.map_grayscale_alpha_pixel(pixel_data, output),
+            COLORTYPE_GRAYSCALE_ALPHA => self.map_grayscale_alpha_pixel(pixel_data, output),
             COLORTYPE_COLOR_ALPHA => self.map_rgba_pixel(pixel_data, output),
             _ => Err("Unsupported color type".to_string()),
         }
@@ -237,7 +242,7 @@ impl Bitmapper {
             COLORTYPE_GRAYSCALE => 1,
             COLORTYPE_COLOR => 3,
             COLORTYPE_PALETTE_COLOR => 1,
-            COLORTYPE_GRAYSCALE | COLORTYPE_ALPHA => 2,
+            COLORTYPE_GRAYSCALE_ALPHA => 2,
             COLORTYPE_COLOR_ALPHA => 4,
             _ => 4,
         }
@@ -512,6 +517,42 @@ impl PaletteProcessor {
         Ok(output)
     }
     
+    /// 调色板alpha表（tRNS），省略的条目为不透明
+    pub fn trans_color(&self) -> Option<&[u8]> {
+        self.trans_color.as_deref()
+    }
+    
+    /// 将每个条目的alpha映射到levels个均匀分布值中最近的一个（0和255总在其中），
+    /// 并去掉alpha表末尾的255，全部不透明时不再需要tRNS
+    pub fn quantize_alpha(&mut self, levels: u8) -> Result<(), String> {
+        if levels < 2 {
+            return Err(format!("Alpha must be quantized to at least 2 levels, got {}", levels));
+        }
+        let trans_color = match self.trans_color.as_mut() {
+            Some(trans_color) => trans_color,
+            None => return Ok(()),
+        };
+        
+        let steps = (levels - 1) as u32;
+        for alpha in trans_color.iter_mut() {
+            let level = (*alpha as u32 * steps + 127) / 255;
+            *alpha = ((level * 255 + steps / 2) / steps) as u8;
+        }
+        
+        while trans_color.last() == Some(&255) {
+            trans_color.pop();
+        }
+        if trans_color.is_empty() {
+            self.trans_color = None;
+        }
+        Ok(())
+    }
+    
+    /// 能容纳全部调色板索引的最小位深度（1/2/4/8）
+    pub fn minimal_bit_depth(&self) -> u8 {
+        index_bit_depth(self.palette.len().saturating_sub(1).min(255) as u8)
+    }
+    
     /// 获取调色板大小
     pub fn get_palette_size(&self) -> usize {
         self.palette.len()
@@ -526,3 +567,30 @@ impl PaletteProcessor {
         }
     }
 }
+
+/// 能表示最大索引值的最小位深度（1/2/4/8）
+pub fn index_bit_depth(max_index: u8) -> u8 {
+    match max_index {
+        0..=1 => 1,
+        2..=3 => 2,
+        4..=15 => 4,
+        _ => 8,
+    }
+}
+
+/// 按实际使用的最大索引计算调色板图像的最小位深度
+pub fn minimal_index_depth(indices: &[u8]) -> u8 {
+    index_bit_depth(indices.iter().copied().max().unwrap_or(0))
+}
+
+/// 8位灰度数据无损降低位深度后的最小位深度（1/2/4/8）
+/// 低位深度的样本按 255 / (2^depth - 1) 放大，例如只有0和255时可用1位
+pub fn minimal_grayscale_depth(data: &[u8]) -> u8 {
+    [1u8, 2, 4]
+        .into_iter()
+        .find(|&depth| {
+            let step = 255 / ((1u16 << depth) - 1) as u8;
+            data.iter().all(|&value| value % step == 0)
+        })
+        .unwrap_or(8)
+}
@@ -17,14 +17,35 @@ pub const TYPE_iCCP: u32 = 0x69434350;
 pub const TYPE_tEXt: u32 = 0x74455874;
 pub const TYPE_zTXt: u32 = 0x7a545874;
 pub const TYPE_iTXt: u32 = 0x69545874;
+pub const TYPE_eXIf: u32 = 0x65584966;
+pub const TYPE_hIST: u32 = 0x68495354;
+pub const TYPE_pHYs: u32 = 0x70485973;
+pub const TYPE_sPLT: u32 = 0x73504c54;
+pub const TYPE_oFFs: u32 = 0x6f464673;
+pub const TYPE_acTL: u32 = 0x6163544c;
+pub const TYPE_fcTL: u32 = 0x6663544c;
+pub const TYPE_fdAT: u32 = 0x66644154;
 
 // 颜色类型常量
 pub const COLORTYPE_GRAYSCALE: u8 = 0;
 pub const COLORTYPE_PALETTE: u8 = 1;
 pub const COLORTYPE_COLOR: u8 = 2;
+// pngjs的alpha标志位，不要与颜色类型按位或后用于匹配
 pub const COLORTYPE_ALPHA: u8 = 4;
 pub const COLORTYPE_PALETTE_COLOR: u8 = 3;
 pub const COLORTYPE_COLOR_ALPHA: u8 = 6;
+// 灰度+Alpha的PNG颜色类型值
+pub const COLORTYPE_GRAYSCALE_ALPHA: u8 = 4;
+
+// 检查颜色类型与位深度的组合是否符合PNG规范
+pub fn is_valid_color_bitdepth(color_type: u8, bit_depth: u8) -> bool {
+    match color_type {
+        COLORTYPE_GRAYSCALE => matches!(bit_depth, 1 | 2 | 4 | 8 | 16),
+        COLORTYPE_PALETTE_COLOR => matches!(bit_depth, 1 | 2 | 4 | 8),
+        COLORTYPE_COLOR | COLORTYPE_GRAYSCALE_ALPHA | COLORTYPE_COLOR_ALPHA => matches!(bit_depth, 8 | 16),
+        _ => false,
+    }
+}
 
 // 颜色类型到每像素字节数的映射
 pub const COLORTYPE_TO_BPP_MAP: [u8; 7] = [0, 1, 3, 1, 2, 0, 4];
@@ -36,9 +57,28 @@ pub const FILTER_UP: u8 = 2;
 pub const FILTER_AVERAGE: u8 = 3;
 pub const FILTER_PAETH: u8 = 4;
 
+// Deflate策略常量 (与zlib的strategy取值一致)
+pub const DEFLATE_STRATEGY_DEFAULT: u8 = 0;
+pub const DEFLATE_STRATEGY_FILTERED: u8 = 1;
+pub const DEFLATE_STRATEGY_HUFFMAN_ONLY: u8 = 2;
+pub const DEFLATE_STRATEGY_RLE: u8 = 3;
+pub const DEFLATE_STRATEGY_FIXED: u8 = 4;
+
 // Gamma除法因子
 pub const GAMMA_DIVISION: u32 = 100000;
 
+// 标准sRGB色彩空间对应的gAMA值 (1/2.2 * 100000)
+pub const SRGB_GAMMA: u32 = 45455;
+
+// sRGB渲染意图
+pub const SRGB_INTENT_PERCEPTUAL: u8 = 0;
+pub const SRGB_INTENT_RELATIVE: u8 = 1;
+pub const SRGB_INTENT_SATURATION: u8 = 2;
+pub const SRGB_INTENT_ABSOLUTE: u8 = 3;
+
+// 标准sRGB色度值：白点、红、绿、蓝的 (x, y)，乘以100000
+pub const SRGB_CHROMATICITIES: [u32; 8] = [31270, 32900, 64000, 33000, 30000, 60000, 15000, 6000];
+
 // 交错通道定义 (Adam7)
 pub const INTERLACE_PASSES: [[u8; 8]; 7] = [
     [0, 0, 0, 0, 0, 0, 0, 0], // pass 1
@@ -60,26 +100,26 @@ pub const INTERLACE_Y_PASSES: [[u8; 8]; 7] = [
     [1, 3, 5, 7, 0, 0, 0, 0], // pass 7
 ];
 
+// Adam7各通道的 (x起点, y起点, x步长, y步长)
+pub const ADAM7_OFFSETS: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8), // pass 1
+    (4, 0, 8, 8), // pass 2
+    (0, 4, 4, 8), // pass 3
+    (2, 0, 4, 4), // pass 4
+    (0, 2, 2, 4), // pass 5
+    (1, 0, 2, 2), // pass 6
+    (0, 1, 1, 2), // pass 7
+];
+
 // 获取交错通道的宽度和高度
 pub fn get_interlace_pass_size(width: u32, height: u32, pass: usize) -> (u32, u32) {
     if pass >= 7 {
         return (0, 0);
     }
     
-    let x_pass = INTERLACE_PASSES[pass];
-    let y_pass = INTERLACE_Y_PASSES[pass];
-    
-    let mut pass_width = 0;
-    let mut pass_height = 0;
-    
-    for i in 0..8 {
-        if x_pass[i] != 0 {
-            pass_width += (width + (7 - x_pass[i] as u32)) / 8;
-        }
-        if y_pass[i] != 0 {
-            pass_height += (height + (7 - y_pass[i] as u32)) / 8;
-        }
-    }
+    let (x_offset, y_offset, x_step, y_step) = ADAM7_OFFSETS[pass];
+    let pass_width = if width > x_offset { (width - x_offset).div_ceil(x_step) } else { 0 };
+    let pass_height = if height > y_offset { (height - y_offset).div_ceil(y_step) } else { 0 };
     
     (pass_width, pass_height)
 }
@@ -51,11 +51,46 @@ const CRC_TABLE: [u32; 256] = [
 /// 计算CRC32校验和
 /// 匹配原始pngjs库的CRC计算逻辑
 pub fn crc32(data: &[u8]) -> u32 {
-    let mut crc = 0xffffffff;
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finalize()
+}
+
+/// 增量CRC32计算器，结果与crc32一致
+/// 用于分段到达的chunk：先输入4字节类型码，再依次输入数据
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Self { state: 0xffffffff }
+    }
+    
+    /// 输入一段数据
+    pub fn update(&mut self, data: &[u8]) {
+        self.state = update_crc(self.state, data);
+    }
+    
+    /// 返回目前为止所有数据的CRC，计算器本身不变，可以继续输入
+    pub fn finalize(&self) -> u32 {
+        self.state ^ 0xffffffff
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 用数据更新未取反的CRC寄存器
+fn update_crc(mut crc: u32, data: &[u8]) -> u32 {
     for &byte in data {
         crc = CRC_TABLE[((crc ^ (byte as u32)) & 0xff) as usize] ^ (crc >> 8);
     }
-    crc ^ 0xffffffff
+    crc
 }
 
 /// 验证CRC校验和
@@ -65,8 +100,57 @@ pub fn verify_crc(data: &[u8], expected_crc: u32) -> bool {
 
 /// 计算chunk的CRC校验和
 pub fn calculate_chunk_crc(chunk_type: &[u8], chunk_data: &[u8]) -> u32 {
-    let mut combined = Vec::new();
-    combined.extend_from_slice(chunk_type);
-    combined.extend_from_slice(chunk_data);
-    crc32(&combined)
+    let mut crc = Crc32::new();
+    crc.update(chunk_type);
+    crc.update(chunk_data);
+    crc.finalize()
+}
+
+// Adler-32模数，小于2^16的最大素数
+const ADLER_MOD: u32 = 65521;
+// a、b累加不会溢出u32的最大字节数，每段结束时再取模
+const ADLER_NMAX: usize = 5552;
+
+/// 计算Adler-32校验和（zlib数据流末尾的校验值）
+pub fn adler32(data: &[u8]) -> u32 {
+    let mut adler = Adler32::new();
+    adler.update(data);
+    adler.finalize()
+}
+
+/// 增量Adler-32计算器，结果与adler32一致
+/// 用于手工拼接zlib数据流时逐段计算末尾校验值
+#[derive(Debug, Clone, Copy)]
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Adler32 {
+    pub fn new() -> Self {
+        Self { a: 1, b: 0 }
+    }
+    
+    /// 输入一段数据
+    pub fn update(&mut self, data: &[u8]) {
+        for block in data.chunks(ADLER_NMAX) {
+            for &byte in block {
+                self.a += byte as u32;
+                self.b += self.a;
+            }
+            self.a %= ADLER_MOD;
+            self.b %= ADLER_MOD;
+        }
+    }
+    
+    /// 返回目前为止所有数据的Adler-32，计算器本身不变，可以继续输入
+    pub fn finalize(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+}
+
+impl Default for Adler32 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -1,13 +1,17 @@
 //! 自定义滤镜实现示例
 //! 展示如何创建和注册自定义滤镜
 
+use crate::constants::{FILTER_SUB, FILTER_UP, FILTER_AVERAGE};
 use crate::filter_extensible::{Filter, FilterContext};
+use crate::utils::clamp_u8;
 use std::sync::Arc;
 
 /// 自适应滤镜 - 根据图像内容自动选择最佳策略
 pub struct AdaptiveFilter {
     name: String,
     filter_type: u8,
+    /// 固定使用的策略，为None时按图像内容选择
+    strategy: Option<FilterStrategy>,
 }
 
 impl AdaptiveFilter {
@@ -15,8 +19,20 @@ impl AdaptiveFilter {
         Self {
             name: "Adaptive".to_string(),
             filter_type: 10, // 自定义滤镜类型
+            strategy: None,
         }
     }
+    
+    /// 固定使用指定策略，不再按图像内容选择
+    /// 按内容选择时apply和reverse看到的数据不同，可能选中不同的策略，固定策略后二者互为逆操作
+    pub fn with_strategy(mut self, strategy: FilterStrategy) -> Self {
+        self.strategy = Some(strategy);
+        self
+    }
+    
+    fn strategy(&self, data: &[u8], context: &FilterContext) -> FilterStrategy {
+        self.strategy.unwrap_or_else(|| self.analyze_image_content(data, context))
+    }
 }
 
 impl Filter for AdaptiveFilter {
@@ -30,7 +46,7 @@ impl Filter for AdaptiveFilter {
     
     fn apply(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
         // 分析图像内容，选择最佳滤镜策略
-        let strategy = self.analyze_image_content(data, context);
+        let strategy = self.strategy(data, context);
         
         match strategy {
             FilterStrategy::Horizontal => self.apply_horizontal_filter(data, context),
@@ -42,7 +58,7 @@ impl Filter for AdaptiveFilter {
     
     fn reverse(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
         // 反向应用相同的策略
-        let strategy = self.analyze_image_content(data, context);
+        let strategy = self.strategy(data, context);
         
         match strategy {
             FilterStrategy::Horizontal => self.reverse_horizontal_filter(data, context),
@@ -54,7 +70,7 @@ impl Filter for AdaptiveFilter {
     
     fn calculate_compression_ratio(&self, data: &[u8], context: &FilterContext) -> f64 {
         // 计算自适应滤镜的压缩效果
-        let strategy = self.analyze_image_content(data, context);
+        let strategy = self.strategy(data, context);
         self.calculate_strategy_compression(data, context, strategy)
     }
     
@@ -128,19 +144,8 @@ impl AdaptiveFilter {
     }
     
     fn apply_horizontal_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
-        let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
-        
-        if row_end > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
-        for x in context.bytes_per_pixel..bytes_per_row {
-            row[x] = row[x].wrapping_add(row[x - context.bytes_per_pixel]);
-        }
-        Ok(())
+        // 加上左侧已重建的像素，即标准Sub滤镜的重建
+        context.defilter_row(data, FILTER_SUB)
     }
     
     fn reverse_horizontal_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
@@ -153,33 +158,16 @@ impl AdaptiveFilter {
         }
         
         let row = &mut data[row_start..row_end];
-        for x in context.bytes_per_pixel..bytes_per_row {
+        // 从右向左处理，左侧像素仍是原始值
+        for x in (context.bytes_per_pixel..bytes_per_row).rev() {
             row[x] = row[x].wrapping_sub(row[x - context.bytes_per_pixel]);
         }
         Ok(())
     }
     
     fn apply_vertical_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
-        if context.row_index == 0 {
-            return Ok(());
-        }
-        
-        let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
-        let prev_row_start = (context.row_index - 1) * bytes_per_row;
-        
-        if row_end > data.len() || prev_row_start + bytes_per_row > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
-        let prev_row = &data[prev_row_start..prev_row_start + bytes_per_row];
-        
-        for x in 0..bytes_per_row {
-            row[x] = row[x].wrapping_add(prev_row[x]);
-        }
-        Ok(())
+        // 加上上一行的像素，即标准Up滤镜的重建
+        context.defilter_row(data, FILTER_UP)
     }
     
     fn reverse_vertical_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
@@ -187,43 +175,25 @@ impl AdaptiveFilter {
             return Ok(());
         }
         
-        let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
-        let prev_row_start = (context.row_index - 1) * bytes_per_row;
-        
-        if row_end > data.len() || prev_row_start + bytes_per_row > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
-        let prev_row = &data[prev_row_start..prev_row_start + bytes_per_row];
+        let (prev_row, row) = context.split_rows(data)?;
         
-        for x in 0..bytes_per_row {
-            row[x] = row[x].wrapping_sub(prev_row[x]);
+        if let Some(prev_row) = prev_row {
+            for (value, &up) in row.iter_mut().zip(prev_row) {
+                *value = value.wrapping_sub(up);
+            }
         }
         Ok(())
     }
     
     fn apply_diagonal_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
         let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
-        
-        if row_end > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
+        let (prev_row, row) = context.split_rows(data)?;
         
         for x in 0..bytes_per_row {
             let left = if x >= context.bytes_per_pixel { row[x - context.bytes_per_pixel] } else { 0 };
-            let up = if context.row_index > 0 && x < data.len() - (context.row_index - 1) * bytes_per_row {
-                data[(context.row_index - 1) * bytes_per_row + x]
-            } else { 0 };
-            let up_left = if context.row_index > 0 && x >= context.bytes_per_pixel && 
-                           x < data.len() - (context.row_index - 1) * bytes_per_row {
-                data[(context.row_index - 1) * bytes_per_row + x - context.bytes_per_pixel]
+            let up = prev_row.map_or(0, |prev| prev[x]);
+            let up_left = if x >= context.bytes_per_pixel {
+                prev_row.map_or(0, |prev| prev[x - context.bytes_per_pixel])
             } else { 0 };
             
             // 对角线预测器
@@ -235,23 +205,14 @@ impl AdaptiveFilter {
     
     fn reverse_diagonal_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
         let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
+        let (prev_row, row) = context.split_rows(data)?;
         
-        if row_end > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
-        
-        for x in 0..bytes_per_row {
+        // 从右向左处理，左侧像素仍是原始值
+        for x in (0..bytes_per_row).rev() {
             let left = if x >= context.bytes_per_pixel { row[x - context.bytes_per_pixel] } else { 0 };
-            let up = if context.row_index > 0 && x < data.len() - (context.row_index - 1) * bytes_per_row {
-                data[(context.row_index - 1) * bytes_per_row + x]
-            } else { 0 };
-            let up_left = if context.row_index > 0 && x >= context.bytes_per_pixel && 
-                           x < data.len() - (context.row_index - 1) * bytes_per_row {
-                data[(context.row_index - 1) * bytes_per_row + x - context.bytes_per_pixel]
+            let up = prev_row.map_or(0, |prev| prev[x]);
+            let up_left = if x >= context.bytes_per_pixel {
+                prev_row.map_or(0, |prev| prev[x - context.bytes_per_pixel])
             } else { 0 };
             
             let predictor = ((left as u16 + up as u16 + up_left as u16) / 3) as u8;
@@ -261,45 +222,18 @@ impl AdaptiveFilter {
     }
     
     fn apply_gradient_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
-        let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
-        
-        if row_end > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
-        
-        for x in 0..bytes_per_row {
-            let left = if x >= context.bytes_per_pixel { row[x - context.bytes_per_pixel] } else { 0 };
-            let up = if context.row_index > 0 && x < data.len() - (context.row_index - 1) * bytes_per_row {
-                data[(context.row_index - 1) * bytes_per_row + x]
-            } else { 0 };
-            
-            // 梯度预测器
-            let gradient = ((left as i16 + up as i16) / 2) as u8;
-            row[x] = row[x].wrapping_add(gradient);
-        }
-        Ok(())
+        // 梯度预测器取左侧与上方的均值（向下取整），即标准Average滤镜的重建
+        context.defilter_row(data, FILTER_AVERAGE)
     }
     
     fn reverse_gradient_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
         let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
-        
-        if row_end > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
+        let (prev_row, row) = context.split_rows(data)?;
         
-        for x in 0..bytes_per_row {
+        // 从右向左处理，左侧像素仍是原始值
+        for x in (0..bytes_per_row).rev() {
             let left = if x >= context.bytes_per_pixel { row[x - context.bytes_per_pixel] } else { 0 };
-            let up = if context.row_index > 0 && x < data.len() - (context.row_index - 1) * bytes_per_row {
-                data[(context.row_index - 1) * bytes_per_row + x]
-            } else { 0 };
+            let up = prev_row.map_or(0, |prev| prev[x]);
             
             let gradient = ((left as i16 + up as i16) / 2) as u8;
             row[x] = row[x].wrapping_sub(gradient);
@@ -319,8 +253,8 @@ impl AdaptiveFilter {
 }
 
 /// 滤镜策略枚举
-#[derive(Debug, Clone, Copy)]
-enum FilterStrategy {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterStrategy {
     Horizontal,
     Vertical,
     Diagonal,
@@ -355,25 +289,16 @@ impl Filter for EdgeDetectionFilter {
     
     fn apply(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
         let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
-        
-        if row_end > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
+        let (prev_row, row) = context.split_rows(data)?;
         
         for x in 0..bytes_per_row {
             let current = row[x];
             let left = if x >= context.bytes_per_pixel { row[x - context.bytes_per_pixel] } else { current };
-            let up = if context.row_index > 0 && x < data.len() - (context.row_index - 1) * bytes_per_row {
-                data[(context.row_index - 1) * bytes_per_row + x]
-            } else { current };
+            let up = prev_row.map_or(current, |prev| prev[x]);
             
             // 边缘检测算法
-            let edge_strength = ((current as i16 - left as i16).abs() + 
-                                (current as i16 - up as i16).abs()) as u8;
+            let edge_strength = clamp_u8(((current as i16 - left as i16).abs() + 
+                                (current as i16 - up as i16).abs()) as i32);
             
             if edge_strength > self.threshold {
                 row[x] = row[x].wrapping_add(edge_strength);
@@ -384,24 +309,15 @@ impl Filter for EdgeDetectionFilter {
     
     fn reverse(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
         let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
-        
-        if row_end > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
+        let (prev_row, row) = context.split_rows(data)?;
         
         for x in 0..bytes_per_row {
             let current = row[x];
             let left = if x >= context.bytes_per_pixel { row[x - context.bytes_per_pixel] } else { current };
-            let up = if context.row_index > 0 && x < data.len() - (context.row_index - 1) * bytes_per_row {
-                data[(context.row_index - 1) * bytes_per_row + x]
-            } else { current };
+            let up = prev_row.map_or(current, |prev| prev[x]);
             
-            let edge_strength = ((current as i16 - left as i16).abs() + 
-                                (current as i16 - up as i16).abs()) as u8;
+            let edge_strength = clamp_u8(((current as i16 - left as i16).abs() + 
+                                (current as i16 - up as i16).abs()) as i32);
             
             if edge_strength > self.threshold {
                 row[x] = row[x].wrapping_sub(edge_strength);
@@ -423,8 +339,8 @@ impl Filter for EdgeDetectionFilter {
                     let left = if x > 0 { data[idx - context.bytes_per_pixel] } else { current };
                     let up = if y > 0 { data[idx - bytes_per_row] } else { current };
                     
-                    let edge_strength = ((current as i16 - left as i16).abs() + 
-                                        (current as i16 - up as i16).abs()) as u8;
+                    let edge_strength = clamp_u8(((current as i16 - left as i16).abs() + 
+                                        (current as i16 - up as i16).abs()) as i32);
                     
                     if edge_strength > self.threshold {
                         edge_count += 1;
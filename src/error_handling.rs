@@ -3,6 +3,10 @@
 
 use std::fmt;
 use std::error::Error as StdError;
+use crate::constants::{is_valid_color_bitdepth, COLORTYPE_PALETTE_COLOR, PNG_SIGNATURE};
+use crate::filter::unfilter_scanline;
+use crate::png_chunks::{ChunkType, PNGChunkParser};
+use crate::sync_inflate::inflate_zlib_partial;
 
 /// PNG处理错误类型
 #[derive(Debug, Clone)]
@@ -21,6 +25,8 @@ pub enum PNGError {
     MemoryError(String),
     /// 性能错误
     PerformanceError(String),
+    /// 超出解析资源限制
+    LimitsExceeded(String),
     /// 未知错误
     Unknown(String),
 }
@@ -35,6 +41,7 @@ impl fmt::Display for PNGError {
             PNGError::DecodeError(msg) => write!(f, "Decode error: {}", msg),
             PNGError::MemoryError(msg) => write!(f, "Memory error: {}", msg),
             PNGError::PerformanceError(msg) => write!(f, "Performance error: {}", msg),
+            PNGError::LimitsExceeded(msg) => write!(f, "Limits exceeded: {}", msg),
             PNGError::Unknown(msg) => write!(f, "Unknown error: {}", msg),
         }
     }
@@ -42,6 +49,35 @@ impl fmt::Display for PNGError {
 
 impl StdError for PNGError {}
 
+/// 根据文件头的魔数识别图像格式（PNG、JPEG、GIF、BMP、WebP）
+pub fn sniff_format(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&PNG_SIGNATURE) {
+        Some("PNG")
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        Some("JPEG")
+    } else if data.starts_with(b"GIF8") {
+        Some("GIF")
+    } else if data.starts_with(b"BM") {
+        Some("BMP")
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        Some("WebP")
+    } else {
+        None
+    }
+}
+
+/// 检查PNG签名，数据是其他常见图像格式时在错误信息中指出实际格式
+pub fn check_png_signature(data: &[u8]) -> Result<(), PNGError> {
+    match sniff_format(data) {
+        Some("PNG") => Ok(()),
+        Some(format) => Err(PNGError::InvalidFormat(format!("Data is a {}, not a PNG", format))),
+        None if data.len() < PNG_SIGNATURE.len() => {
+            Err(PNGError::InsufficientData("Truncated PNG signature".to_string()))
+        }
+        None => Err(PNGError::InvalidFormat("Invalid PNG signature".to_string())),
+    }
+}
+
 /// 错误恢复策略
 #[derive(Debug, Clone)]
 pub enum RecoveryStrategy {
@@ -137,6 +173,7 @@ impl ErrorHandler {
             PNGError::DecodeError(_) => "DecodeError",
             PNGError::MemoryError(_) => "MemoryError",
             PNGError::PerformanceError(_) => "PerformanceError",
+            PNGError::LimitsExceeded(_) => "LimitsExceeded",
             PNGError::Unknown(_) => "Unknown",
         };
         
@@ -241,6 +278,14 @@ impl ErrorReporter {
     }
 }
 
+/// 完整校验结果
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
 /// 错误验证器
 pub struct ErrorValidator {
     strict_mode: bool,
@@ -266,15 +311,7 @@ impl ErrorValidator {
     }
     
     pub fn validate_png_data(&self, data: &[u8]) -> Result<(), PNGError> {
-        if data.len() < 8 {
-            return Err(PNGError::InsufficientData("PNG signature too short".to_string()));
-        }
-        
-        // 检查PNG签名
-        let signature = [0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a];
-        if &data[0..8] != &signature {
-            return Err(PNGError::InvalidFormat("Invalid PNG signature".to_string()));
-        }
+        check_png_signature(data)?;
         
         if self.strict_mode {
             // 严格模式下的额外验证
@@ -284,6 +321,78 @@ impl ErrorValidator {
         Ok(())
     }
     
+    /// 完整校验：签名和chunk结构、CRC、IHDR字段、首行扫描线试解码
+    /// 收集所有问题而不是遇到第一个错误就返回
+    pub fn validate_report(&self, data: &[u8]) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        
+        if let Err(e) = self.validate_png_data(data).and_then(|_| self.validate_chunks(data)) {
+            report.errors.push(e.to_string());
+            return report;
+        }
+        
+        let mut parser = PNGChunkParser::new().with_lenient(true);
+        if let Err(e) = parser.parse(data) {
+            report.errors.push(e.to_string());
+            return report;
+        }
+        for chunk_type in &parser.crc_warnings {
+            report.warnings.push(format!("Invalid CRC for ancillary chunk {:?}", chunk_type));
+        }
+        if let Some(chunk_type) = &parser.truncated_chunk {
+            report.warnings.push(format!("Truncated chunk {:?}", chunk_type));
+        }
+        if !parser.has_chunk(&ChunkType::IEND) {
+            report.warnings.push("Missing IEND chunk".to_string());
+        }
+        
+        let ihdr = match parser.ihdr.as_ref() {
+            Some(ihdr) => ihdr,
+            None => {
+                report.errors.push("Missing IHDR chunk".to_string());
+                return report;
+            }
+        };
+        
+        if ihdr.width == 0 || ihdr.height == 0 {
+            report.errors.push(format!("Invalid image dimensions {}x{}", ihdr.width, ihdr.height));
+        }
+        if !is_valid_color_bitdepth(ihdr.color_type, ihdr.bit_depth) {
+            report.errors.push(format!("Invalid bit depth {} for color type {}", ihdr.bit_depth, ihdr.color_type));
+        }
+        if ihdr.compression_method != 0 {
+            report.errors.push(format!("Invalid compression method {}", ihdr.compression_method));
+        }
+        if ihdr.filter_method != 0 {
+            report.errors.push(format!("Invalid filter method {}", ihdr.filter_method));
+        }
+        if ihdr.interlace_method > 1 {
+            report.errors.push(format!("Invalid interlace method {}", ihdr.interlace_method));
+        }
+        if ihdr.color_type == COLORTYPE_PALETTE_COLOR && parser.palette.is_none() {
+            report.errors.push("Missing PLTE chunk for indexed image".to_string());
+        }
+        
+        // 试解码第一行扫描线（交错图像的首行属于第一个通道，这里只检查非交错图像）
+        if report.errors.is_empty() && ihdr.interlace_method == 0 {
+            let stride = ihdr.stride();
+            let bpp = ((ihdr.bits_per_pixel() + 7) / 8).max(1);
+            let (raw, _) = inflate_zlib_partial(&parser.idat_data());
+            match raw.get(..stride + 1) {
+                Some(line) => {
+                    let mut row = line[1..].to_vec();
+                    if let Err(e) = unfilter_scanline(line[0], &mut row, None, bpp) {
+                        report.errors.push(format!("Failed to decode first scanline: {}", e));
+                    }
+                }
+                None => report.errors.push("Failed to decode first scanline: insufficient image data".to_string()),
+            }
+        }
+        
+        report.valid = report.errors.is_empty() && !(self.warnings_as_errors && !report.warnings.is_empty());
+        report
+    }
+    
     fn validate_chunks(&self, data: &[u8]) -> Result<(), PNGError> {
         let mut offset = 8; // 跳过PNG签名
         
@@ -296,11 +405,13 @@ impl ErrorValidator {
                 data[offset], data[offset + 1], data[offset + 2], data[offset + 3]
             ]) as usize;
             
-            if offset + 12 + length > data.len() {
+            let chunk_end = (offset + 12).checked_add(length)
+                .ok_or_else(|| PNGError::InvalidFormat(format!("Chunk length {} overflows the data offset", length)))?;
+            if chunk_end > data.len() {
                 return Err(PNGError::InsufficientData("Chunk data incomplete".to_string()));
             }
             
-            offset += 12 + length; // 移动到下一个chunk
+            offset = chunk_end; // 移动到下一个chunk
         }
         
         Ok(())
@@ -335,6 +446,7 @@ impl ErrorRecovery {
             PNGError::DecodeError(_) => "DecodeError",
             PNGError::MemoryError(_) => "MemoryError",
             PNGError::PerformanceError(_) => "PerformanceError",
+            PNGError::LimitsExceeded(_) => "LimitsExceeded",
             PNGError::Unknown(_) => "Unknown",
         };
         
@@ -376,6 +488,7 @@ impl ErrorStats {
             PNGError::DecodeError(_) => "DecodeError",
             PNGError::MemoryError(_) => "MemoryError",
             PNGError::PerformanceError(_) => "PerformanceError",
+            PNGError::LimitsExceeded(_) => "LimitsExceeded",
             PNGError::Unknown(_) => "Unknown",
         };
         
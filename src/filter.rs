@@ -3,26 +3,14 @@
 
 use crate::constants::*;
 
-/// Paeth预测器算法
-/// 匹配原始pngjs库的paeth-predictor.js
-pub fn paeth_predictor(a: u8, b: u8, c: u8) -> u8 {
-    let p = (a as i16) + (b as i16) - (c as i16);
-    let pa = (p - (a as i16)).abs();
-    let pb = (p - (b as i16)).abs();
-    let pc = (p - (c as i16)).abs();
-    
-    if pa <= pb && pa <= pc {
-        a
-    } else if pb <= pc {
-        b
-    } else {
-        c
-    }
-}
+pub use crate::png_core::{paeth_predictor, unfilter_scanline, defilter_image};
 
 /// 应用PNG滤镜
 pub fn apply_filter(filter_type: u8, data: &mut [u8], width: usize, bpp: usize) {
     let bytes_per_row = width * bpp;
+    if bytes_per_row == 0 {
+        return;
+    }
     
     for y in 0..(data.len() / bytes_per_row) {
         let row_start = y * bytes_per_row;
@@ -82,6 +70,9 @@ pub fn apply_filter(filter_type: u8, data: &mut [u8], width: usize, bpp: usize)
 /// 反向应用PNG滤镜（用于编码）
 pub fn reverse_filter(filter_type: u8, data: &mut [u8], width: usize, bpp: usize) {
     let bytes_per_row = width * bpp;
+    if bytes_per_row == 0 {
+        return;
+    }
     
     for y in 0..(data.len() / bytes_per_row) {
         let row_start = y * bytes_per_row;
@@ -138,6 +129,119 @@ pub fn reverse_filter(filter_type: u8, data: &mut [u8], width: usize, bpp: usize
     }
 }
 
+/// 对单行扫描线应用编码滤镜
+/// prev_row为None时表示首行，上一行按全0处理
+pub fn filter_scanline(filter_type: u8, row: &[u8], prev_row: Option<&[u8]>, bpp: usize, out: &mut Vec<u8>) {
+    out.clear();
+    out.reserve(row.len());
+
+    for x in 0..row.len() {
+        let left = if x >= bpp { row[x - bpp] } else { 0 };
+        let up = prev_row.map_or(0, |prev| prev[x]);
+        let up_left = if x >= bpp { prev_row.map_or(0, |prev| prev[x - bpp]) } else { 0 };
+
+        let predictor = match filter_type {
+            FILTER_SUB => left,
+            FILTER_UP => up,
+            FILTER_AVERAGE => ((left as u16 + up as u16) / 2) as u8,
+            FILTER_PAETH => paeth_predictor(left, up, up_left),
+            _ => 0,
+        };
+
+        out.push(row[x].wrapping_sub(predictor));
+    }
+}
+
+/// 估算滤镜后扫描线的代价（有符号绝对值之和）
+pub fn scanline_cost(filtered: &[u8]) -> u64 {
+    filtered.iter().map(|&b| (b as i8).unsigned_abs() as u64).sum()
+}
+
+/// 平坦扫描线的快速判断，无需逐个估算候选滤镜
+/// 与上一行相同时使用Up（输出全0），所有像素相同时使用None（重复的字节序列）
+pub fn flat_scanline_filter(row: &[u8], prev_row: Option<&[u8]>, bpp: usize, candidates: &[u8]) -> Option<u8> {
+    if prev_row == Some(row) && candidates.contains(&FILTER_UP) {
+        return Some(FILTER_UP);
+    }
+    
+    let first_pixel = row.get(..bpp)?;
+    if candidates.contains(&FILTER_NONE) && row.chunks(bpp).all(|pixel| pixel == &first_pixel[..pixel.len()]) {
+        return Some(FILTER_NONE);
+    }
+    
+    None
+}
+
+/// 为单行选择代价最小的候选滤镜
+/// 代价只依赖原始数据的当前行和上一行，因此各行之间相互独立
+pub fn select_scanline_filter(row: &[u8], prev_row: Option<&[u8]>, bpp: usize, candidates: &[u8]) -> u8 {
+    if let Some(filter_type) = flat_scanline_filter(row, prev_row, bpp, candidates) {
+        return filter_type;
+    }
+    
+    let mut filtered = Vec::with_capacity(row.len());
+    let mut best_filter = candidates.first().copied().unwrap_or(FILTER_NONE);
+    let mut best_cost = u64::MAX;
+    
+    for &filter_type in candidates {
+        filter_scanline(filter_type, row, prev_row, bpp, &mut filtered);
+        let cost = scanline_cost(&filtered);
+        if cost < best_cost {
+            best_cost = cost;
+            best_filter = filter_type;
+        }
+    }
+    
+    best_filter
+}
+
+/// 为每一行选择滤镜（串行）
+pub fn select_row_filters_serial(data: &[u8], bytes_per_row: usize, bpp: usize, candidates: &[u8]) -> Vec<u8> {
+    if bytes_per_row == 0 {
+        return Vec::new();
+    }
+    
+    data.chunks_exact(bytes_per_row)
+        .enumerate()
+        .map(|(y, row)| {
+            let prev_row = if y > 0 { Some(&data[(y - 1) * bytes_per_row..y * bytes_per_row]) } else { None };
+            select_scanline_filter(row, prev_row, bpp, candidates)
+        })
+        .collect()
+}
+
+/// 为每一行选择滤镜（rayon并行）
+/// 只并行代价估算，滤镜本身仍按行顺序应用
+#[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+pub fn select_row_filters_parallel(data: &[u8], bytes_per_row: usize, bpp: usize, candidates: &[u8]) -> Vec<u8> {
+    use rayon::prelude::*;
+    
+    if bytes_per_row == 0 {
+        return Vec::new();
+    }
+    
+    data.par_chunks_exact(bytes_per_row)
+        .enumerate()
+        .map(|(y, row)| {
+            let prev_row = if y > 0 { Some(&data[(y - 1) * bytes_per_row..y * bytes_per_row]) } else { None };
+            select_scanline_filter(row, prev_row, bpp, candidates)
+        })
+        .collect()
+}
+
+/// 为每一行选择滤镜
+/// 启用rayon特性时并行执行，wasm32目标下始终串行
+pub fn select_row_filters(data: &[u8], bytes_per_row: usize, bpp: usize, candidates: &[u8]) -> Vec<u8> {
+    #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+    {
+        select_row_filters_parallel(data, bytes_per_row, bpp, candidates)
+    }
+    #[cfg(not(all(feature = "rayon", not(target_arch = "wasm32"))))]
+    {
+        select_row_filters_serial(data, bytes_per_row, bpp, candidates)
+    }
+}
+
 /// 选择最佳滤镜类型
 pub fn choose_best_filter(data: &[u8], width: usize, bpp: usize) -> u8 {
     let bytes_per_row = width * bpp;
@@ -2,6 +2,7 @@
 //! 支持自定义滤镜和插件式架构
 
 use crate::constants::*;
+use crate::filter::defilter_image;
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -16,6 +17,43 @@ pub struct FilterContext {
     pub previous_row: Option<Vec<u8>>,
 }
 
+impl FilterContext {
+    /// 将数据拆分为上一行和当前行，首行没有上一行
+    pub fn split_rows<'a>(&self, data: &'a mut [u8]) -> Result<(Option<&'a [u8]>, &'a mut [u8]), String> {
+        let bytes_per_row = self.width * self.bytes_per_pixel;
+        let row_start = self.row_index * bytes_per_row;
+        let row_end = row_start + bytes_per_row;
+        
+        if row_end > data.len() {
+            return Err("Row index out of bounds".to_string());
+        }
+        
+        let (before, rest) = data.split_at_mut(row_start);
+        // checked_sub避免首行时下溢
+        let prev_row = self.row_index.checked_sub(1)
+            .map(|prev_index| &before[prev_index * bytes_per_row..]);
+        
+        Ok((prev_row, &mut rest[..bytes_per_row]))
+    }
+    
+    /// 用标准PNG滤镜重建row_index所在行，上一行需已重建
+    pub fn defilter_row(&self, data: &mut [u8], filter_type: u8) -> Result<(), String> {
+        let bytes_per_row = self.width * self.bytes_per_pixel;
+        let row_end = (self.row_index + 1) * bytes_per_row;
+        
+        if row_end > data.len() {
+            return Err("Row index out of bounds".to_string());
+        }
+        
+        // 连同上一行一起交给defilter_image，上一行按None处理保持不变
+        let first_row = self.row_index.saturating_sub(1);
+        let mut filter_bytes = vec![FILTER_NONE; self.row_index - first_row + 1];
+        filter_bytes[self.row_index - first_row] = filter_type;
+        
+        defilter_image(&mut data[first_row * bytes_per_row..row_end], self.width, filter_bytes.len(), self.bytes_per_pixel, &filter_bytes)
+    }
+}
+
 /// 滤镜处理结果
 #[derive(Debug, Clone)]
 pub struct FilterResult {
@@ -85,10 +123,7 @@ impl Filter for StandardFilter {
     fn apply(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
         match self.filter_type {
             FILTER_NONE => Ok(()),
-            FILTER_SUB => self.apply_sub_filter(data, context),
-            FILTER_UP => self.apply_up_filter(data, context),
-            FILTER_AVERAGE => self.apply_average_filter(data, context),
-            FILTER_PAETH => self.apply_paeth_filter(data, context),
+            FILTER_SUB | FILTER_UP | FILTER_AVERAGE | FILTER_PAETH => context.defilter_row(data, self.filter_type),
             _ => Err(format!("Unsupported filter type: {}", self.filter_type)),
         }
     }
@@ -117,22 +152,6 @@ impl Filter for StandardFilter {
 }
 
 impl StandardFilter {
-    fn apply_sub_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
-        let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
-        
-        if row_end > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
-        for x in context.bytes_per_pixel..bytes_per_row {
-            row[x] = row[x].wrapping_add(row[x - context.bytes_per_pixel]);
-        }
-        Ok(())
-    }
-    
     fn reverse_sub_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
         let bytes_per_row = context.width * context.bytes_per_pixel;
         let row_start = context.row_index * bytes_per_row;
@@ -143,148 +162,52 @@ impl StandardFilter {
         }
         
         let row = &mut data[row_start..row_end];
-        for x in context.bytes_per_pixel..bytes_per_row {
+        // 从右向左处理，左侧像素仍是原始值
+        for x in (context.bytes_per_pixel..bytes_per_row).rev() {
             row[x] = row[x].wrapping_sub(row[x - context.bytes_per_pixel]);
         }
         Ok(())
     }
     
-    fn apply_up_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
-        if context.row_index == 0 {
-            return Ok(());
-        }
-        
-        let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
-        let prev_row_start = (context.row_index - 1) * bytes_per_row;
-        
-        if row_end > data.len() || prev_row_start + bytes_per_row > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
-        let prev_row = &data[prev_row_start..prev_row_start + bytes_per_row];
-        
-        for x in 0..bytes_per_row {
-            row[x] = row[x].wrapping_add(prev_row[x]);
-        }
-        Ok(())
-    }
-    
     fn reverse_up_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
         if context.row_index == 0 {
             return Ok(());
         }
         
-        let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
-        let prev_row_start = (context.row_index - 1) * bytes_per_row;
-        
-        if row_end > data.len() || prev_row_start + bytes_per_row > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
-        let prev_row = &data[prev_row_start..prev_row_start + bytes_per_row];
+        let (prev_row, row) = context.split_rows(data)?;
         
-        for x in 0..bytes_per_row {
-            row[x] = row[x].wrapping_sub(prev_row[x]);
-        }
-        Ok(())
-    }
-    
-    fn apply_average_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
-        let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
-        
-        if row_end > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
-        
-        for x in 0..bytes_per_row {
-            let left = if x >= context.bytes_per_pixel { row[x - context.bytes_per_pixel] } else { 0 };
-            let up = if context.row_index > 0 && x < data.len() - (context.row_index - 1) * bytes_per_row {
-                data[(context.row_index - 1) * bytes_per_row + x]
-            } else { 0 };
-            let average = ((left as u16 + up as u16) / 2) as u8;
-            row[x] = row[x].wrapping_add(average);
+        if let Some(prev_row) = prev_row {
+            for (value, &up) in row.iter_mut().zip(prev_row) {
+                *value = value.wrapping_sub(up);
+            }
         }
         Ok(())
     }
     
     fn reverse_average_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
         let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
-        
-        if row_end > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
+        let (prev_row, row) = context.split_rows(data)?;
         
-        let row = &mut data[row_start..row_end];
-        
-        for x in 0..bytes_per_row {
+        // 从右向左处理，左侧像素仍是原始值
+        for x in (0..bytes_per_row).rev() {
             let left = if x >= context.bytes_per_pixel { row[x - context.bytes_per_pixel] } else { 0 };
-            let up = if context.row_index > 0 && x < data.len() - (context.row_index - 1) * bytes_per_row {
-                data[(context.row_index - 1) * bytes_per_row + x]
-            } else { 0 };
+            let up = prev_row.map_or(0, |prev| prev[x]);
             let average = ((left as u16 + up as u16) / 2) as u8;
             row[x] = row[x].wrapping_sub(average);
         }
         Ok(())
     }
     
-    fn apply_paeth_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
-        let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
-        
-        if row_end > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
-        
-        for x in 0..bytes_per_row {
-            let left = if x >= context.bytes_per_pixel { row[x - context.bytes_per_pixel] } else { 0 };
-            let up = if context.row_index > 0 && x < data.len() - (context.row_index - 1) * bytes_per_row {
-                data[(context.row_index - 1) * bytes_per_row + x]
-            } else { 0 };
-            let up_left = if context.row_index > 0 && x >= context.bytes_per_pixel && 
-                           x < data.len() - (context.row_index - 1) * bytes_per_row {
-                data[(context.row_index - 1) * bytes_per_row + x - context.bytes_per_pixel]
-            } else { 0 };
-            
-            let predictor = self.paeth_predictor(left, up, up_left);
-            row[x] = row[x].wrapping_add(predictor);
-        }
-        Ok(())
-    }
-    
     fn reverse_paeth_filter(&self, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
         let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_start = context.row_index * bytes_per_row;
-        let row_end = row_start + bytes_per_row;
+        let (prev_row, row) = context.split_rows(data)?;
         
-        if row_end > data.len() {
-            return Err("Row index out of bounds".to_string());
-        }
-        
-        let row = &mut data[row_start..row_end];
-        
-        for x in 0..bytes_per_row {
+        // 从右向左处理，左侧像素仍是原始值
+        for x in (0..bytes_per_row).rev() {
             let left = if x >= context.bytes_per_pixel { row[x - context.bytes_per_pixel] } else { 0 };
-            let up = if context.row_index > 0 && x < data.len() - (context.row_index - 1) * bytes_per_row {
-                data[(context.row_index - 1) * bytes_per_row + x]
-            } else { 0 };
-            let up_left = if context.row_index > 0 && x >= context.bytes_per_pixel && 
-                           x < data.len() - (context.row_index - 1) * bytes_per_row {
-                data[(context.row_index - 1) * bytes_per_row + x - context.bytes_per_pixel]
+            let up = prev_row.map_or(0, |prev| prev[x]);
+            let up_left = if x >= context.bytes_per_pixel {
+                prev_row.map_or(0, |prev| prev[x - context.bytes_per_pixel])
             } else { 0 };
             
             let predictor = self.paeth_predictor(left, up, up_left);
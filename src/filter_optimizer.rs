@@ -2,9 +2,8 @@
 //! 提供并行处理、缓存优化和性能分析
 
 use crate::filter_extensible::{Filter, FilterContext, FilterProcessor};
-use std::sync::Arc;
+use crate::utils::clamp_u8;
 use std::thread;
-use std::sync::mpsc;
 
 /// 并行滤镜处理器
 pub struct ParallelFilterProcessor {
@@ -26,120 +25,91 @@ impl ParallelFilterProcessor {
         }
     }
     
-    /// 并行应用滤镜
+    /// 应用滤镜（重建方向）：每行依赖已重建的上一行，只能自上而下串行处理
     pub fn apply_filter_parallel(&self, filter_type: u8, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
-        if let Some(filter) = self.processor.registry.get_filter(filter_type) {
-            if filter.supports_parallel() {
-                self.apply_parallel(data, context, |row_data, row_context| {
-                    filter.apply(row_data, row_context)
-                })
-            } else {
-                // 回退到串行处理
-                filter.apply(data, context)
-            }
-        } else {
-            Err(format!("Filter type {} not found", filter_type))
+        let filter = self.processor.registry.get_filter(filter_type)
+            .ok_or_else(|| format!("Filter type {} not found", filter_type))?;
+        let row_count = Self::checked_row_count(data, context)?;
+        
+        for row in 0..row_count {
+            filter.apply(data, &FilterContext { row_index: row, ..context.clone() })?;
         }
+        
+        Ok(())
     }
     
-    /// 并行反向应用滤镜
+    /// 反向应用滤镜（编码方向）：每行只依赖原始的上一行，可按行块分给各线程
     pub fn reverse_filter_parallel(&self, filter_type: u8, data: &mut [u8], context: &FilterContext) -> Result<(), String> {
-        if let Some(filter) = self.processor.registry.get_filter(filter_type) {
-            if filter.supports_parallel() {
-                self.apply_parallel(data, context, |row_data, row_context| {
-                    filter.reverse(row_data, row_context)
-                })
-            } else {
-                filter.reverse(data, context)
-            }
-        } else {
-            Err(format!("Filter type {} not found", filter_type))
-        }
-    }
-    
-    fn apply_parallel<F>(&self, data: &mut [u8], context: &FilterContext, filter_func: F) -> Result<(), String>
-    where
-        F: Fn(&mut [u8], &FilterContext) -> Result<(), String> + Send + Sync + 'static,
-    {
+        let filter = self.processor.registry.get_filter(filter_type)
+            .ok_or_else(|| format!("Filter type {} not found", filter_type))?;
+        let row_count = Self::checked_row_count(data, context)?;
         let bytes_per_row = context.width * context.bytes_per_pixel;
-        let row_count = context.height;
         
-        if row_count == 0 {
+        if row_count == 0 || bytes_per_row == 0 {
             return Ok(());
         }
         
-        // 计算每个线程处理的行数
-        let rows_per_thread = (row_count + self.thread_count - 1) / self.thread_count;
-        
-        // 创建通道用于线程间通信
-        let (tx, rx) = mpsc::channel();
+        // 各块从原始数据的快照中读取上一行，写入互不重叠的输出块
+        let original = data[..row_count * bytes_per_row].to_vec();
+        let thread_count = if filter.supports_parallel() && !cfg!(target_arch = "wasm32") {
+            self.thread_count.clamp(1, row_count)
+        } else {
+            1
+        };
+        let rows_per_block = (row_count + thread_count - 1) / thread_count;
+        let blocks = data[..row_count * bytes_per_row].chunks_mut(rows_per_block * bytes_per_row).enumerate();
         
-        // 启动工作线程
-        let mut handles = Vec::new();
+        if thread_count == 1 {
+            return blocks
+                .map(|(index, block)| Self::reverse_block(filter.as_ref(), &original, index * rows_per_block, block, context))
+                .collect();
+        }
         
-        for thread_id in 0..self.thread_count {
-            let start_row = thread_id * rows_per_thread;
-            let end_row = ((thread_id + 1) * rows_per_thread).min(row_count);
-            
-            if start_row >= end_row {
-                break;
-            }
-            
-            let tx = tx.clone();
-            let filter_func = Arc::new(filter_func);
-            let data_ptr = data.as_mut_ptr();
-            let data_len = data.len();
-            
-            let handle = thread::spawn(move || {
-                let mut local_data = unsafe {
-                    std::slice::from_raw_parts_mut(data_ptr, data_len)
-                };
-                
-                for row in start_row..end_row {
-                    let row_start = row * bytes_per_row;
-                    let row_end = row_start + bytes_per_row;
-                    
-                    if row_end <= local_data.len() {
-                        let row_data = &mut local_data[row_start..row_end];
-                        let row_context = FilterContext {
-                            width: context.width,
-                            height: context.height,
-                            bytes_per_pixel: context.bytes_per_pixel,
-                            row_index: row,
-                            column_index: 0,
-                            previous_row: if row > 0 {
-                                Some(local_data[(row - 1) * bytes_per_row..row * bytes_per_row].to_vec())
-                            } else {
-                                None
-                            },
-                        };
-                        
-                        if let Err(e) = filter_func(row_data, &row_context) {
-                            let _ = tx.send(Err(e));
-                            return;
-                        }
-                    }
-                }
-                
-                let _ = tx.send(Ok(()));
-            });
+        thread::scope(|scope| {
+            let handles: Vec<_> = blocks
+                .map(|(index, block)| {
+                    let filter = filter.as_ref();
+                    let original = &original;
+                    scope.spawn(move || Self::reverse_block(filter, original, index * rows_per_block, block, context))
+                })
+                .collect();
             
-            handles.push(handle);
-        }
+            handles
+                .into_iter()
+                .map(|handle| handle.join().map_err(|_| "Filter thread panicked".to_string())?)
+                .collect()
+        })
+    }
+    
+    fn checked_row_count(data: &[u8], context: &FilterContext) -> Result<usize, String> {
+        let required = context.width
+            .checked_mul(context.bytes_per_pixel)
+            .and_then(|bytes_per_row| bytes_per_row.checked_mul(context.height))
+            .ok_or("Image dimensions overflow")?;
         
-        // 等待所有线程完成
-        for handle in handles {
-            handle.join().unwrap();
+        if data.len() < required {
+            return Err("Data too short for image dimensions".to_string());
         }
         
-        // 检查是否有错误
-        drop(tx);
-        while let Ok(result) = rx.recv() {
-            if let Err(e) = result {
-                return Err(e);
-            }
+        Ok(context.height)
+    }
+    
+    /// 在带有原始上一行的局部缓冲区中自下而上滤镜一个行块
+    fn reverse_block(filter: &dyn Filter, original: &[u8], first_row: usize, block: &mut [u8], context: &FilterContext) -> Result<(), String> {
+        let bytes_per_row = context.width * context.bytes_per_pixel;
+        let context_row = first_row.saturating_sub(1);
+        let mut local = original[context_row * bytes_per_row..first_row * bytes_per_row + block.len()].to_vec();
+        let local_context = FilterContext {
+            height: local.len() / bytes_per_row,
+            ..context.clone()
+        };
+        let offset = first_row - context_row;
+        
+        for row in (offset..local_context.height).rev() {
+            filter.reverse(&mut local, &FilterContext { row_index: row, ..local_context.clone() })?;
         }
         
+        block.copy_from_slice(&local[offset * bytes_per_row..]);
         Ok(())
     }
 }
@@ -390,8 +360,8 @@ impl SmartFilterSelector {
                     let left = data[idx - context.bytes_per_pixel];
                     let up = data[idx - bytes_per_row];
                     
-                    let edge_strength = ((current as i16 - left as i16).abs() + 
-                                        (current as i16 - up as i16).abs()) as u8;
+                    let edge_strength = clamp_u8(((current as i16 - left as i16).abs() + 
+                                        (current as i16 - up as i16).abs()) as i32);
                     
                     if edge_strength > 10 { // 阈值
                         edge_count += 1;
@@ -0,0 +1,413 @@
+//! 图像几何变换模块
+//! 对RGBA像素缓冲区进行翻转、旋转、缩放、合成等操作，以及16位样本的色调映射
+
+use wasm_bindgen::prelude::*;
+use crate::utils::{clamp_u8, clamp_u8_f64};
+
+/// 像素合成模式
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// 直接复制源像素
+    Copy = 0,
+    /// 源像素按alpha叠加在目标像素之上
+    SourceOver = 1,
+}
+
+/// 位块传输区域：源图像中的矩形，以及写入目标图像时的左上角位置
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlitRegion {
+    pub src_x: u32,
+    pub src_y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub delta_x: u32,
+    pub delta_y: u32,
+}
+
+#[wasm_bindgen]
+impl BlitRegion {
+    #[wasm_bindgen(constructor)]
+    pub fn new(src_x: u32, src_y: u32, width: u32, height: u32, delta_x: u32, delta_y: u32) -> Self {
+        Self { src_x, src_y, width, height, delta_x, delta_y }
+    }
+}
+
+/// 按合成模式将一个RGBA源像素写入目标像素（非预乘alpha）
+pub fn blend_pixel(src: &[u8], dst: &mut [u8], mode: BlendMode) {
+    if mode == BlendMode::Copy || src[3] == 255 {
+        dst[..4].copy_from_slice(&src[..4]);
+        return;
+    }
+    
+    let src_alpha = src[3] as u32;
+    let dst_alpha = dst[3] as u32;
+    // 结果alpha放大255倍以保留精度
+    let out_alpha = src_alpha * 255 + dst_alpha * (255 - src_alpha);
+    if out_alpha == 0 {
+        dst[..4].copy_from_slice(&[0, 0, 0, 0]);
+        return;
+    }
+    
+    for c in 0..3 {
+        let value = src[c] as u32 * src_alpha * 255 + dst[c] as u32 * dst_alpha * (255 - src_alpha);
+        dst[c] = ((value + out_alpha / 2) / out_alpha) as u8;
+    }
+    dst[3] = ((out_alpha + 127) / 255) as u8;
+}
+
+/// 将图像按cols列、rows行均分为网格，按行优先顺序返回每个格子的RGBA数据
+/// 宽高不能被列数、行数整除时报错
+pub fn slice_grid(data: &[u8], width: u32, height: u32, cols: u32, rows: u32) -> Result<Vec<Vec<u8>>, String> {
+    check_rgba_len(data, width, height)?;
+    if cols == 0 || rows == 0 {
+        return Err("Grid must have at least one column and row".to_string());
+    }
+    if width % cols != 0 || height % rows != 0 {
+        return Err(format!("{}x{} image cannot be divided into a {}x{} grid", width, height, cols, rows));
+    }
+    
+    let (cell_width, cell_height) = ((width / cols) as usize * 4, (height / rows) as usize);
+    let stride = width as usize * 4;
+    let mut cells = Vec::with_capacity(cols as usize * rows as usize);
+    for row in 0..rows as usize {
+        for col in 0..cols as usize {
+            let mut cell = Vec::with_capacity(cell_width * cell_height);
+            for y in row * cell_height..(row + 1) * cell_height {
+                let start = y * stride + col * cell_width;
+                cell.extend_from_slice(&data[start..start + cell_width]);
+            }
+            cells.push(cell);
+        }
+    }
+    Ok(cells)
+}
+
+/// 复制RGBA图像中从(x, y)开始的w x h矩形区域，按行拼接
+pub fn copy_region(data: &[u8], width: u32, height: u32, x: u32, y: u32, w: u32, h: u32) -> Result<Vec<u8>, String> {
+    check_rgba_len(data, width, height)?;
+    let fits = |start: u32, size: u32, limit: u32| start.checked_add(size).is_some_and(|end| end <= limit);
+    if !fits(x, w, width) || !fits(y, h, height) {
+        return Err(format!("Region {}x{} at ({}, {}) is outside the {}x{} image", w, h, x, y, width, height));
+    }
+    
+    let stride = width as usize * 4;
+    let row_bytes = w as usize * 4;
+    let mut region = Vec::with_capacity(row_bytes * h as usize);
+    for row in y as usize..(y + h) as usize {
+        let start = row * stride + x as usize * 4;
+        region.extend_from_slice(&data[start..start + row_bytes]);
+    }
+    Ok(region)
+}
+
+/// 色键抠图：每个通道与关键色相差不超过tolerance的像素alpha置为0，其余像素不变
+/// tolerance为0时只匹配完全相同的颜色；返回被抠除的像素数
+pub fn chroma_key(data: &mut [u8], key: [u8; 3], tolerance: u8) -> usize {
+    let mut keyed = 0;
+    for pixel in data.chunks_exact_mut(4) {
+        if pixel[..3].iter().zip(key).all(|(&value, key)| value.abs_diff(key) <= tolerance) {
+            pixel[3] = 0;
+            keyed += 1;
+        }
+    }
+    keyed
+}
+
+/// RGBA缓冲区的字节数 width * height * 4，在usize中做带溢出检查的乘法
+pub(crate) fn rgba_len(width: u32, height: u32) -> Result<usize, String> {
+    (width as usize).checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(4))
+        .ok_or_else(|| format!("Image size {}x{} overflows usize", width, height))
+}
+
+/// 校验RGBA缓冲区长度为 width * height * 4
+pub(crate) fn check_rgba_len(data: &[u8], width: u32, height: u32) -> Result<(), String> {
+    let expected = rgba_len(width, height)?;
+    if data.len() != expected {
+        return Err(format!("Expected {} bytes of RGBA data, got {}", expected, data.len()));
+    }
+    Ok(())
+}
+
+/// 羽化：只对alpha通道做半径为radius的盒式模糊，柔化遮罩边缘，RGB保持不变
+/// 边缘处窗口截断到图像范围内，只对范围内的像素取平均
+pub fn feather_alpha(data: &mut [u8], width: u32, height: u32, radius: u32) -> Result<(), String> {
+    check_rgba_len(data, width, height)?;
+    if radius == 0 || data.is_empty() {
+        return Ok(());
+    }
+    
+    let (width, height, radius) = (width as usize, height as usize, radius as usize);
+    let mut alpha: Vec<u8> = data.chunks_exact(4).map(|pixel| pixel[3]).collect();
+    
+    // 可分离：先逐行、再逐列模糊
+    for row in alpha.chunks_exact_mut(width) {
+        let blurred = box_blur_line(row, radius);
+        row.copy_from_slice(&blurred);
+    }
+    for x in 0..width {
+        let column: Vec<u8> = (0..height).map(|y| alpha[y * width + x]).collect();
+        for (y, value) in box_blur_line(&column, radius).into_iter().enumerate() {
+            alpha[y * width + x] = value;
+        }
+    }
+    
+    for (pixel, value) in data.chunks_exact_mut(4).zip(alpha) {
+        pixel[3] = value;
+    }
+    Ok(())
+}
+
+/// 一维盒式模糊，使用前缀和使耗时与半径无关
+fn box_blur_line(values: &[u8], radius: usize) -> Vec<u8> {
+    let mut prefix = Vec::with_capacity(values.len() + 1);
+    prefix.push(0u32);
+    for &value in values {
+        prefix.push(prefix[prefix.len() - 1] + value as u32);
+    }
+    
+    (0..values.len())
+        .map(|i| {
+            let start = i.saturating_sub(radius);
+            let end = (i + radius + 1).min(values.len());
+            let count = (end - start) as u32;
+            ((prefix[end] - prefix[start] + count / 2) / count) as u8
+        })
+        .collect()
+}
+
+/// 调整亮度：每个像素的RGB通道加上delta并截断到0..=255，alpha不变
+pub fn adjust_brightness(data: &mut [u8], delta: i16) {
+    for pixel in data.chunks_exact_mut(4) {
+        for value in &mut pixel[..3] {
+            *value = clamp_u8(*value as i32 + delta as i32);
+        }
+    }
+}
+
+/// 调整对比度：RGB通道以128为中心按factor缩放并截断到0..=255，alpha不变
+pub fn adjust_contrast(data: &mut [u8], factor: f64) {
+    for pixel in data.chunks_exact_mut(4) {
+        for value in &mut pixel[..3] {
+            *value = clamp_u8_f64((*value as f64 - 128.0) * factor + 128.0);
+        }
+    }
+}
+
+/// 色调分离：将RGB通道均分为levels个区间，每个区间映射到0..=255中均匀分布的一个值
+pub fn posterize(data: &mut [u8], levels: u8) -> Result<(), String> {
+    if levels < 2 {
+        return Err(format!("Posterize requires at least 2 levels, got {}", levels));
+    }
+    
+    let levels = levels as u32;
+    let lut: Vec<u8> = (0..=255u32)
+        .map(|value| (value * levels / 256 * 255 / (levels - 1)) as u8)
+        .collect();
+    for pixel in data.chunks_exact_mut(4) {
+        for value in &mut pixel[..3] {
+            *value = lut[*value as usize];
+        }
+    }
+    Ok(())
+}
+
+/// 16位到8位的色调映射方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMap {
+    /// 取高8位（value >> 8），与默认解码的16位降级结果一致
+    Linear,
+    /// 将实际出现的最小/最大值拉伸到0/255
+    MinMaxStretch,
+    /// 归一化后按 1/gamma 指数校正
+    Gamma(f64),
+}
+
+/// 色调映射模式（JS接口），Gamma模式的指数单独传入
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapMode {
+    Linear = 0,
+    MinMaxStretch = 1,
+    Gamma = 2,
+}
+
+/// 将16位样本映射为8位样本
+/// channels为每像素样本数，has_alpha为true时最后一个通道（alpha）始终取高8位
+pub fn tone_map_16_to_8(samples: &[u16], channels: usize, has_alpha: bool, mode: ToneMap) -> Vec<u8> {
+    let channels = channels.max(1);
+    let is_alpha = |index: usize| has_alpha && index % channels == channels - 1;
+    let linear = |value: u16| (value >> 8) as u8;
+    
+    // 颜色通道实际出现的取值范围
+    let (min, max) = samples.iter().enumerate()
+        .filter(|&(i, _)| !is_alpha(i))
+        .fold((u16::MAX, u16::MIN), |(min, max), (_, &value)| (min.min(value), max.max(value)));
+    
+    samples.iter().enumerate().map(|(i, &value)| {
+        if is_alpha(i) {
+            return linear(value);
+        }
+        match mode {
+            ToneMap::Linear => linear(value),
+            ToneMap::MinMaxStretch if max > min => {
+                let range = (max - min) as u32;
+                (((value - min) as u32 * 255 + range / 2) / range) as u8
+            }
+            ToneMap::MinMaxStretch => linear(value),
+            ToneMap::Gamma(gamma) if gamma > 0.0 => {
+                ((value as f64 / 65535.0).powf(1.0 / gamma) * 255.0).round() as u8
+            }
+            ToneMap::Gamma(_) => linear(value),
+        }
+    }).collect()
+}
+
+/// 按目标坐标到源坐标的映射重排像素，输出与输入像素数相同，索引在usize中计算
+fn remap<F>(data: &[u8], out_width: u32, out_height: u32, src_width: u32, source: F) -> Vec<u8>
+where
+    F: Fn(u32, u32) -> (u32, u32),
+{
+    let mut output = Vec::with_capacity(data.len());
+
+    for y in 0..out_height {
+        for x in 0..out_width {
+            let (sx, sy) = source(x, y);
+            let index = (sy as usize * src_width as usize + sx as usize) * 4;
+            output.extend_from_slice(&data[index..index + 4]);
+        }
+    }
+
+    output
+}
+
+/// 水平翻转
+pub fn flip_horizontal(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    remap(data, width, height, width, |x, y| (width - 1 - x, y))
+}
+
+/// 垂直翻转
+pub fn flip_vertical(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    remap(data, width, height, width, |x, y| (x, height - 1 - y))
+}
+
+/// 旋转180度
+pub fn rotate_180(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    remap(data, width, height, width, |x, y| (width - 1 - x, height - 1 - y))
+}
+
+/// 顺时针旋转90度，输出尺寸为 height x width
+pub fn rotate_90_cw(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    remap(data, height, width, width, |x, y| (y, height - 1 - x))
+}
+
+/// 逆时针旋转90度，输出尺寸为 height x width
+pub fn rotate_90_ccw(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    remap(data, height, width, width, |x, y| (width - 1 - y, x))
+}
+
+/// 沿主对角线转置，输出尺寸为 height x width
+pub fn transpose(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    remap(data, height, width, width, |x, y| (y, x))
+}
+
+/// 沿副对角线转置，输出尺寸为 height x width
+pub fn transverse(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    remap(data, height, width, width, |x, y| (width - 1 - y, height - 1 - x))
+}
+
+/// 按EXIF方向值（1-8）将图像转为正常显示方向
+/// 返回变换后的像素和新的 (宽, 高)
+pub fn apply_orientation(data: &[u8], width: u32, height: u32, orientation: u16) -> Result<(Vec<u8>, u32, u32), String> {
+    check_rgba_len(data, width, height)?;
+
+    let result = match orientation {
+        1 => (data.to_vec(), width, height),
+        2 => (flip_horizontal(data, width, height), width, height),
+        3 => (rotate_180(data, width, height), width, height),
+        4 => (flip_vertical(data, width, height), width, height),
+        5 => (transpose(data, width, height), height, width),
+        6 => (rotate_90_cw(data, width, height), height, width),
+        7 => (transverse(data, width, height), height, width),
+        8 => (rotate_90_ccw(data, width, height), height, width),
+        _ => return Err(format!("Invalid EXIF orientation: {}", orientation)),
+    };
+
+    Ok(result)
+}
+
+/// sRGB编码值到线性光强度的查找表
+fn srgb_to_linear_lut() -> [f32; 256] {
+    let mut lut = [0.0; 256];
+    for (i, value) in lut.iter_mut().enumerate() {
+        let c = i as f32 / 255.0;
+        *value = if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
+    }
+    lut
+}
+
+/// 线性光强度转回sRGB编码值
+fn linear_to_srgb(value: f32) -> u8 {
+    let c = value.clamp(0.0, 1.0);
+    let encoded = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (encoded * 255.0).round() as u8
+}
+
+/// 双线性缩放RGBA图像
+/// gamma_correct为true时颜色通道先转换到线性空间再插值，alpha始终线性插值
+pub fn resize(data: &[u8], width: u32, height: u32, new_width: u32, new_height: u32, gamma_correct: bool) -> Result<Vec<u8>, String> {
+    check_rgba_len(data, width, height)?;
+    let output_len = rgba_len(new_width, new_height)?;
+    if width == 0 || height == 0 || new_width == 0 || new_height == 0 {
+        return Err("Image dimensions must be non-zero".to_string());
+    }
+
+    let lut = srgb_to_linear_lut();
+    let to_linear = |value: u8, channel: usize| -> f32 {
+        if gamma_correct && channel < 3 { lut[value as usize] } else { value as f32 / 255.0 }
+    };
+    let from_linear = |value: f32, channel: usize| -> u8 {
+        if gamma_correct && channel < 3 { linear_to_srgb(value) } else { (value.clamp(0.0, 1.0) * 255.0).round() as u8 }
+    };
+
+    // 目标像素中心映射回源图像坐标
+    let scale_x = width as f32 / new_width as f32;
+    let scale_y = height as f32 / new_height as f32;
+    let mut output = Vec::with_capacity(output_len);
+
+    for y in 0..new_height {
+        let sy = ((y as f32 + 0.5) * scale_y - 0.5).clamp(0.0, (height - 1) as f32);
+        let y0 = sy.floor() as u32;
+        let y1 = (y0 + 1).min(height - 1);
+        let fy = sy - y0 as f32;
+
+        for x in 0..new_width {
+            let sx = ((x as f32 + 0.5) * scale_x - 0.5).clamp(0.0, (width - 1) as f32);
+            let x0 = sx.floor() as u32;
+            let x1 = (x0 + 1).min(width - 1);
+            let fx = sx - x0 as f32;
+
+            for channel in 0..4 {
+                let sample = |px: u32, py: u32| to_linear(data[(py as usize * width as usize + px as usize) * 4 + channel], channel);
+                let top = sample(x0, y0) * (1.0 - fx) + sample(x1, y0) * fx;
+                let bottom = sample(x0, y1) * (1.0 - fx) + sample(x1, y1) * fx;
+                output.push(from_linear(top * (1.0 - fy) + bottom * fy, channel));
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// 保持宽高比缩放到最长边等于max_dimension后的尺寸，较短边四舍五入且至少为1
+pub fn fit_within(width: u32, height: u32, max_dimension: u32) -> Result<(u32, u32), String> {
+    if width == 0 || height == 0 || max_dimension == 0 {
+        return Err("Image dimensions must be non-zero".to_string());
+    }
+
+    let longest = width.max(height) as u64;
+    let scale = |dimension: u32| ((dimension as u64 * max_dimension as u64 + longest / 2) / longest).max(1) as u32;
+    Ok((scale(width), scale(height)))
+}
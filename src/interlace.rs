@@ -97,15 +97,22 @@ const ADAM7_PATTERN: [[[u8; 8]; 8]; 7] = [
     ],
 ];
 
-/// 获取所有交错通道信息
+/// 获取所有交错通道信息（data_size按每像素8位计算）
 pub fn get_interlace_passes(width: u32, height: u32) -> Vec<InterlacePass> {
+    get_interlace_passes_with_bpp(width, height, 8)
+}
+
+/// 获取所有交错通道信息
+/// data_size为该通道在解压数据流中占用的字节数，包含每行的滤镜字节
+pub fn get_interlace_passes_with_bpp(width: u32, height: u32, bits_per_pixel: usize) -> Vec<InterlacePass> {
     let mut passes = Vec::new();
     
     for pass in 0..7 {
         let (pass_width, pass_height) = get_interlace_pass_size(width, height, pass);
         if pass_width > 0 && pass_height > 0 {
             let (x_offset, y_offset, x_step, y_step) = get_interlace_offsets(pass);
-            let data_size = (pass_width * pass_height) as usize;
+            let pass_stride = (pass_width as usize * bits_per_pixel + 7) / 8;
+            let data_size = pass_height as usize * (1 + pass_stride);
             passes.push(InterlacePass {
                 pass,
                 width: pass_width,
@@ -122,18 +129,9 @@ pub fn get_interlace_passes(width: u32, height: u32) -> Vec<InterlacePass> {
     passes
 }
 
-/// 获取交错通道的偏移量和步长
+/// 获取交错通道的偏移量和步长，见 [`ADAM7_OFFSETS`]
 fn get_interlace_offsets(pass: usize) -> (u32, u32, u32, u32) {
-    match pass {
-        0 => (0, 0, 8, 8),  // pass 1: 1px
-        1 => (4, 0, 8, 8),  // pass 2: 1px
-        2 => (0, 4, 4, 8),  // pass 3: 2px
-        3 => (2, 0, 4, 4),  // pass 4: 4px
-        4 => (0, 2, 2, 4),  // pass 5: 8px
-        5 => (1, 0, 2, 2),  // pass 6: 16px
-        6 => (0, 1, 1, 2),  // pass 7: 32px
-        _ => (0, 0, 1, 1),
-    }
+    ADAM7_OFFSETS.get(pass).copied().unwrap_or((0, 0, 1, 1))
 }
 
 /// 检查像素是否属于指定通道
@@ -202,6 +200,35 @@ pub fn deinterlace_passes(passes: &[Vec<u8>], width: u32, height: u32, bytes_per
     result
 }
 
+/// 将一个交错通道的像素按位写回完整图像（支持1/2/4位等不足1字节的像素）
+/// pass_pixels和output均为按字节对齐的扫描线，不含滤镜字节
+pub fn scatter_pass_pixels(pass_pixels: &[u8], pass: &InterlacePass, output: &mut [u8], output_stride: usize, bits_per_pixel: usize) {
+    let pass_stride = (pass.width as usize * bits_per_pixel + 7) / 8;
+    
+    for py in 0..pass.height as usize {
+        let src_row = &pass_pixels[py * pass_stride..(py + 1) * pass_stride];
+        let y = pass.y_offset as usize + py * pass.y_step as usize;
+        let dst_row = &mut output[y * output_stride..(y + 1) * output_stride];
+        
+        for px in 0..pass.width as usize {
+            let x = pass.x_offset as usize + px * pass.x_step as usize;
+            
+            if bits_per_pixel >= 8 {
+                let bytes = bits_per_pixel / 8;
+                dst_row[x * bytes..(x + 1) * bytes].copy_from_slice(&src_row[px * bytes..(px + 1) * bytes]);
+            } else {
+                let mask = ((1u16 << bits_per_pixel) - 1) as u8;
+                let src_bit = px * bits_per_pixel;
+                let dst_bit = x * bits_per_pixel;
+                let src_shift = 8 - bits_per_pixel - src_bit % 8;
+                let dst_shift = 8 - bits_per_pixel - dst_bit % 8;
+                let value = (src_row[src_bit / 8] >> src_shift) & mask;
+                dst_row[dst_bit / 8] = (dst_row[dst_bit / 8] & !(mask << dst_shift)) | (value << dst_shift);
+            }
+        }
+    }
+}
+
 /// 将完整图像分解为交错通道
 pub fn interlace_image(image_data: &[u8], width: u32, height: u32, bytes_per_pixel: usize) -> Vec<Vec<u8>> {
     let mut passes = Vec::new();
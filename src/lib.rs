@@ -1,54 +1,142 @@
 //! Rust PNG处理库
 //! 完全兼容原始pngjs库的API
+//! 关闭默认的std特性时以no_std + alloc编译，只提供png_core中的纯解码逻辑
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+// 宿主平台上cdylib仍需std提供panic处理和全局分配器，代码本身不使用std
+#[cfg(all(not(feature = "std"), not(target_os = "none")))]
+extern crate std;
+
+#[cfg(feature = "std")]
 use wasm_bindgen::prelude::*;
 
 // 模块声明
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
 mod constants;
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
 mod crc;
+pub mod png_core;
+#[cfg(feature = "std")]
 mod filter;
+#[cfg(feature = "std")]
 mod filter_extensible;
+#[cfg(feature = "std")]
 mod custom_filters;
+#[cfg(feature = "std")]
 mod filter_optimizer;
+#[cfg(feature = "std")]
 mod bitmap;
+#[cfg(feature = "std")]
 mod bitmapper;
+#[cfg(feature = "std")]
 mod utils;
+#[cfg(feature = "std")]
 mod interlace;
+#[cfg(feature = "std")]
+mod image_ops;
+#[cfg(feature = "std")]
 mod png_packer;
+#[cfg(feature = "std")]
 mod png_chunks;
+#[cfg(feature = "std")]
 mod filter_pack;
+#[cfg(feature = "std")]
 mod sync_inflate;
+// 依赖rayon，仅在parallel特性下编译
+#[cfg(all(feature = "parallel", feature = "std"))]
 mod performance;
+#[cfg(feature = "std")]
 mod chunk_stream;
+#[cfg(feature = "std")]
 mod error_handling;
+#[cfg(feature = "std")]
 mod testing;
+#[cfg(feature = "std")]
 mod advanced_png;
+#[cfg(feature = "std")]
 mod wasm_optimization;
+#[cfg(feature = "std")]
 mod advanced_filters;
+#[cfg(feature = "std")]
 mod png;
+#[cfg(feature = "std")]
 mod png_structures;
+#[cfg(feature = "std")]
 mod png_semantic;
 
 // 重新导出主要类型
+#[cfg(feature = "std")]
 pub use png::{PNG, PNGSync};
+#[cfg(feature = "std")]
 pub use png_semantic::{SemanticPNG, SemanticPNGSync};
+#[cfg(feature = "std")]
+pub use image_ops::{BlendMode, BlitRegion, ToneMapMode};
 
 // 当模块被加载时调用
+#[cfg(feature = "std")]
 #[wasm_bindgen(start)]
 pub fn main() {
     console_error_panic_hook::set_once();
 }
 
 // 导出函数用于从JavaScript调用（兼容原始pngjs API）
+#[cfg(feature = "std")]
 #[wasm_bindgen]
 pub fn create_png() -> PNG {
     PNG::new(None)
 }
 
 // 兼容性函数 - 创建并解析PNG
+#[cfg(feature = "std")]
 #[wasm_bindgen]
 pub fn create_png_from_data(data: &[u8]) -> Result<PNG, JsValue> {
     let mut png = PNG::new(None);
     png.parse(data, None)?;
     Ok(png)
-}
\ No newline at end of file
+}
+
+// Adam7七个pass的几何信息 - 不依赖已解析的图像，可用于在JS中实现渐进式渲染
+// 每项为 { width, height, xOffset, yOffset, xStep, yStep, byteSize }，byteSize按8位RGBA计算
+#[cfg(feature = "std")]
+#[wasm_bindgen]
+pub fn adam7_pass_geometry(width: u32, height: u32) -> js_sys::Array {
+    let passes = js_sys::Array::new();
+    for pass in png_structures::InterlaceInfo::adam7_passes(width, height) {
+        let obj = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&obj, &"width".into(), &pass.width.into());
+        let _ = js_sys::Reflect::set(&obj, &"height".into(), &pass.height.into());
+        let _ = js_sys::Reflect::set(&obj, &"xOffset".into(), &pass.x_offset.into());
+        let _ = js_sys::Reflect::set(&obj, &"yOffset".into(), &pass.y_offset.into());
+        let _ = js_sys::Reflect::set(&obj, &"xStep".into(), &pass.x_step.into());
+        let _ = js_sys::Reflect::set(&obj, &"yStep".into(), &pass.y_step.into());
+        let _ = js_sys::Reflect::set(&obj, &"byteSize".into(), &(pass.byte_size as u32).into());
+        passes.push(&obj);
+    }
+    passes
+}
+
+// 批量解析 - 一次调用解析多个PNG，单个文件失败不影响其余文件
+// 按输入顺序返回PNG对象，失败的文件返回 { error } 对象
+#[cfg(feature = "std")]
+#[wasm_bindgen]
+pub fn decode_batch(files: &js_sys::Array) -> js_sys::Array {
+    let results = js_sys::Array::new();
+    for file in files.iter() {
+        let data = js_sys::Uint8Array::new(&file).to_vec();
+        match create_png_from_data(&data) {
+            Ok(png) => {
+                results.push(&JsValue::from(png));
+            }
+            Err(e) => {
+                let message = e.as_string().unwrap_or_else(|| "Failed to decode PNG".to_string());
+                let error = js_sys::Object::new();
+                let _ = js_sys::Reflect::set(&error, &"error".into(), &message.into());
+                results.push(&error);
+            }
+        }
+    }
+    results
+}
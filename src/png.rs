@@ -3,6 +3,8 @@
 
 use wasm_bindgen::prelude::*;
 use js_sys::{Array, Uint8Array, Uint8ClampedArray};
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use png::{Decoder, ColorType, BitDepth, Transformations};
 use std::io::Cursor;
@@ -16,9 +18,12 @@ use crate::png_chunks::*;
 use crate::filter_pack::*;
 use crate::sync_inflate::*;
 use crate::bitmapper::*;
+use crate::image_ops::{adjust_brightness, adjust_contrast, apply_orientation, blend_pixel, check_rgba_len, chroma_key, copy_region, feather_alpha, fit_within, posterize, resize, rgba_len, slice_grid, tone_map_16_to_8, BlendMode, BlitRegion, ToneMap, ToneMapMode};
+use crate::error_handling::{check_png_signature, ErrorValidator};
 
 /// PNG结构体 - 匹配原始pngjs库的PNG类
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct PNG {
     width: u32,
     height: u32,
@@ -43,23 +48,75 @@ pub struct PNG {
     bpp: u8, // bytes per pixel
     depth: u8, // bit depth
     chunk_parser: PNGChunkParser,
+    // 16位图像的原始精度样本
+    keep_native_depth: bool,
+    native_data: Option<Vec<u16>>,
+    // 部分解码：数据截断时保留已解码的行，其余填充partial_fill
+    partial: bool,
+    partial_fill: [u8; 4],
+    decoded_rows: u32,
+    // 调色板图像的索引（每像素一字节）和调色板alpha
+    indices: Option<Vec<u8>>,
+    palette_alpha: Option<Vec<u8>>,
+    // 解码时展开调色板和低位深度样本（png crate的EXPAND）；关闭时pixel_data保持原始格式
+    expand: bool,
+    // 解码器实际输出的样本位深度
+    delivered_bit_depth: u8,
+    // 转换为RGBA后保留解码器输出的原始样本（pixel_data），默认释放以节省内存
+    keep_raw: bool,
+    // 灰度图像只保存8位灰度（或灰度+Alpha）样本，读取时再展开为RGBA
+    compact_grayscale: bool,
+    gray_data: Option<Vec<u8>>,
+    // 紧凑灰度缓冲区每像素的通道数（灰度为1，灰度+Alpha为2），解析时确定
+    gray_channels: u8,
+    // 最近一次parse的输入数据，reoptimize无法缩小时原样返回
+    source_data: Option<Vec<u8>>,
+    // 有损操作的记录，按发生顺序；只读的导出方法也会记录，因此使用RefCell
+    warnings: RefCell<Vec<String>>,
 }
 
 #[wasm_bindgen]
 impl PNG {
     /// 构造函数 - 匹配原始pngjs库
+    /// 指定尺寸时用fillColor [r, g, b, a]（缺省分量为0，alpha缺省为255）填充画布；
+    /// 只设置fill: true时填充不透明黑色，两者都未设置时为全透明
     #[wasm_bindgen(constructor)]
     pub fn new(options: Option<JsValue>) -> PNG {
         let mut width = 0;
         let mut height = 0;
-        let mut fill = false;
+        let mut fill_color = None;
+        let mut keep_native_depth = false;
+        let mut partial = false;
+        let mut partial_fill = [0, 0, 0, 0];
+        let mut expand = true;
+        let mut compact_grayscale = false;
+        let mut keep_raw = false;
         
         // 解析选项
         if let Some(opts) = options {
             if let Ok(parsed) = serde_wasm_bindgen::from_value::<serde_json::Value>(opts) {
                 width = parsed.get("width").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
                 height = parsed.get("height").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
-                fill = parsed.get("fill").and_then(|v| v.as_bool()).unwrap_or(false);
+                if parsed.get("fill").and_then(|v| v.as_bool()).unwrap_or(false) {
+                    fill_color = Some([0, 0, 0, 255]);
+                }
+                if let Some(color) = parsed.get("fillColor").and_then(|v| v.as_array()) {
+                    let mut rgba = [0, 0, 0, 255];
+                    for (i, value) in color.iter().take(4).enumerate() {
+                        rgba[i] = value.as_u64().unwrap_or(0).min(255) as u8;
+                    }
+                    fill_color = Some(rgba);
+                }
+                keep_native_depth = parsed.get("keepNativeDepth").and_then(|v| v.as_bool()).unwrap_or(false);
+                partial = parsed.get("partial").and_then(|v| v.as_bool()).unwrap_or(false);
+                expand = parsed.get("expand").and_then(|v| v.as_bool()).unwrap_or(true);
+                compact_grayscale = parsed.get("compactGrayscale").and_then(|v| v.as_bool()).unwrap_or(false);
+                keep_raw = parsed.get("keepRaw").and_then(|v| v.as_bool()).unwrap_or(false);
+                if let Some(color) = parsed.get("partialFill").and_then(|v| v.as_array()) {
+                    for (i, value) in color.iter().take(4).enumerate() {
+                        partial_fill[i] = value.as_u64().unwrap_or(0).min(255) as u8;
+                    }
+                }
             }
         }
         
@@ -67,8 +124,10 @@ impl PNG {
         if width > 0 && height > 0 {
             let data_size = (4 * width * height) as usize;
             let mut data = vec![0; data_size];
-            if fill {
-                data.fill(0);
+            if let Some(color) = fill_color {
+                for pixel in data.chunks_exact_mut(4) {
+                    pixel.copy_from_slice(&color);
+                }
             }
             rgba_data = Some(data);
         }
@@ -96,21 +155,53 @@ impl PNG {
             bpp: 4, // RGBA = 4 bytes per pixel
             depth: 8,
             chunk_parser: PNGChunkParser::new(),
+            keep_native_depth,
+            native_data: None,
+            partial,
+            partial_fill,
+            decoded_rows: 0,
+            indices: None,
+            palette_alpha: None,
+            expand,
+            delivered_bit_depth: 8,
+            keep_raw,
+            compact_grayscale,
+            gray_data: None,
+            gray_channels: 1,
+            source_data: None,
+            warnings: RefCell::new(Vec::new()),
         }
     }
 
+    /// 由RGBA 8位数据创建图像，长度必须等于 width * height * 4，可直接pack
+    #[wasm_bindgen]
+    pub fn from_rgba(width: u32, height: u32, data: &[u8]) -> Result<PNG, JsValue> {
+        let expected = (width as usize).checked_mul(height as usize).and_then(|pixels| pixels.checked_mul(4));
+        if expected != Some(data.len()) {
+            return Err(JsValue::from_str(&format!(
+                "RGBA data length {} does not match {}x{}", data.len(), width, height
+            )));
+        }
+        Ok(PNG::from_rgba8(width, height, data.to_vec()))
+    }
+
     /// 解析PNG数据 - 匹配原始pngjs库的parse方法
     #[wasm_bindgen]
     pub fn parse(&mut self, data: &[u8], callback: Option<js_sys::Function>) -> Result<(), JsValue> {
         console_log!("Parsing PNG data with length: {}", data.len());
         
-        // 验证PNG签名
-        if !validate_png_signature(data) {
-            return Err(JsValue::from_str("Invalid PNG signature"));
+        // 验证PNG签名，其他图像格式给出具体提示
+        check_png_signature(data).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        
+        // 解析chunk结构，保留辅助chunk供重新编码使用
+        let mut chunk_parser = PNGChunkParser::new().with_lenient(true);
+        if chunk_parser.parse(data).is_ok() {
+            self.chunk_parser = chunk_parser;
+            self.source_data = Some(data.to_vec());
         }
         
         let mut decoder = Decoder::new(Cursor::new(data));
-        decoder.set_transformations(Transformations::EXPAND);
+        decoder.set_transformations(if self.expand { Transformations::EXPAND } else { Transformations::IDENTITY });
         
         let mut reader = match decoder.read_info() {
             Ok(reader) => reader,
@@ -133,6 +224,8 @@ impl PNG {
         // 设置每像素字节数
         self.bpp = get_bytes_per_pixel(self.color_type) as u8;
         self.depth = self.bit_depth;
+        let (output_color, output_depth) = reader.output_color_type();
+        self.delivered_bit_depth = output_depth as u8;
         
         // 处理调色板
         if let Some(palette) = info.palette() {
@@ -140,6 +233,9 @@ impl PNG {
         }
         
         // 处理透明度
+        if self.color_type == COLORTYPE_PALETTE_COLOR {
+            self.palette_alpha = info.trns.as_ref().map(|trns| trns.to_vec());
+        }
         if let Some(trns) = info.trns() {
             self.trans_color = Some(trns.iter().map(|&x| x as u16).collect());
             self.alpha = true;
@@ -151,20 +247,69 @@ impl PNG {
         }
         
         // 读取像素数据
+        self.gray_data = None;
+        let compact = self.compact_grayscale && self.expand
+            && matches!(output_color, ColorType::Grayscale | ColorType::GrayscaleAlpha);
         let mut buffer = vec![0; reader.output_buffer_size()];
         match reader.next_frame(&mut buffer) {
+            Ok(_) if compact => {
+                // 紧凑灰度：8位样本直接作为灰度缓冲区，16位取高字节
+                self.gray_channels = if output_color == ColorType::GrayscaleAlpha { 2 } else { 1 };
+                if self.delivered_bit_depth == 16 {
+                    self.gray_data = Some(buffer.chunks_exact(2).map(|sample| sample[0]).collect());
+                    self.warnings.borrow_mut().push("Reduced 16-bit grayscale samples to 8 bits".to_string());
+                    self.native_data = self.keep_native_depth.then(|| to_native_16(&buffer));
+                    self.pixel_data = self.keep_raw.then_some(buffer);
+                } else {
+                    self.gray_data = Some(buffer);
+                    self.pixel_data = None;
+                    self.native_data = None;
+                }
+                self.rgba_data = None;
+                self.indices = None;
+                self.decoded_rows = self.height;
+                self.has_ihdr = true;
+                self.has_iend = true;
+                
+                if let Some(cb) = callback {
+                    let _ = cb.call0(&JsValue::null());
+                }
+            }
             Ok(_) => {
-                // 转换为RGBA格式
-                self.rgba_data = Some(convert_to_rgba(
-                    &buffer,
-                    self.width as usize,
-                    self.height as usize,
-                    self.color_type,
-                    self.bit_depth,
-                    self.palette.as_deref()
-                ));
+                // 转换为RGBA格式：EXPAND的输出已是8/16位的非调色板样本
+                self.rgba_data = Some(if self.expand {
+                    convert_to_rgba(
+                        &buffer,
+                        self.width as usize,
+                        self.height as usize,
+                        output_color as u8,
+                        self.delivered_bit_depth,
+                        None
+                    )
+                } else {
+                    self.raw_to_rgba(&buffer, self.height as usize)
+                });
+                
+                // 保留16位原始样本
+                self.native_data = if self.keep_native_depth && self.bit_depth == 16 {
+                    Some(to_native_16(&buffer))
+                } else {
+                    None
+                };
                 
-                self.pixel_data = Some(buffer);
+                // 保留调色板索引以便修改调色板后重新渲染；未展开时直接使用解码器输出
+                self.indices = if self.color_type != COLORTYPE_PALETTE_COLOR {
+                    None
+                } else if self.expand {
+                    self.chunk_parser.decode_pixels().ok().map(|raw| {
+                        unpack_indices(&raw, self.width as usize, self.height as usize, self.bit_depth)
+                    })
+                } else {
+                    Some(unpack_indices(&buffer, self.width as usize, self.height as usize, self.bit_depth))
+                };
+                
+                self.pixel_data = self.keep_raw.then_some(buffer);
+                self.decoded_rows = self.height;
                 self.has_ihdr = true;
                 self.has_iend = true;
                 
@@ -176,6 +321,14 @@ impl PNG {
                     let _ = cb.call0(&JsValue::null());
                 }
             }
+            Err(e) if self.partial => {
+                console_log!("Error reading PNG frame, decoding partially: {:?}", e);
+                self.parse_partial()?;
+                
+                if let Some(cb) = callback {
+                    let _ = cb.call0(&JsValue::null());
+                }
+            }
             Err(e) => {
                 console_log!("Error reading PNG frame: {:?}", e);
                 return Err(JsValue::from_str(&format!("Failed to read PNG frame: {}", e)));
@@ -185,22 +338,99 @@ impl PNG {
         Ok(())
     }
 
+    /// 解码为RGBA并写入调用方提供的缓冲区（至少width*height*4字节），返回图像尺寸 [width, height]
+    /// 像素数据不经过中间缓冲区，适合逐帧解码相同尺寸的图像
+    #[wasm_bindgen]
+    pub fn decode_into(data: &[u8], out: &mut [u8]) -> Result<Array, JsValue> {
+        let mut decoder = Decoder::new(Cursor::new(data));
+        decoder.set_transformations(Transformations::ALPHA | Transformations::STRIP_16);
+        let mut reader = decoder.read_info()
+            .map_err(|e| JsValue::from_str(&format!("Failed to read PNG info: {}", e)))?;
+        
+        let (width, height) = reader.info().size();
+        // 允许更大的缓冲区，所需字节数做带溢出检查的计算
+        let needed = rgba_len(width, height).map_err(|e| JsValue::from_str(&e))?;
+        if out.len() < needed {
+            return Err(JsValue::from_str(&format!("Output buffer too small: need {} bytes, got {}", needed, out.len())));
+        }
+        let pixels = needed / 4;
+        
+        // 输出为RGBA或灰度+Alpha（8位）
+        let (output_color, _) = reader.output_color_type();
+        let size = reader.output_buffer_size();
+        reader.next_frame(&mut out[..size])
+            .map_err(|e| JsValue::from_str(&format!("Failed to read PNG frame: {}", e)))?;
+        
+        if output_color == ColorType::GrayscaleAlpha {
+            // 从后向前原地展开，避免覆盖尚未读取的数据
+            for i in (0..pixels).rev() {
+                let (gray, alpha) = (out[i * 2], out[i * 2 + 1]);
+                out[i * 4..i * 4 + 4].copy_from_slice(&[gray, gray, gray, alpha]);
+            }
+        }
+        
+        let size = Array::new();
+        size.push(&width.into());
+        size.push(&height.into());
+        Ok(size)
+    }
+
     /// 打包PNG数据 - 匹配原始pngjs库的pack方法
+    /// on_progress以已处理扫描线的比例（0.0-1.0）调用，约每2%一次
     #[wasm_bindgen]
-    pub fn pack(&self) -> Result<Vec<u8>, JsValue> {
-        if let Some(ref data) = self.rgba_data {
+    pub fn pack(&self, on_progress: Option<js_sys::Function>) -> Result<Vec<u8>, JsValue> {
+        let rgb;
+        // RGB图像解析到的tRNS关键色原样写回，关键色像素的alpha由它表示
+        let transparency = match &self.chunk_parser.transparency {
+            Some(trns @ TRNSData::RGB { .. }) if self.color_type == COLORTYPE_COLOR => Some(trns.clone()),
+            _ => None,
+        };
+        let packed = match (&self.rgba_data, &self.gray_data) {
+            // 去掉冗余alpha后按RGB写出
+            (Some(data), _) if self.color_type == COLORTYPE_COLOR && self.bit_depth == 8 => {
+                let keyed = |pixel: &[u8]| match &transparency {
+                    Some(TRNSData::RGB { r, g, b }) => pixel[3] == 0
+                        && [pixel[0] as u16, pixel[1] as u16, pixel[2] as u16] == [*r, *g, *b],
+                    _ => false,
+                };
+                if data.chunks_exact(4).any(|pixel| pixel[3] != 255 && !keyed(pixel)) {
+                    self.warnings.borrow_mut().push("Dropped alpha channel during RGB conversion".to_string());
+                }
+                rgb = rgba_to_rgb(data, None);
+                Some((&rgb[..], self.color_type, self.bit_depth))
+            }
+            (Some(data), _) => Some((&data[..], self.color_type, self.bit_depth)),
+            // 紧凑灰度直接写出8位灰度（或灰度+Alpha）样本
+            (None, Some(gray)) => Some((&gray[..], self.gray_color_type(), 8)),
+            (None, None) => None,
+        };
+        
+        if let Some((data, color_type, bit_depth)) = packed {
             let options = PackerOptions {
                 width: self.width,
                 height: self.height,
-                bit_depth: self.bit_depth,
-                color_type: self.color_type,
-                input_color_type: self.color_type,
+                bit_depth,
+                color_type,
+                input_color_type: color_type,
                 input_has_alpha: self.alpha,
+                // 原样保留EXIF、oFFs和sRGB渲染意图
+                chunks: [ChunkType::EXIF, ChunkType::OFFS, ChunkType::SRGB].iter()
+                    .filter_map(|chunk_type| self.chunk_parser.get_chunks(chunk_type))
+                    .flatten()
+                    .cloned()
+                    .collect(),
+                transparency,
                 ..Default::default()
             };
             
             let packer = PNGPacker::new(options);
-            match packer.pack(data) {
+            let result = match on_progress {
+                Some(callback) => packer.pack_with_progress(data, |fraction| {
+                    let _ = callback.call1(&JsValue::null(), &JsValue::from_f64(fraction));
+                }),
+                None => packer.pack(data),
+            };
+            match result {
                 Ok(packed_data) => Ok(packed_data),
                 Err(e) => Err(JsValue::from_str(&e)),
             }
@@ -209,6 +439,200 @@ impl PNG {
         }
     }
 
+    /// 去掉APNG动画，返回只包含默认图像的静态PNG
+    /// 默认图像的IDAT数据和辅助chunk原样保留，不重新压缩；非APNG输入按原样重新序列化
+    #[wasm_bindgen]
+    pub fn to_static(&self) -> Result<Vec<u8>, JsValue> {
+        if self.chunk_parser.ihdr.is_none() {
+            return Err(JsValue::from_str("No parsed PNG data to convert"));
+        }
+        
+        let mut parser = self.chunk_parser.clone();
+        parser.strip_animation();
+        Ok(parser.serialize())
+    }
+
+    /// 无损重新压缩 - 保持像素、颜色类型、位深度和辅助chunk不变
+    /// 使用逐行最佳滤镜和最高压缩级别，不比parse的输入小时原样返回输入数据
+    #[wasm_bindgen]
+    pub fn reoptimize(&self) -> Result<Vec<u8>, JsValue> {
+        let (ihdr, original) = self.chunk_parser.ihdr.as_ref().zip(self.source_data.as_ref())
+            .ok_or_else(|| JsValue::from_str("No parsed PNG data to reoptimize"))?;
+        let samples = Self::decode_identity(original)?;
+        
+        let options = PackerOptions {
+            width: ihdr.width,
+            height: ihdr.height,
+            bit_depth: ihdr.bit_depth,
+            color_type: ihdr.color_type,
+            input_color_type: ihdr.color_type,
+            // IDENTITY解码的样本已按位打包
+            input_bit_depth: ihdr.bit_depth,
+            deflate_level: 9,
+            deflate_strategy: DEFLATE_STRATEGY_DEFAULT,
+            chunks: self.chunk_parser.passthrough_chunks(),
+            ..Default::default()
+        };
+        
+        let packed = PNGPacker::new(options).pack(&samples)
+            .map_err(|e| JsValue::from_str(&e))?;
+        
+        if packed.len() < original.len() {
+            Ok(packed)
+        } else {
+            Ok(original.clone())
+        }
+    }
+
+    /// 以逐行最佳滤镜重新滤镜原始格式的像素，返回未压缩的扫描线流
+    /// 每行为一个滤镜字节加滤镜后的数据，可交给外部压缩器（如zopfli）处理
+    #[wasm_bindgen]
+    pub fn optimize_filters(&self) -> Result<Vec<u8>, JsValue> {
+        let ihdr = self.chunk_parser.ihdr.as_ref()
+            .ok_or_else(|| JsValue::from_str("No parsed PNG data to filter"))?;
+        let samples = Self::decode_identity(&self.chunk_parser.serialize())?;
+        
+        let options = PackerOptions {
+            width: ihdr.width,
+            height: ihdr.height,
+            bit_depth: ihdr.bit_depth,
+            color_type: ihdr.color_type,
+            input_color_type: ihdr.color_type,
+            input_bit_depth: ihdr.bit_depth,
+            // 默认策略下五种滤镜都参与选择
+            deflate_strategy: DEFLATE_STRATEGY_DEFAULT,
+            filter_type: None,
+            ..Default::default()
+        };
+        
+        PNGPacker::new(options).filtered_scanlines(&samples)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// 不实际压缩，快速估算以最佳滤镜重新编码后IDAT数据的大小
+    /// 照片类内容通常比实际结果小5%~15%，大面积重复的纹理和图形误差一般在20%以内
+    #[wasm_bindgen]
+    pub fn estimate_compressed_size(&self) -> Result<u64, JsValue> {
+        Ok(estimate_deflate_size(&self.optimize_filters()?))
+    }
+
+    /// 估算输入PNG的压缩力度（0~1）：以逐行最佳滤镜重新滤镜后，
+    /// 比较原始IDAT大小与 [`estimate_deflate_size`] 的估算结果在未压缩大小上的位置。
+    /// 接近0表示几乎未压缩（如store-only），接近1表示已接近最佳压缩，重新优化收益很小
+    #[wasm_bindgen]
+    pub fn estimate_source_compression(data: &[u8]) -> Result<f64, JsValue> {
+        let mut parser = PNGChunkParser::new().with_lenient(true);
+        parser.parse(data).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let ihdr = parser.ihdr.as_ref()
+            .ok_or_else(|| JsValue::from_str("Missing IHDR chunk"))?;
+        let idat_size = parser.idat_data().len() as f64;
+        
+        let options = PackerOptions {
+            width: ihdr.width,
+            height: ihdr.height,
+            bit_depth: ihdr.bit_depth,
+            color_type: ihdr.color_type,
+            input_color_type: ihdr.color_type,
+            input_bit_depth: ihdr.bit_depth,
+            deflate_strategy: DEFLATE_STRATEGY_DEFAULT,
+            filter_type: None,
+            ..Default::default()
+        };
+        let filtered = PNGPacker::new(options).filtered_scanlines(&Self::decode_identity(data)?)
+            .map_err(|e| JsValue::from_str(&e))?;
+        let uncompressed = filtered.len() as f64;
+        let best = estimate_deflate_size(&filtered) as f64;
+        
+        // 数据本身不可压缩时无法区分压缩力度，视为已充分压缩
+        if best >= uncompressed {
+            return Ok(1.0);
+        }
+        Ok(((uncompressed - idat_size) / (uncompressed - best)).clamp(0.0, 1.0))
+    }
+
+    /// 获取每行扫描线使用的滤镜类型（调试用，交错图像按通道顺序排列）
+    #[wasm_bindgen]
+    pub fn get_filter_bytes(data: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let mut parser = PNGChunkParser::new().with_lenient(true);
+        parser.parse(data).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        parser.filter_bytes().map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// 只做反滤镜和去交错，按原始颜色类型和位深度返回样本 { samples, colorType, bitDepth, channels }
+    /// 不展开为RGBA：16位样本为大端字节，低于8位的样本按行打包
+    #[wasm_bindgen]
+    pub fn decode_samples(data: &[u8]) -> Result<js_sys::Object, JsValue> {
+        let mut parser = PNGChunkParser::new().with_lenient(true);
+        parser.parse(data).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let samples = parser.decode_pixels().map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let ihdr = parser.ihdr.as_ref()
+            .ok_or_else(|| JsValue::from_str("Missing IHDR chunk"))?;
+        
+        let obj = js_sys::Object::new();
+        js_sys::Reflect::set(&obj, &"samples".into(), &Uint8Array::from(&samples[..]))?;
+        js_sys::Reflect::set(&obj, &"colorType".into(), &ihdr.color_type.into())?;
+        js_sys::Reflect::set(&obj, &"bitDepth".into(), &ihdr.bit_depth.into())?;
+        js_sys::Reflect::set(&obj, &"channels".into(), &COLORTYPE_TO_BPP_MAP[ihdr.color_type as usize].into())?;
+        
+        Ok(obj)
+    }
+
+    /// 只解码矩形区域，返回该区域的RGBA数据
+    /// 非交错图像只解压、反滤镜到区域最后一行，且只有区域内的行转换为RGBA；
+    /// 交错图像的每个pass都覆盖整幅图像，仍需完整解码，没有性能收益
+    #[wasm_bindgen]
+    pub fn decode_region(data: &[u8], x: u32, y: u32, w: u32, h: u32) -> Result<Uint8ClampedArray, JsValue> {
+        let mut parser = PNGChunkParser::new().with_lenient(true);
+        parser.parse(data).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let ihdr = parser.ihdr.clone()
+            .ok_or_else(|| JsValue::from_str("Missing IHDR chunk"))?;
+        let bottom = y.checked_add(h).filter(|&bottom| bottom <= ihdr.height)
+            .ok_or_else(|| JsValue::from_str("Region out of bounds"))?;
+        
+        let raw = parser.decode_top_rows(bottom).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let stride = ihdr.stride();
+        
+        // 借用PNG的格式转换（调色板、tRNS、低位深度）处理区域内的行
+        let mut png = PNG::new(None);
+        png.width = ihdr.width;
+        png.height = ihdr.height;
+        png.color_type = ihdr.color_type;
+        png.bit_depth = ihdr.bit_depth;
+        png.palette = parser.palette.as_ref().map(|plte| plte.palette.concat());
+        png.palette_alpha = match &parser.transparency {
+            Some(TRNSData::Palette { alpha }) => Some(alpha.clone()),
+            _ => None,
+        };
+        png.chunk_parser = parser;
+        let mut rgba = png.raw_to_rgba(&raw[y as usize * stride..], h as usize);
+        rgba.truncate(ihdr.width as usize * h as usize * 4);
+        
+        let region = copy_region(&rgba, ihdr.width, h, x, 0, w, h).map_err(|e| JsValue::from_str(&e))?;
+        Ok(Uint8ClampedArray::from(&region[..]))
+    }
+
+    /// 完整校验PNG数据，返回 { valid, errors, warnings }
+    #[wasm_bindgen]
+    pub fn validate(data: &[u8]) -> Result<js_sys::Object, JsValue> {
+        let report = ErrorValidator::new().with_strict_mode(true).validate_report(data);
+        
+        let errors = Array::new();
+        for error in &report.errors {
+            errors.push(&JsValue::from_str(error));
+        }
+        let warnings = Array::new();
+        for warning in &report.warnings {
+            warnings.push(&JsValue::from_str(warning));
+        }
+        
+        let obj = js_sys::Object::new();
+        js_sys::Reflect::set(&obj, &"valid".into(), &report.valid.into())?;
+        js_sys::Reflect::set(&obj, &"errors".into(), &errors)?;
+        js_sys::Reflect::set(&obj, &"warnings".into(), &warnings)?;
+        
+        Ok(obj)
+    }
+
     /// 写入文件 - 匹配原始pngjs库的writeFile方法
     #[wasm_bindgen]
     pub fn write_file(&self, _filename: &str) -> Result<(), JsValue> {
@@ -220,8 +644,8 @@ impl PNG {
     /// 获取缓冲区 - 匹配原始pngjs库的toBuffer方法
     #[wasm_bindgen]
     pub fn to_buffer(&self) -> Result<Vec<u8>, JsValue> {
-        if let Some(rgba_data) = &self.rgba_data {
-            Ok(rgba_data.clone())
+        if let Some(rgba_data) = self.rgba_view() {
+            Ok(rgba_data.into_owned())
         } else {
             Err(JsValue::from_str("No image data available"))
         }
@@ -230,6 +654,13 @@ impl PNG {
     /// 位块传输 - 匹配原始pngjs库的bitblt方法
     #[wasm_bindgen]
     pub fn bitblt(&self, dst: &mut PNG, src_x: u32, src_y: u32, width: u32, height: u32, delta_x: u32, delta_y: u32) -> Result<(), JsValue> {
+        self.bitblt_blend(dst, BlitRegion::new(src_x, src_y, width, height, delta_x, delta_y), BlendMode::Copy)
+    }
+
+    /// 按合成模式进行位块传输，SourceOver用于叠加带透明度的图层
+    #[wasm_bindgen]
+    pub fn bitblt_blend(&self, dst: &mut PNG, region: BlitRegion, mode: BlendMode) -> Result<(), JsValue> {
+        let BlitRegion { src_x, src_y, width, height, delta_x, delta_y } = region;
         if src_x + width > self.width || src_y + height > self.height {
             return Err(JsValue::from_str("Source coordinates out of bounds"));
         }
@@ -238,17 +669,15 @@ impl PNG {
             return Err(JsValue::from_str("Destination coordinates out of bounds"));
         }
         
-        if let (Some(src_data), Some(dst_data)) = (&self.rgba_data, &mut dst.rgba_data) {
+        dst.expand_grayscale();
+        if let (Some(src_data), Some(dst_data)) = (self.rgba_view(), &mut dst.rgba_data) {
             for y in 0..height {
                 for x in 0..width {
-                    let src_idx = ((src_y + y) * self.width + src_x + x) * 4;
-                    let dst_idx = ((delta_y + y) * dst.width + delta_x + x) * 4;
+                    let src_idx = (((src_y + y) * self.width + src_x + x) * 4) as usize;
+                    let dst_idx = (((delta_y + y) * dst.width + delta_x + x) * 4) as usize;
                     
                     if src_idx + 3 < src_data.len() && dst_idx + 3 < dst_data.len() {
-                        dst_data[dst_idx] = src_data[src_idx];
-                        dst_data[dst_idx + 1] = src_data[src_idx + 1];
-                        dst_data[dst_idx + 2] = src_data[src_idx + 2];
-                        dst_data[dst_idx + 3] = src_data[src_idx + 3];
+                        blend_pixel(&src_data[src_idx..src_idx + 4], &mut dst_data[dst_idx..dst_idx + 4], mode);
                     }
                 }
             }
@@ -257,9 +686,121 @@ impl PNG {
         Ok(())
     }
 
+    /// 将当前图像以SourceOver方式叠加在背景之上，返回新的合成图像
+    /// 两幅图像尺寸必须相同，没有alpha通道的图像按不透明处理；结果沿用背景的元数据，格式为8位RGBA
+    #[wasm_bindgen]
+    pub fn over(&self, background: &PNG) -> Result<PNG, JsValue> {
+        if self.width != background.width || self.height != background.height {
+            return Err(JsValue::from_str(&format!(
+                "Image dimensions differ: {}x{} over {}x{}",
+                self.width, self.height, background.width, background.height
+            )));
+        }
+        let src_data = self.rgba_view()
+            .ok_or_else(|| JsValue::from_str("No image data available"))?;
+        
+        let mut composite = background.clone();
+        composite.expand_grayscale();
+        let dst_data = composite.rgba_data.as_mut()
+            .ok_or_else(|| JsValue::from_str("No background image data available"))?;
+        for (src_pixel, dst_pixel) in src_data.chunks_exact(4).zip(dst_data.chunks_exact_mut(4)) {
+            blend_pixel(src_pixel, dst_pixel, BlendMode::SourceOver);
+        }
+        
+        // 合成后可能出现新颜色，不再对应背景的原始格式
+        composite.detach_to_rgba8();
+        
+        Ok(composite)
+    }
+
+    /// 色键抠图：每个通道与(r, g, b)相差不超过tolerance的像素alpha置为0，其余像素不变
+    /// 作用于解码后的RGBA数据，tolerance为0时只匹配完全相同的颜色
+    #[wasm_bindgen]
+    pub fn chroma_key(&mut self, r: u8, g: u8, b: u8, tolerance: u8) -> Result<(), JsValue> {
+        self.expand_grayscale();
+        let data = self.rgba_data.as_mut()
+            .ok_or_else(|| JsValue::from_str("No image data available"))?;
+        
+        if chroma_key(data, [r, g, b], tolerance) > 0 {
+            // 新增了透明像素，不再对应原始格式
+            self.detach_to_rgba8();
+        }
+        
+        Ok(())
+    }
+
+    /// 羽化遮罩边缘：只对alpha通道做半径为radius的盒式模糊，RGB保持不变
+    #[wasm_bindgen]
+    pub fn feather_alpha(&mut self, radius: u32) -> Result<(), JsValue> {
+        self.expand_grayscale();
+        let data = self.rgba_data.as_mut()
+            .ok_or_else(|| JsValue::from_str("No image data available"))?;
+        // 完全不透明的图像模糊后不变
+        if radius == 0 || is_opaque(data) {
+            return Ok(());
+        }
+        
+        feather_alpha(data, self.width, self.height, radius)
+            .map_err(|e| JsValue::from_str(&e))?;
+        self.detach_to_rgba8();
+        
+        Ok(())
+    }
+
+    /// 将精灵图按cols列、rows行均分，按行优先顺序返回每个格子的独立PNG
+    /// 宽高不能被整除时报错
+    #[wasm_bindgen]
+    pub fn slice_grid(&self, cols: u32, rows: u32) -> Result<Array, JsValue> {
+        let data = self.rgba_view()
+            .ok_or_else(|| JsValue::from_str("No image data available"))?;
+        let cells = slice_grid(&data, self.width, self.height, cols, rows)
+            .map_err(|e| JsValue::from_str(&e))?;
+        
+        let array = Array::new();
+        for cell in cells {
+            array.push(&JsValue::from(PNG::from_rgba8(self.width / cols, self.height / rows, cell)));
+        }
+        Ok(array)
+    }
+
+    /// 调整亮度：RGB通道加上delta并截断到0..=255
+    #[wasm_bindgen]
+    pub fn adjust_brightness(&mut self, delta: i16) -> Result<(), JsValue> {
+        self.expand_grayscale();
+        let data = self.rgba_data.as_mut()
+            .ok_or_else(|| JsValue::from_str("No image data available"))?;
+        adjust_brightness(data, delta);
+        self.detach_to_rgba8();
+        Ok(())
+    }
+
+    /// 调整对比度：RGB通道以128为中心按factor缩放，factor大于1增强、小于1减弱
+    #[wasm_bindgen]
+    pub fn adjust_contrast(&mut self, factor: f64) -> Result<(), JsValue> {
+        self.expand_grayscale();
+        let data = self.rgba_data.as_mut()
+            .ok_or_else(|| JsValue::from_str("No image data available"))?;
+        adjust_contrast(data, factor);
+        self.detach_to_rgba8();
+        Ok(())
+    }
+
+    /// 色调分离：每个RGB通道只保留levels个色阶
+    #[wasm_bindgen]
+    pub fn posterize(&mut self, levels: u8) -> Result<(), JsValue> {
+        self.expand_grayscale();
+        let data = self.rgba_data.as_mut()
+            .ok_or_else(|| JsValue::from_str("No image data available"))?;
+        posterize(data, levels).map_err(|e| JsValue::from_str(&e))?;
+        self.detach_to_rgba8();
+        self.warnings.borrow_mut().push(format!("Posterized RGB channels to {} levels", levels));
+        Ok(())
+    }
+
     /// 调整Gamma - 匹配原始pngjs库的adjustGamma方法
     #[wasm_bindgen]
     pub fn adjust_gamma(&mut self) {
+        self.expand_grayscale();
         if self.gamma > 0.0 {
             if let Some(rgba_data) = &mut self.rgba_data {
                 let gamma_correction = 1.0 / self.gamma;
@@ -294,6 +835,11 @@ impl PNG {
             } else {
                 Err(JsValue::from_str("Pixel data out of bounds"))
             }
+        } else if let Some(gray) = &self.gray_data {
+            let channels = self.gray_channels as usize;
+            let index = (y * self.width + x) as usize * channels;
+            let alpha = if channels == 2 { gray[index + 1] } else { 255 };
+            Ok(pixel_to_array([gray[index], gray[index], gray[index], alpha]))
         } else {
             Err(JsValue::from_str("No image data available"))
         }
@@ -305,6 +851,7 @@ impl PNG {
         if x >= self.width || y >= self.height {
             return Err(JsValue::from_str("Pixel coordinates out of bounds"));
         }
+        self.expand_grayscale();
 
         if let Some(rgba_data) = &mut self.rgba_data {
             let index = ((y * self.width + x) * 4) as usize;
@@ -315,6 +862,12 @@ impl PNG {
                 rgba_data[index + 3] = a;
             }
         }
+        // 按RGB记录的图像写入非不透明像素后恢复alpha通道
+        if a != 255 && self.color_type == COLORTYPE_COLOR && self.bit_depth == 8 {
+            self.color_type = COLORTYPE_COLOR_ALPHA;
+            self.bpp = 4;
+            self.alpha = true;
+        }
 
         Ok(())
     }
@@ -347,6 +900,18 @@ impl PNG {
     #[wasm_bindgen(setter)]
     pub fn set_gamma(&mut self, gamma: f64) { self.gamma = gamma; }
 
+    /// sRGB渲染意图（0感知、1相对色度、2饱和度、3绝对色度），没有sRGB时返回undefined
+    #[wasm_bindgen]
+    pub fn rendering_intent(&self) -> Option<u8> {
+        self.chunk_parser.srgb.as_ref().map(|srgb| srgb.rendering_intent)
+    }
+
+    /// 实际生效的gamma，sRGB优先于gAMA；两者都不存在时返回undefined
+    #[wasm_bindgen]
+    pub fn effective_gamma(&self) -> Option<f64> {
+        self.chunk_parser.effective_gamma()
+    }
+
     #[wasm_bindgen(getter)]
     pub fn alpha(&self) -> bool { self.alpha }
 
@@ -359,19 +924,495 @@ impl PNG {
     /// 获取RGBA数据
     #[wasm_bindgen]
     pub fn get_rgba8_array(&self) -> Result<Uint8ClampedArray, JsValue> {
-        if let Some(rgba_data) = &self.rgba_data {
-            Ok(vec_to_uint8_clamped_array(rgba_data))
+        if let Some(rgba_data) = self.rgba_view() {
+            Ok(vec_to_uint8_clamped_array(&rgba_data))
         } else {
             Err(JsValue::from_str("No image data available"))
         }
     }
 
+    /// 获取RGB数据（每像素3字节）
+    /// 提供背景色 [r, g, b] 时半透明像素与背景合成，否则直接丢弃alpha
+    #[wasm_bindgen]
+    pub fn to_rgb_bytes(&self, background: Option<Vec<u8>>) -> Vec<u8> {
+        let background = background
+            .filter(|bg| bg.len() >= 3)
+            .map(|bg| [bg[0], bg[1], bg[2]]);
+        self.rgba_view().map_or_else(Vec::new, |data| {
+            if !is_opaque(&data) {
+                self.warnings.borrow_mut().push(match background {
+                    Some(_) => "Flattened alpha channel onto background during RGB conversion".to_string(),
+                    None => "Dropped alpha channel during RGB conversion".to_string(),
+                });
+            }
+            rgba_to_rgb(&data, background)
+        })
+    }
+
+    /// 分析RGBA内容，推荐无损编码的最小格式 { colorType, bitDepth, reason }
+    #[wasm_bindgen]
+    pub fn analyze_optimal_format(&self) -> Result<js_sys::Object, JsValue> {
+        let data = self.rgba_view()
+            .ok_or_else(|| JsValue::from_str("No image data available"))?;
+        let recommendation = recommend_format(&data);
+        
+        let obj = js_sys::Object::new();
+        js_sys::Reflect::set(&obj, &"colorType".into(), &recommendation.color_type.into())?;
+        js_sys::Reflect::set(&obj, &"bitDepth".into(), &recommendation.bit_depth.into())?;
+        js_sys::Reflect::set(&obj, &"reason".into(), &recommendation.reason.into())?;
+        Ok(obj)
+    }
+
+    /// 获取矩形区域的RGBA数据，按行紧密排列（每行w * 4字节）
+    #[wasm_bindgen]
+    pub fn get_region(&self, x: u32, y: u32, w: u32, h: u32) -> Result<Uint8ClampedArray, JsValue> {
+        let data = self.rgba_view()
+            .ok_or_else(|| JsValue::from_str("No image data available"))?;
+        let region = copy_region(&data, self.width, self.height, x, y, w, h)
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(vec_to_uint8_clamped_array(&region))
+    }
+
+    /// 获取平面格式的通道数据 { r, g, b, a }，每个通道为一个Uint8Array
+    #[wasm_bindgen]
+    pub fn get_planar(&self) -> Result<js_sys::Object, JsValue> {
+        let data = self.rgba_view()
+            .ok_or_else(|| JsValue::from_str("No image data available"))?;
+        
+        let obj = js_sys::Object::new();
+        for (name, plane) in ["r", "g", "b", "a"].into_iter().zip(rgba_to_planar(&data)) {
+            js_sys::Reflect::set(&obj, &name.into(), &vec_to_uint8_array(&plane))?;
+        }
+        Ok(obj)
+    }
+
+    /// 深拷贝图像：像素、调色板、透明色和全部元数据，修改副本不影响原图
+    #[wasm_bindgen]
+    pub fn clone_image(&self) -> PNG {
+        self.clone()
+    }
+
+    /// 获取RGBA数据（每像素4字节）
+    #[wasm_bindgen]
+    pub fn to_rgba_bytes(&self) -> Vec<u8> {
+        self.rgba_view().map(Cow::into_owned).unwrap_or_default()
+    }
+
+    /// 替换RGBA数据，长度必须等于 width * height * 4，不符时报错且原数据不变
+    #[wasm_bindgen]
+    pub fn set_data(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        check_rgba_len(data, self.width, self.height).map_err(|e| JsValue::from_str(&e))?;
+        
+        self.rgba_data = Some(data.to_vec());
+        self.gray_data = None;
+        self.detach_to_rgba8();
+        Ok(())
+    }
+
+    /// 紧凑灰度模式下的灰度缓冲区（每像素1字节，灰度+Alpha为2字节），未启用或已展开时返回undefined
+    #[wasm_bindgen]
+    pub fn get_gray_data(&self) -> Option<Uint8Array> {
+        self.gray_data.as_ref().map(|gray| vec_to_uint8_array(gray))
+    }
+
+    /// 将紧凑灰度缓冲区展开为RGBA数据，之后可以使用所有编辑方法
+    #[wasm_bindgen]
+    pub fn expand_grayscale(&mut self) {
+        if self.rgba_data.is_none() {
+            self.rgba_data = self.rgba_view().map(Cow::into_owned);
+        }
+        self.gray_data = None;
+    }
+
+    /// 像素内容哈希（16位十六进制），解码结果相同的文件哈希相同
+    #[wasm_bindgen]
+    pub fn content_hash(&self) -> String {
+        let rgba = self.rgba_view().unwrap_or_default();
+        format!("{:016x}", content_fingerprint(self.width, self.height, &rgba))
+    }
+
+    /// 检查图像是否完全不透明（可降级为RGB）
+    #[wasm_bindgen]
+    pub fn is_opaque(&self) -> bool {
+        self.rgba_view().map_or(true, |data| is_opaque(&data))
+    }
+
+    /// 8位RGBA图像完全不透明时改为按RGB记录，之后pack写出RGB图像，返回是否进行了转换
+    /// RGBA数据本身保持不变，像素读取不受影响
+    #[wasm_bindgen]
+    pub fn drop_redundant_alpha(&mut self) -> bool {
+        if self.color_type != COLORTYPE_COLOR_ALPHA || self.bit_depth != 8 || self.rgba_data.is_none() || !self.is_opaque() {
+            return false;
+        }
+        self.color_type = COLORTYPE_COLOR;
+        self.bpp = 3;
+        self.alpha = false;
+        // 原始样本仍带alpha，不再与颜色类型对应
+        self.pixel_data = None;
+        true
+    }
+
+    /// 检查图像是否包含半透明像素（无法用tRNS表示）
+    #[wasm_bindgen]
+    pub fn has_translucency(&self) -> bool {
+        self.rgba_view().map_or(false, |data| has_translucency(&data))
+    }
+
+    /// 统计不同RGBA颜色的个数，超过max时提前停止并返回max + 1
+    /// 不超过256时可以无损转换为调色板图像
+    #[wasm_bindgen]
+    pub fn distinct_color_count(&self, max: u32) -> u32 {
+        self.rgba_view().map_or(0, |data| distinct_color_count(&data, max))
+    }
+
+    /// 获取不同alpha值的个数（1为单一alpha，2为二值透明，更多为平滑alpha）
+    /// 没有图像数据时返回0
+    #[wasm_bindgen]
+    pub fn alpha_levels(&self) -> u32 {
+        self.rgba_view().map_or(0, |data| alpha_levels(&data))
+    }
+
+    /// 获取成功解码的行数（部分解码时可能小于高度）
+    #[wasm_bindgen(getter)]
+    pub fn decoded_rows(&self) -> u32 {
+        self.decoded_rows
+    }
+
+    /// 文件中存储的位深度（IHDR）
+    #[wasm_bindgen]
+    pub fn stored_bit_depth(&self) -> u8 {
+        self.bit_depth
+    }
+
+    /// 解码器实际输出的样本位深度：默认展开时低位深度和调色板图像为8，
+    /// 构造时设置expand为false则与存储的位深度相同
+    #[wasm_bindgen]
+    pub fn delivered_bit_depth(&self) -> u8 {
+        self.delivered_bit_depth
+    }
+
+    /// 获取解码器输出的样本数据（每行不含滤镜字节），expand为false时低位深度样本按位打包
+    /// 需在构造时设置keepRaw（紧凑灰度模式下的8位灰度除外）
+    #[wasm_bindgen]
+    pub fn get_raw_data(&self) -> Option<Uint8Array> {
+        // 8位紧凑灰度缓冲区就是解码器输出
+        self.pixel_data.as_ref().or(self.gray_data.as_ref()).map(|data| vec_to_uint8_array(data))
+    }
+
+    /// 释放解码器输出的原始样本数据，只保留RGBA数据，get_raw_data之后返回undefined
+    #[wasm_bindgen]
+    pub fn free_pixel_data(&mut self) {
+        self.pixel_data = None;
+    }
+
+    /// 像素相关缓冲区占用的内存字节数（按容量计算）：
+    /// RGBA数据、原始样本、紧凑灰度、16位样本、调色板索引和调色板
+    #[wasm_bindgen]
+    pub fn memory_footprint(&self) -> u64 {
+        let capacity = |buffer: &Option<Vec<u8>>| buffer.as_ref().map_or(0, |data| data.capacity() as u64);
+        let native = self.native_data.as_ref().map_or(0, |data| data.capacity() as u64 * 2);
+        
+        capacity(&self.rgba_data)
+            + capacity(&self.pixel_data)
+            + capacity(&self.gray_data)
+            + native
+            + capacity(&self.indices)
+            + capacity(&self.palette)
+            + capacity(&self.palette_alpha)
+    }
+
+    /// 获取16位原始精度样本（需在构造时设置keepNativeDepth）
+    #[wasm_bindgen]
+    pub fn get_native_data(&self) -> Option<js_sys::Uint16Array> {
+        self.native_data.as_ref().map(|data| js_sys::Uint16Array::from(&data[..]))
+    }
+
+    /// 获取调色板图像每个像素的调色板索引（未经RGBA展开），非调色板图像返回undefined
+    /// 修改调色板后RGBA数据会按这些索引重新渲染
+    #[wasm_bindgen]
+    pub fn get_indices(&self) -> Option<Uint8Array> {
+        self.indices.as_ref().map(|indices| vec_to_uint8_array(indices))
+    }
+
     /// 获取调色板数据
     #[wasm_bindgen]
     pub fn get_palette(&self) -> Option<Uint8Array> {
         self.palette.as_ref().map(|palette| vec_to_uint8_array(palette))
     }
 
+    /// 设置调色板（每3字节一个RGB条目）并重新渲染RGBA数据
+    #[wasm_bindgen]
+    pub fn set_palette(&mut self, palette: &[u8]) -> Result<(), JsValue> {
+        if self.color_type != COLORTYPE_PALETTE_COLOR {
+            return Err(JsValue::from_str("Palette can only be set on indexed images"));
+        }
+        if palette.is_empty() || palette.len() % 3 != 0 || palette.len() > 256 * 3 {
+            return Err(JsValue::from_str("Palette length must be a multiple of 3 with at most 256 entries"));
+        }
+        
+        self.palette = Some(palette.to_vec());
+        self.render_indexed()
+    }
+
+    /// 设置调色板alpha（每个调色板条目一字节）并重新渲染RGBA数据
+    #[wasm_bindgen]
+    pub fn set_palette_alpha(&mut self, alpha: &[u8]) -> Result<(), JsValue> {
+        if self.color_type != COLORTYPE_PALETTE_COLOR {
+            return Err(JsValue::from_str("Palette alpha can only be set on indexed images"));
+        }
+        if alpha.len() > 256 {
+            return Err(JsValue::from_str("Palette alpha must have at most 256 entries"));
+        }
+        
+        self.palette_alpha = Some(alpha.to_vec());
+        self.alpha = alpha.iter().any(|&a| a < 255);
+        self.render_indexed()
+    }
+
+    /// 获取EXIF原始数据（eXIf chunk的TIFF格式内容）
+    #[wasm_bindgen]
+    pub fn get_exif(&self) -> Option<Uint8Array> {
+        self.chunk_parser.exif.as_ref().map(|exif| vec_to_uint8_array(&exif.raw))
+    }
+
+    /// 按EXIF方向标签旋转/翻转图像，完成后将标签重置为1
+    /// 没有EXIF数据或方向标签时不做任何处理
+    #[wasm_bindgen]
+    pub fn apply_exif_orientation(&mut self) -> Result<(), JsValue> {
+        let mut exif = match self.chunk_parser.exif.clone() {
+            Some(exif) => exif,
+            None => return Ok(()),
+        };
+        let orientation = match exif.orientation() {
+            Some(1) | None => return Ok(()),
+            Some(orientation) => orientation,
+        };
+        self.expand_grayscale();
+        let data = self.rgba_data.as_ref()
+            .ok_or_else(|| JsValue::from_str("No image data available"))?;
+        
+        let (rotated, width, height) = apply_orientation(data, self.width, self.height, orientation)
+            .map_err(|e| JsValue::from_str(&e))?;
+        self.rgba_data = Some(rotated);
+        self.width = width;
+        self.height = height;
+        
+        // 原始格式的数据不再与RGBA数据对应
+        self.pixel_data = None;
+        self.native_data = None;
+        self.indices = None;
+        
+        exif.set_orientation(1);
+        self.chunk_parser.set_exif(exif);
+        
+        Ok(())
+    }
+
+    /// 双线性缩放图像，gamma_correct为true时在线性光空间插值
+    #[wasm_bindgen]
+    pub fn resize(&mut self, width: u32, height: u32, gamma_correct: bool) -> Result<(), JsValue> {
+        self.expand_grayscale();
+        let data = self.rgba_data.as_ref()
+            .ok_or_else(|| JsValue::from_str("No image data available"))?;
+        
+        let resized = resize(data, self.width, self.height, width, height, gamma_correct)
+            .map_err(|e| JsValue::from_str(&e))?;
+        self.rgba_data = Some(resized);
+        self.warnings.borrow_mut().push(format!("Resampled image from {}x{} to {}x{}", self.width, self.height, width, height));
+        self.width = width;
+        self.height = height;
+        
+        // 原始格式的数据不再与RGBA数据对应
+        self.pixel_data = None;
+        self.native_data = None;
+        self.indices = None;
+        
+        Ok(())
+    }
+
+    /// 生成缩略图：保持宽高比缩放到最长边等于max_dimension，返回新图像，原图不变
+    #[wasm_bindgen]
+    pub fn make_thumbnail(&self, max_dimension: u32) -> Result<PNG, JsValue> {
+        let data = self.rgba_view()
+            .ok_or_else(|| JsValue::from_str("No image data available"))?;
+        
+        let (width, height) = fit_within(self.width, self.height, max_dimension)
+            .map_err(|e| JsValue::from_str(&e))?;
+        let resized = resize(&data, self.width, self.height, width, height, true)
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(PNG::from_rgba8(width, height, resized))
+    }
+
+    /// 生成count级逐步细化的预览图，依次为原图的1/2^(count-1)、…、1/2和原始分辨率
+    /// 每级由更大的一级缩小一半得到（奇数尺寸向上取整），最小的一级可用作模糊占位图
+    #[wasm_bindgen]
+    pub fn generate_preview_levels(&self, count: u32) -> Result<Array, JsValue> {
+        if count == 0 {
+            return Err(JsValue::from_str("Preview level count must be at least 1"));
+        }
+        let data = self.rgba_view()
+            .ok_or_else(|| JsValue::from_str("No image data available"))?;
+        
+        let mut levels = Vec::with_capacity(count as usize);
+        let (mut width, mut height, mut current) = (self.width, self.height, data.into_owned());
+        for _ in 1..count {
+            let (next_width, next_height) = (width.div_ceil(2), height.div_ceil(2));
+            let next = resize(&current, width, height, next_width, next_height, true)
+                .map_err(|e| JsValue::from_str(&e))?;
+            levels.push(PNG::from_rgba8(width, height, std::mem::replace(&mut current, next)));
+            width = next_width;
+            height = next_height;
+        }
+        levels.push(PNG::from_rgba8(width, height, current));
+        
+        let array = Array::new();
+        for level in levels.into_iter().rev() {
+            array.push(&JsValue::from(level));
+        }
+        Ok(array)
+    }
+
+    /// oFFs声明的图像位置 [x, y, unit]，unit为0表示像素，1表示微米；没有oFFs时返回undefined
+    #[wasm_bindgen]
+    pub fn get_offset(&self) -> Option<Array> {
+        self.chunk_parser.offset.as_ref().map(|offset| {
+            let array = Array::new();
+            array.push(&offset.x.into());
+            array.push(&offset.y.into());
+            array.push(&offset.unit.into());
+            array
+        })
+    }
+
+    /// APNG动画元数据 { frameCount, loopCount, delays }，来自acTL和各fcTL，不解码帧像素
+    /// loopCount为0表示无限循环，delays为每帧显示时长（毫秒）；不是APNG时返回undefined
+    #[wasm_bindgen]
+    pub fn get_animation_info(&self) -> Option<js_sys::Object> {
+        let control = self.chunk_parser.animation_control.as_ref()?;
+        
+        let delays = Array::new();
+        for frame in &self.chunk_parser.frame_controls {
+            delays.push(&frame.delay_ms().into());
+        }
+        
+        let obj = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&obj, &"frameCount".into(), &control.num_frames.into());
+        let _ = js_sys::Reflect::set(&obj, &"loopCount".into(), &control.num_plays.into());
+        let _ = js_sys::Reflect::set(&obj, &"delays".into(), &delays);
+        Some(obj)
+    }
+
+    /// pHYs声明的像素宽高比（宽/高），没有pHYs时返回undefined
+    #[wasm_bindgen]
+    pub fn pixel_aspect_ratio(&self) -> Option<f64> {
+        self.chunk_parser.physical.as_ref().and_then(|physical| physical.pixel_aspect_ratio())
+    }
+
+    /// 获取sPLT建议调色板 [{ name, sampleDepth, entries }]
+    /// entries为Uint16Array，每个条目依次为R、G、B、A、频率
+    #[wasm_bindgen]
+    pub fn get_suggested_palettes(&self) -> Result<Array, JsValue> {
+        let array = Array::new();
+        
+        for palette in &self.chunk_parser.suggested_palettes {
+            let entries: Vec<u16> = palette.entries.iter()
+                .flat_map(|&(r, g, b, a, frequency)| [r, g, b, a, frequency])
+                .collect();
+            let palette_obj = js_sys::Object::new();
+            js_sys::Reflect::set(&palette_obj, &"name".into(), &palette.name.as_str().into())?;
+            js_sys::Reflect::set(&palette_obj, &"sampleDepth".into(), &palette.sample_depth.into())?;
+            js_sys::Reflect::set(&palette_obj, &"entries".into(), &js_sys::Uint16Array::from(&entries[..]))?;
+            array.push(&palette_obj);
+        }
+        
+        Ok(array)
+    }
+
+    /// 按pHYs将非正方形像素缩放为正方形像素，拉伸较短的方向
+    /// unit为0（只有宽高比）时同样生效；返回是否进行了缩放
+    #[wasm_bindgen]
+    pub fn correct_pixel_aspect(&mut self) -> Result<bool, JsValue> {
+        let physical = match self.chunk_parser.physical.clone() {
+            Some(physical) if physical.ppu_x != physical.ppu_y => physical,
+            _ => return Ok(false),
+        };
+        
+        let (width, height) = physical.square_pixel_size(self.width, self.height)
+            .ok_or_else(|| JsValue::from_str("Invalid pHYs pixel aspect ratio"))?;
+        self.resize(width, height, false)?;
+        
+        // 缩放后像素已是正方形
+        let ppu = physical.ppu_x.max(physical.ppu_y);
+        self.chunk_parser.set_physical(PHYSData { ppu_x: ppu, ppu_y: ppu, unit: physical.unit });
+        
+        Ok(true)
+    }
+
+    /// 按指定方式将16位图像重新映射为8位RGBA数据，原始16位数据保持不变
+    /// gamma仅在Gamma模式下使用，默认2.2
+    #[wasm_bindgen]
+    pub fn tone_map_16_to_8(&mut self, mode: ToneMapMode, gamma: Option<f64>) -> Result<(), JsValue> {
+        if self.bit_depth != 16 {
+            return Err(JsValue::from_str("Tone mapping requires 16-bit image data"));
+        }
+        // 未保留原始样本时从chunk数据重新解码
+        let decoded;
+        let data = match &self.pixel_data {
+            Some(data) => data,
+            None => {
+                decoded = self.decode_stored_samples()?;
+                &decoded
+            }
+        };
+        
+        let mode = match mode {
+            ToneMapMode::Linear => ToneMap::Linear,
+            ToneMapMode::MinMaxStretch => ToneMap::MinMaxStretch,
+            ToneMapMode::Gamma => ToneMap::Gamma(gamma.unwrap_or(2.2)),
+        };
+        let has_alpha = self.color_type & COLORTYPE_ALPHA != 0;
+        let channels = get_bytes_per_pixel(self.color_type);
+        let native = to_native_16(data);
+        let samples = tone_map_16_to_8(&native, channels, has_alpha, mode);
+        
+        let mut rgba = convert_to_rgba(
+            &samples,
+            self.width as usize,
+            self.height as usize,
+            self.color_type,
+            8,
+            None,
+        );
+        // tRNS关键色按16位原始样本比较，映射后的8位值可能与其他颜色重合
+        let key = match self.chunk_parser.transparency {
+            Some(TRNSData::Grayscale { value }) => Some(vec![value]),
+            Some(TRNSData::RGB { r, g, b }) => Some(vec![r, g, b]),
+            _ => None,
+        };
+        if let Some(key) = key.filter(|key| key.len() == channels) {
+            for (pixel, samples) in rgba.chunks_exact_mut(4).zip(native.chunks_exact(channels)) {
+                if samples == &key[..] {
+                    pixel[3] = 0;
+                }
+            }
+        }
+        self.rgba_data = Some(rgba);
+        self.gray_data = None;
+        self.warnings.borrow_mut().push(format!("Reduced 16-bit samples to 8 bits ({:?} tone mapping)", mode));
+        
+        Ok(())
+    }
+
+    /// 获取有损操作的警告记录（如位深度降低、色调分离、缩放），按发生顺序排列
+    #[wasm_bindgen]
+    pub fn get_warnings(&self) -> Array {
+        let warnings = Array::new();
+        for warning in self.warnings.borrow().iter() {
+            warnings.push(&JsValue::from_str(warning));
+        }
+        warnings
+    }
+
     /// 获取透明度颜色
     #[wasm_bindgen]
     pub fn get_trans_color(&self) -> Option<Uint8Array> {
@@ -387,7 +1428,8 @@ impl PNG {
     /// 获取交错通道信息
     #[wasm_bindgen]
     pub fn get_interlace_passes(&self) -> Result<Array, JsValue> {
-        let passes = get_interlace_passes(self.width, self.height);
+        let bits_per_pixel = get_bytes_per_pixel(self.color_type) * self.bit_depth as usize;
+        let passes = get_interlace_passes_with_bpp(self.width, self.height, bits_per_pixel);
         let array = Array::new();
         
         for pass in passes {
@@ -399,12 +1441,31 @@ impl PNG {
             js_sys::Reflect::set(&pass_obj, &"yOffset".into(), &pass.y_offset.into())?;
             js_sys::Reflect::set(&pass_obj, &"xStep".into(), &pass.x_step.into())?;
             js_sys::Reflect::set(&pass_obj, &"yStep".into(), &pass.y_step.into())?;
+            js_sys::Reflect::set(&pass_obj, &"dataSize".into(), &(pass.data_size as u32).into())?;
             array.push(&pass_obj);
         }
         
         Ok(array)
     }
 
+    /// 获取chunk索引 - 每个chunk的偏移、长度、类型、CRC状态
+    #[wasm_bindgen]
+    pub fn get_chunk_index(&self) -> Result<Array, JsValue> {
+        let array = Array::new();
+        
+        for info in self.chunk_parser.chunk_index() {
+            let info_obj = js_sys::Object::new();
+            js_sys::Reflect::set(&info_obj, &"offset".into(), &(info.offset as u32).into())?;
+            js_sys::Reflect::set(&info_obj, &"length".into(), &info.length.into())?;
+            js_sys::Reflect::set(&info_obj, &"typeCode".into(), &info.type_code.into())?;
+            js_sys::Reflect::set(&info_obj, &"crcOk".into(), &info.crc_ok.into())?;
+            js_sys::Reflect::set(&info_obj, &"critical".into(), &info.critical.into())?;
+            array.push(&info_obj);
+        }
+        
+        Ok(array)
+    }
+
     /// 检查是否为交错图像
     #[wasm_bindgen]
     pub fn is_interlaced(&self) -> bool {
@@ -448,18 +1509,153 @@ impl PNGSync {
     /// 同步写入PNG
     #[wasm_bindgen]
     pub fn write(png: &PNG, options: Option<JsValue>) -> Result<Vec<u8>, JsValue> {
-        png.pack()
+        png.pack(None)
     }
 }
 
 impl PNG {
+    /// 由8位RGBA数据创建新图像
+    fn from_rgba8(width: u32, height: u32, data: Vec<u8>) -> PNG {
+        let mut png = PNG::new(None);
+        png.width = width;
+        png.height = height;
+        png.rgba_data = Some(data);
+        png.detach_to_rgba8();
+        png
+    }
+    
+    /// 紧凑灰度缓冲区对应的颜色类型
+    fn gray_color_type(&self) -> u8 {
+        if self.gray_channels == 2 { COLORTYPE_GRAYSCALE_ALPHA } else { COLORTYPE_GRAYSCALE }
+    }
+    
+    /// RGBA数据，紧凑灰度模式下由灰度缓冲区展开
+    fn rgba_view(&self) -> Option<Cow<'_, [u8]>> {
+        if let Some(data) = &self.rgba_data {
+            return Some(Cow::Borrowed(data));
+        }
+        let gray = self.gray_data.as_ref()?;
+        Some(Cow::Owned(convert_to_rgba(gray, self.width as usize, self.height as usize, self.gray_color_type(), 8, None)))
+    }
+    
+    /// RGBA数据修改后不再对应原始格式：改为8位RGBA并清除原始格式数据
+    fn detach_to_rgba8(&mut self) {
+        if self.bit_depth == 16 {
+            self.warnings.borrow_mut().push("Reduced 16-bit image to 8-bit RGBA".to_string());
+        }
+        self.color_type = COLORTYPE_COLOR_ALPHA;
+        self.bit_depth = 8;
+        self.depth = 8;
+        self.bpp = 4;
+        self.alpha = self.rgba_data.as_deref().is_some_and(|data| !is_opaque(data));
+        self.palette = None;
+        self.palette_alpha = None;
+        self.trans_color = None;
+        self.pixel_data = None;
+        self.native_data = None;
+        self.indices = None;
+    }
+    
+    /// 以原始格式解码像素（不做任何转换）
+    fn decode_identity(data: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let mut decoder = Decoder::new(Cursor::new(data));
+        decoder.set_transformations(Transformations::IDENTITY);
+        let mut reader = decoder.read_info()
+            .map_err(|e| JsValue::from_str(&format!("Failed to read PNG info: {}", e)))?;
+        let mut samples = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut samples)
+            .map_err(|e| JsValue::from_str(&format!("Failed to read PNG frame: {}", e)))?;
+        Ok(samples)
+    }
+    
+    /// 从解析时保留的chunk数据以原始格式重新解码像素，图像尺寸已改变时无法使用
+    fn decode_stored_samples(&self) -> Result<Vec<u8>, JsValue> {
+        match &self.chunk_parser.ihdr {
+            Some(ihdr) if ihdr.width == self.width && ihdr.height == self.height => {
+                Self::decode_identity(&self.chunk_parser.serialize())
+            }
+            _ => Err(JsValue::from_str("No image data available")),
+        }
+    }
+    
+    /// 使用当前调色板从索引重新生成RGBA数据
+    fn render_indexed(&mut self) -> Result<(), JsValue> {
+        let indices = self.indices.as_ref()
+            .ok_or_else(|| JsValue::from_str("No indexed pixel data available"))?;
+        let palette = self.palette.as_deref().unwrap_or(&[]);
+        let palette_alpha = self.palette_alpha.as_deref().unwrap_or(&[]);
+        
+        self.rgba_data = Some(render_palette(indices, palette, palette_alpha));
+        Ok(())
+    }
+    
+    /// 将原始格式（未经EXPAND）的rows行扫描线数据转换为8位RGBA
+    /// 支持按位打包的低位深度样本、调色板和tRNS关键色
+    fn raw_to_rgba(&self, raw: &[u8], rows: usize) -> Vec<u8> {
+        let width = self.width as usize;
+        match self.color_type {
+            COLORTYPE_PALETTE_COLOR => {
+                let indices = unpack_indices(raw, width, rows, self.bit_depth);
+                render_palette(
+                    &indices,
+                    self.palette.as_deref().unwrap_or(&[]),
+                    self.palette_alpha.as_deref().unwrap_or(&[]),
+                )
+            }
+            COLORTYPE_GRAYSCALE | COLORTYPE_COLOR if self.bit_depth < 8 || self.chunk_parser.transparency.is_some() => {
+                let key = match self.chunk_parser.transparency {
+                    Some(TRNSData::Grayscale { value }) => vec![value],
+                    Some(TRNSData::RGB { r, g, b }) => vec![r, g, b],
+                    // 低位深度样本不会超过255，用作永不匹配的关键色
+                    _ => vec![u16::MAX],
+                };
+                let mut rgba = vec![0; width * rows * 4];
+                replace_transparent_color(raw, &mut rgba, width, rows, self.color_type, self.bit_depth, &key);
+                rgba
+            }
+            _ => convert_to_rgba(raw, width, rows, self.color_type, self.bit_depth, None),
+        }
+    }
+    
+    /// 部分解码：保留已解码的行，其余行填充partial_fill
+    fn parse_partial(&mut self) -> Result<(), JsValue> {
+        let partial = self.chunk_parser.decode_pixels_partial()
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let width = self.width as usize;
+        let rows = partial.decoded_rows as usize;
+        let stride = self.chunk_parser.ihdr.as_ref().map_or(0, |ihdr| ihdr.stride());
+        
+        let mut rgba = self.raw_to_rgba(&partial.pixels[..stride * rows], rows);
+        rgba.truncate(width * rows * 4);
+        for _ in rows * width..self.height as usize * width {
+            rgba.extend_from_slice(&self.partial_fill);
+        }
+        
+        self.rgba_data = Some(rgba);
+        self.pixel_data = self.keep_raw.then_some(partial.pixels);
+        self.delivered_bit_depth = self.bit_depth;
+        self.decoded_rows = partial.decoded_rows;
+        self.has_ihdr = true;
+        // 截断的数据没有IEND，也不保留上一幅图像的索引和16位样本
+        self.has_iend = false;
+        self.indices = None;
+        self.native_data = None;
+        if self.decoded_rows < self.height {
+            self.warnings.borrow_mut().push(format!(
+                "Image data truncated: decoded {} of {} rows", self.decoded_rows, self.height
+            ));
+        }
+        
+        Ok(())
+    }
+    
     /// 获取每像素字节数
     fn get_bytes_per_pixel(&self) -> usize {
         match self.color_type {
             COLORTYPE_GRAYSCALE => 1,
             COLORTYPE_COLOR => 3,
             COLORTYPE_PALETTE_COLOR => 1,
-            COLORTYPE_GRAYSCALE | COLORTYPE_ALPHA => 2,
+            COLORTYPE_GRAYSCALE_ALPHA => 2,
             COLORTYPE_COLOR_ALPHA => 4,
             _ => 4,
         }
@@ -3,7 +3,11 @@
 
 use std::collections::HashMap;
 use crate::constants::*;
-use crate::crc::crc32;
+use crate::crc::calculate_chunk_crc;
+use crate::error_handling::{check_png_signature, PNGError};
+use crate::filter::unfilter_scanline;
+use crate::interlace::{get_interlace_passes_with_bpp, scatter_pass_pixels};
+use crate::sync_inflate::{inflate_zlib, inflate_zlib_partial, inflate_zlib_prefix};
 
 /// PNG Chunk类型
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
@@ -20,6 +24,14 @@ pub enum ChunkType {
     TEXT,
     ZTXT,
     ITXT,
+    EXIF,
+    HIST,
+    PHYS,
+    SPLT,
+    OFFS,
+    ACTL,
+    FCTL,
+    FDAT,
     Unknown(u32),
 }
 
@@ -38,6 +50,14 @@ impl ChunkType {
             TYPE_tEXt => ChunkType::TEXT,
             TYPE_zTXt => ChunkType::ZTXT,
             TYPE_iTXt => ChunkType::ITXT,
+            TYPE_eXIf => ChunkType::EXIF,
+            TYPE_hIST => ChunkType::HIST,
+            TYPE_pHYs => ChunkType::PHYS,
+            TYPE_sPLT => ChunkType::SPLT,
+            TYPE_oFFs => ChunkType::OFFS,
+            TYPE_acTL => ChunkType::ACTL,
+            TYPE_fcTL => ChunkType::FCTL,
+            TYPE_fdAT => ChunkType::FDAT,
             _ => ChunkType::Unknown(value),
         }
     }
@@ -56,9 +76,42 @@ impl ChunkType {
             ChunkType::TEXT => TYPE_tEXt,
             ChunkType::ZTXT => TYPE_zTXt,
             ChunkType::ITXT => TYPE_iTXt,
+            ChunkType::EXIF => TYPE_eXIf,
+            ChunkType::HIST => TYPE_hIST,
+            ChunkType::PHYS => TYPE_pHYs,
+            ChunkType::SPLT => TYPE_sPLT,
+            ChunkType::OFFS => TYPE_oFFs,
+            ChunkType::ACTL => TYPE_acTL,
+            ChunkType::FCTL => TYPE_fcTL,
+            ChunkType::FDAT => TYPE_fdAT,
             ChunkType::Unknown(value) => *value,
         }
     }
+    
+    /// 从4字符类型码解析chunk类型，每个字符必须是ASCII字母
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        let bytes: [u8; 4] = name.as_bytes().try_into()
+            .map_err(|_| format!("Chunk type must be 4 characters: {:?}", name))?;
+        if !bytes.iter().all(u8::is_ascii_alphabetic) {
+            return Err(format!("Chunk type must consist of ASCII letters: {:?}", name));
+        }
+        Ok(Self::from_u32(u32::from_be_bytes(bytes)))
+    }
+    
+    /// 辅助chunk（第一个字符小写），解码器可以忽略
+    pub fn is_ancillary(&self) -> bool {
+        self.to_u32() & 0x2000_0000 != 0
+    }
+    
+    /// 私有chunk（第二个字符小写），不属于PNG规范
+    pub fn is_private(&self) -> bool {
+        self.to_u32() & 0x0020_0000 != 0
+    }
+    
+    /// 保留位（第三个字符）必须为大写
+    pub fn is_reserved_bit_valid(&self) -> bool {
+        self.to_u32() & 0x0000_2000 == 0
+    }
 }
 
 /// PNG Chunk结构
@@ -68,6 +121,8 @@ pub struct PNGChunk {
     pub chunk_type: ChunkType,
     pub data: Vec<u8>,
     pub crc: u32,
+    /// 位于IDAT之后（只对辅助chunk有效），重新编码时保持原位置
+    pub after_idat: bool,
 }
 
 impl PNGChunk {
@@ -78,14 +133,23 @@ impl PNGChunk {
             chunk_type,
             data,
             crc,
+            after_idat: false,
         }
     }
     
+    /// 标记为写在IDAT之后
+    pub fn with_after_idat(mut self, after_idat: bool) -> Self {
+        self.after_idat = after_idat;
+        self
+    }
+    
+    /// 编码时是否写在IDAT之后：关键chunk总是写在IDAT之前
+    pub fn is_after_idat(&self) -> bool {
+        self.after_idat && self.chunk_type.is_ancillary()
+    }
+    
     pub fn calculate_crc(chunk_type: &ChunkType, data: &[u8]) -> u32 {
-        let mut crc_data = Vec::new();
-        crc_data.extend_from_slice(&chunk_type.to_u32().to_be_bytes());
-        crc_data.extend_from_slice(data);
-        crc32(&crc_data)
+        calculate_chunk_crc(&chunk_type.to_u32().to_be_bytes(), data)
     }
     
     pub fn verify_crc(&self) -> bool {
@@ -121,6 +185,11 @@ impl IHDRData {
             return Err("IHDR data must be 13 bytes".to_string());
         }
         
+        let (bit_depth, color_type) = (data[8], data[9]);
+        if !is_valid_color_bitdepth(color_type, bit_depth) {
+            return Err(format!("Invalid bit depth {} for color type {}", bit_depth, color_type));
+        }
+        
         Ok(Self {
             width: u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
             height: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
@@ -132,6 +201,22 @@ impl IHDRData {
         })
     }
     
+    /// 每像素位数
+    pub fn bits_per_pixel(&self) -> usize {
+        let channels = match self.color_type {
+            COLORTYPE_COLOR => 3,
+            COLORTYPE_GRAYSCALE_ALPHA => 2,
+            COLORTYPE_COLOR_ALPHA => 4,
+            _ => 1,
+        };
+        channels * self.bit_depth as usize
+    }
+    
+    /// 每行字节数（不含滤镜字节）
+    pub fn stride(&self) -> usize {
+        (self.width as usize * self.bits_per_pixel() + 7) / 8
+    }
+    
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
         bytes.extend_from_slice(&self.width.to_be_bytes());
@@ -176,6 +261,282 @@ impl PLTEData {
     }
 }
 
+/// hIST Chunk数据 - 每个调色板条目的使用频率
+#[derive(Debug, Clone)]
+pub struct HISTData {
+    pub frequencies: Vec<u16>,
+}
+
+impl HISTData {
+    /// 解析hIST数据，条目数必须与调色板一致
+    pub fn from_bytes(data: &[u8], palette_entries: usize) -> Result<Self, String> {
+        if data.len() != palette_entries * 2 {
+            return Err(format!("hIST must have {} entries to match PLTE, got {} bytes", palette_entries, data.len()));
+        }
+        
+        Ok(Self {
+            frequencies: data.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect(),
+        })
+    }
+    
+    /// 根据索引使用次数计算直方图
+    /// 次数超出u16范围时按比例缩放，被使用过的条目至少为1
+    pub fn from_indices(indices: &[u8], palette_entries: usize) -> Self {
+        let mut counts = vec![0u64; palette_entries];
+        for &index in indices {
+            if let Some(count) = counts.get_mut(index as usize) {
+                *count += 1;
+            }
+        }
+        
+        let max = counts.iter().copied().max().unwrap_or(0);
+        let frequencies = counts.iter().map(|&count| {
+            if max <= u16::MAX as u64 || count == 0 {
+                count as u16
+            } else {
+                (count * u16::MAX as u64 / max).max(1) as u16
+            }
+        }).collect();
+        
+        Self { frequencies }
+    }
+    
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.frequencies.iter().flat_map(|frequency| frequency.to_be_bytes()).collect()
+    }
+}
+
+/// pHYs Chunk数据 - 每单位的像素数，unit为1表示米，0表示只给出宽高比
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PHYSData {
+    pub ppu_x: u32,
+    pub ppu_y: u32,
+    pub unit: u8,
+}
+
+impl PHYSData {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        if data.len() != 9 {
+            return Err(format!("pHYs chunk must be 9 bytes, got {}", data.len()));
+        }
+        
+        Ok(Self {
+            ppu_x: u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+            ppu_y: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            unit: data[8],
+        })
+    }
+    
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(9);
+        bytes.extend_from_slice(&self.ppu_x.to_be_bytes());
+        bytes.extend_from_slice(&self.ppu_y.to_be_bytes());
+        bytes.push(self.unit);
+        bytes
+    }
+    
+    /// 像素宽高比（像素宽度 / 像素高度 = ppu_y / ppu_x），任一值为0时返回None
+    pub fn pixel_aspect_ratio(&self) -> Option<f64> {
+        if self.ppu_x == 0 || self.ppu_y == 0 {
+            return None;
+        }
+        Some(self.ppu_y as f64 / self.ppu_x as f64)
+    }
+    
+    /// 像素变为正方形后的图像尺寸：拉伸较短的方向，另一方向保持不变
+    /// 宽高比无效或结果超出u32范围时返回None
+    pub fn square_pixel_size(&self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if self.ppu_x == 0 || self.ppu_y == 0 {
+            return None;
+        }
+        
+        // 四舍五入 dimension * numerator / denominator
+        let scale = |dimension: u32, numerator: u32, denominator: u32| {
+            let scaled = (dimension as u64 * numerator as u64 + denominator as u64 / 2) / denominator as u64;
+            u32::try_from(scaled).ok()
+        };
+        
+        if self.ppu_y > self.ppu_x {
+            // 像素比高度宽，横向拉伸
+            Some((scale(width, self.ppu_y, self.ppu_x)?, height))
+        } else {
+            Some((width, scale(height, self.ppu_x, self.ppu_y)?))
+        }
+    }
+}
+
+/// oFFs Chunk数据 - 图像在页面上的位置，偏移为有符号数，unit为0表示像素，1表示微米
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OFFSData {
+    pub x: i32,
+    pub y: i32,
+    pub unit: u8,
+}
+
+impl OFFSData {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        if data.len() != 9 {
+            return Err(format!("oFFs chunk must be 9 bytes, got {}", data.len()));
+        }
+        
+        Ok(Self {
+            x: i32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+            y: i32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            unit: data[8],
+        })
+    }
+    
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(9);
+        bytes.extend_from_slice(&self.x.to_be_bytes());
+        bytes.extend_from_slice(&self.y.to_be_bytes());
+        bytes.push(self.unit);
+        bytes
+    }
+}
+
+/// acTL Chunk数据 - APNG动画控制：帧数和播放次数（0表示无限循环）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ACTLData {
+    pub num_frames: u32,
+    pub num_plays: u32,
+}
+
+impl ACTLData {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        if data.len() != 8 {
+            return Err(format!("acTL chunk must be 8 bytes, got {}", data.len()));
+        }
+        
+        Ok(Self {
+            num_frames: u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+            num_plays: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+        })
+    }
+    
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8);
+        bytes.extend_from_slice(&self.num_frames.to_be_bytes());
+        bytes.extend_from_slice(&self.num_plays.to_be_bytes());
+        bytes
+    }
+}
+
+/// fcTL Chunk数据 - APNG帧控制：帧区域、显示时长（delay_num/delay_den秒）和处置/混合方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FCTLData {
+    pub sequence_number: u32,
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: u32,
+    pub y_offset: u32,
+    pub delay_num: u16,
+    pub delay_den: u16,
+    pub dispose_op: u8,
+    pub blend_op: u8,
+}
+
+impl FCTLData {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        if data.len() != 26 {
+            return Err(format!("fcTL chunk must be 26 bytes, got {}", data.len()));
+        }
+        let read_u32 = |i: usize| u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        
+        Ok(Self {
+            sequence_number: read_u32(0),
+            width: read_u32(4),
+            height: read_u32(8),
+            x_offset: read_u32(12),
+            y_offset: read_u32(16),
+            delay_num: u16::from_be_bytes([data[20], data[21]]),
+            delay_den: u16::from_be_bytes([data[22], data[23]]),
+            dispose_op: data[24],
+            blend_op: data[25],
+        })
+    }
+    
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(26);
+        bytes.extend_from_slice(&self.sequence_number.to_be_bytes());
+        bytes.extend_from_slice(&self.width.to_be_bytes());
+        bytes.extend_from_slice(&self.height.to_be_bytes());
+        bytes.extend_from_slice(&self.x_offset.to_be_bytes());
+        bytes.extend_from_slice(&self.y_offset.to_be_bytes());
+        bytes.extend_from_slice(&self.delay_num.to_be_bytes());
+        bytes.extend_from_slice(&self.delay_den.to_be_bytes());
+        bytes.push(self.dispose_op);
+        bytes.push(self.blend_op);
+        bytes
+    }
+    
+    /// 帧显示时长（毫秒），按APNG规范delay_den为0时视为100
+    pub fn delay_ms(&self) -> f64 {
+        let den = if self.delay_den == 0 { 100 } else { self.delay_den };
+        self.delay_num as f64 * 1000.0 / den as f64
+    }
+}
+
+/// sPLT Chunk数据 - 建议调色板，每个条目为 (R, G, B, A, 频率)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SPLTData {
+    pub name: String,
+    pub sample_depth: u8,
+    pub entries: Vec<(u16, u16, u16, u16, u16)>,
+}
+
+impl SPLTData {
+    /// 解析sPLT数据：以null结尾的名称、1字节样本深度，
+    /// 然后是深度8时6字节、深度16时10字节的条目
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        let null_pos = data.iter().position(|&b| b == 0).ok_or("No null terminator found")?;
+        if null_pos == 0 || null_pos > 79 {
+            return Err(format!("sPLT palette name must be 1-79 bytes, got {}", null_pos));
+        }
+        let name = latin1_to_string(&data[..null_pos]);
+        
+        let sample_depth = *data.get(null_pos + 1).ok_or("Insufficient data for sPLT")?;
+        let entry_size = match sample_depth {
+            8 => 6,
+            16 => 10,
+            _ => return Err(format!("Invalid sPLT sample depth: {}", sample_depth)),
+        };
+        
+        let entries_data = &data[null_pos + 2..];
+        if entries_data.len() % entry_size != 0 {
+            return Err(format!("sPLT entry data length {} is not a multiple of {}", entries_data.len(), entry_size));
+        }
+        
+        let entries = entries_data.chunks_exact(entry_size).map(|entry| {
+            let (samples, frequency) = entry.split_at(entry_size - 2);
+            let sample = |i: usize| match sample_depth {
+                8 => samples[i] as u16,
+                _ => u16::from_be_bytes([samples[i * 2], samples[i * 2 + 1]]),
+            };
+            (sample(0), sample(1), sample(2), sample(3), u16::from_be_bytes([frequency[0], frequency[1]]))
+        }).collect();
+        
+        Ok(Self { name, sample_depth, entries })
+    }
+    
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = string_to_latin1(&self.name);
+        bytes.push(0);
+        bytes.push(self.sample_depth);
+        for &(r, g, b, a, frequency) in &self.entries {
+            for sample in [r, g, b, a] {
+                if self.sample_depth == 8 {
+                    bytes.push(sample as u8);
+                } else {
+                    bytes.extend_from_slice(&sample.to_be_bytes());
+                }
+            }
+            bytes.extend_from_slice(&frequency.to_be_bytes());
+        }
+        bytes
+    }
+}
+
 /// tRNS Chunk数据
 #[derive(Debug, Clone)]
 pub enum TRNSData {
@@ -269,6 +630,12 @@ pub struct CHRMData {
 }
 
 impl CHRMData {
+    /// 标准sRGB色度值
+    pub fn srgb() -> Self {
+        let [white_point_x, white_point_y, red_x, red_y, green_x, green_y, blue_x, blue_y] = SRGB_CHROMATICITIES;
+        Self { white_point_x, white_point_y, red_x, red_y, green_x, green_y, blue_x, blue_y }
+    }
+    
     pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
         if data.len() != 32 {
             return Err("cHRM data must be 32 bytes".to_string());
@@ -322,7 +689,81 @@ impl SRGBData {
     }
 }
 
-/// tEXt Chunk数据
+/// eXIf Chunk数据 - 原样保存的TIFF格式EXIF数据，不做解析
+#[derive(Debug, Clone)]
+pub struct EXIFData {
+    pub raw: Vec<u8>,
+}
+
+impl EXIFData {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        Ok(Self {
+            raw: data.to_vec(),
+        })
+    }
+    
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.raw.clone()
+    }
+    
+    /// 读取IFD0中的Orientation标签（0x0112）
+    pub fn orientation(&self) -> Option<u16> {
+        let offset = self.orientation_offset()?;
+        Some(self.read_u16(offset))
+    }
+    
+    /// 修改IFD0中的Orientation标签，标签不存在时返回false
+    pub fn set_orientation(&mut self, orientation: u16) -> bool {
+        let offset = match self.orientation_offset() {
+            Some(offset) => offset,
+            None => return false,
+        };
+        let bytes = if self.is_little_endian() { orientation.to_le_bytes() } else { orientation.to_be_bytes() };
+        self.raw[offset..offset + 2].copy_from_slice(&bytes);
+        true
+    }
+    
+    fn is_little_endian(&self) -> bool {
+        self.raw.starts_with(b"II")
+    }
+    
+    fn read_u16(&self, offset: usize) -> u16 {
+        let bytes = [self.raw[offset], self.raw[offset + 1]];
+        if self.is_little_endian() { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) }
+    }
+    
+    /// 查找Orientation标签值在raw中的偏移（SHORT类型，值内联在条目中）
+    fn orientation_offset(&self) -> Option<usize> {
+        if self.raw.len() < 8 || !(self.raw.starts_with(b"II*\0") || self.raw.starts_with(b"MM\0*")) {
+            return None;
+        }
+        
+        let offset_bytes = [self.raw[4], self.raw[5], self.raw[6], self.raw[7]];
+        let ifd = if self.is_little_endian() {
+            u32::from_le_bytes(offset_bytes)
+        } else {
+            u32::from_be_bytes(offset_bytes)
+        } as usize;
+        if ifd.checked_add(2)? > self.raw.len() {
+            return None;
+        }
+        
+        let count = self.read_u16(ifd) as usize;
+        for i in 0..count {
+            let entry = ifd + 2 + i * 12;
+            if entry + 12 > self.raw.len() {
+                return None;
+            }
+            if self.read_u16(entry) == 0x0112 && self.read_u16(entry + 2) == 3 {
+                return Some(entry + 8);
+            }
+        }
+        
+        None
+    }
+}
+
+/// tEXt Chunk数据，关键字和文本均为Latin-1编码
 #[derive(Debug, Clone)]
 pub struct TEXTData {
     pub keyword: String,
@@ -332,23 +773,31 @@ pub struct TEXTData {
 impl TEXTData {
     pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
         let null_pos = data.iter().position(|&b| b == 0).ok_or("No null terminator found")?;
-        let keyword = String::from_utf8(data[..null_pos].to_vec())
-            .map_err(|_| "Invalid keyword encoding")?;
-        let text = String::from_utf8(data[null_pos + 1..].to_vec())
-            .map_err(|_| "Invalid text encoding")?;
+        let keyword = latin1_to_string(&data[..null_pos]);
+        let text = latin1_to_string(&data[null_pos + 1..]);
         
         Ok(Self { keyword, text })
     }
     
+    /// Latin-1无法表示的字符写为'?'
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(self.keyword.as_bytes());
+        let mut bytes = string_to_latin1(&self.keyword);
         bytes.push(0);
-        bytes.extend_from_slice(self.text.as_bytes());
+        bytes.extend_from_slice(&string_to_latin1(&self.text));
         bytes
     }
 }
 
+/// Latin-1字节解码：每个字节对应相同码位的Unicode字符
+fn latin1_to_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// 编码为Latin-1，超出U+00FF的字符替换为'?'
+fn string_to_latin1(text: &str) -> Vec<u8> {
+    text.chars().map(|c| u8::try_from(c).unwrap_or(b'?')).collect()
+}
+
 /// zTXt Chunk数据
 #[derive(Debug, Clone)]
 pub struct ZTXTData {
@@ -462,7 +911,48 @@ impl ITXTData {
     }
 }
 
+/// Chunk索引信息
+#[derive(Debug, Clone)]
+pub struct ChunkInfo {
+    /// chunk起始位置（长度字段）在文件中的偏移
+    pub offset: usize,
+    pub length: u32,
+    pub type_code: String,
+    pub crc_ok: bool,
+    /// 类型码首字母大写为关键chunk
+    pub critical: bool,
+}
+
+/// 部分解码结果
+#[derive(Debug, Clone)]
+pub struct PartialDecode {
+    /// 原始布局的像素数据，未解码的行填充为0
+    pub pixels: Vec<u8>,
+    /// 成功解码的行数
+    pub decoded_rows: u32,
+}
+
+/// 解析资源限制，用于处理不可信输入
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserLimits {
+    /// 单个chunk声明长度的上限（字节）
+    pub max_chunk_length: u32,
+    /// chunk总数的上限
+    pub max_chunk_count: usize,
+}
+
+impl Default for ParserLimits {
+    /// 默认只限制为规范允许的最大chunk长度 2^31-1
+    fn default() -> Self {
+        Self {
+            max_chunk_length: 0x7fff_ffff,
+            max_chunk_count: usize::MAX,
+        }
+    }
+}
+
 /// PNG Chunk解析器
+#[derive(Debug, Clone)]
 pub struct PNGChunkParser {
     pub chunks: HashMap<ChunkType, Vec<PNGChunk>>,
     pub ihdr: Option<IHDRData>,
@@ -474,6 +964,29 @@ pub struct PNGChunkParser {
     pub text_chunks: Vec<TEXTData>,
     pub ztxt_chunks: Vec<ZTXTData>,
     pub itxt_chunks: Vec<ITXTData>,
+    pub exif: Option<EXIFData>,
+    pub histogram: Option<HISTData>,
+    pub physical: Option<PHYSData>,
+    pub suggested_palettes: Vec<SPLTData>,
+    pub offset: Option<OFFSData>,
+    /// APNG动画控制，只解析元数据，不解码帧像素
+    pub animation_control: Option<ACTLData>,
+    /// APNG各帧的帧控制，按出现顺序排列
+    pub frame_controls: Vec<FCTLData>,
+    /// 宽松模式：辅助chunk的CRC错误只记录警告，不中断解析；
+    /// 被截断的最后一个chunk保留已有数据并记录警告
+    pub lenient: bool,
+    /// 宽松模式下CRC校验失败的chunk类型
+    pub crc_warnings: Vec<ChunkType>,
+    /// 宽松模式下数据在其中截断的chunk类型（只可能是最后一个chunk）
+    pub truncated_chunk: Option<ChunkType>,
+    /// 存储的CRC与内容不符、已按内容重新计算CRC的chunk类型码（如"tEXt"），重新序列化时写出正确的CRC
+    pub crc_repairs: Vec<String>,
+    /// chunk长度和数量限制，在分配chunk数据之前检查
+    pub limits: ParserLimits,
+    chunk_infos: Vec<ChunkInfo>,
+    /// chunks中每个chunk存入时的类型，按文件顺序排列，重新序列化时保持原有顺序
+    chunk_order: Vec<ChunkType>,
 }
 
 impl PNGChunkParser {
@@ -489,27 +1002,51 @@ impl PNGChunkParser {
             text_chunks: Vec::new(),
             ztxt_chunks: Vec::new(),
             itxt_chunks: Vec::new(),
+            exif: None,
+            histogram: None,
+            physical: None,
+            suggested_palettes: Vec::new(),
+            offset: None,
+            animation_control: None,
+            frame_controls: Vec::new(),
+            lenient: false,
+            crc_warnings: Vec::new(),
+            truncated_chunk: None,
+            crc_repairs: Vec::new(),
+            limits: ParserLimits::default(),
+            chunk_infos: Vec::new(),
+            chunk_order: Vec::new(),
         }
     }
     
+    /// 设置宽松模式
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+    
+    /// 设置chunk长度和数量限制
+    pub fn with_limits(mut self, limits: ParserLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+    
     /// 解析PNG数据
-    pub fn parse(&mut self, data: &[u8]) -> Result<(), String> {
+    pub fn parse(&mut self, data: &[u8]) -> Result<(), PNGError> {
         let mut offset = 0;
         
         // 检查PNG签名
-        if data.len() < PNG_SIGNATURE.len() {
-            return Err("Insufficient data for PNG signature".to_string());
-        }
-        
-        if &data[offset..offset + PNG_SIGNATURE.len()] != &PNG_SIGNATURE {
-            return Err("Invalid PNG signature".to_string());
-        }
+        check_png_signature(data)?;
         offset += PNG_SIGNATURE.len();
         
         // 解析chunks
+        let mut chunk_count = 0;
+        let mut seen_idat = false;
         while offset < data.len() {
-            if offset + 8 > data.len() {
-                return Err("Insufficient data for chunk header".to_string());
+            let chunk_start = offset;
+            
+            if data.len() - offset < 8 {
+                return Err(PNGError::InsufficientData("Truncated chunk header".to_string()));
             }
             
             let length = u32::from_be_bytes([
@@ -521,32 +1058,88 @@ impl PNGChunkParser {
             
             offset += 8;
             
-            if offset + length as usize + 4 > data.len() {
-                return Err("Insufficient data for chunk".to_string());
+            // 在分配chunk数据之前检查资源限制
+            chunk_count += 1;
+            if chunk_count > self.limits.max_chunk_count {
+                return Err(PNGError::LimitsExceeded(format!("More than {} chunks", self.limits.max_chunk_count)));
+            }
+            if length > self.limits.max_chunk_length {
+                return Err(PNGError::LimitsExceeded(format!(
+                    "Chunk length {} exceeds limit {}", length, self.limits.max_chunk_length
+                )));
+            }
+            
+            // 长度来自不可信输入，32位平台上直接相加可能溢出回绕
+            let overflow = || PNGError::InvalidFormat(format!("Chunk length {} overflows the data offset", length));
+            let data_end = offset.checked_add(length as usize).ok_or_else(overflow)?;
+            let chunk_end = data_end.checked_add(4).ok_or_else(overflow)?;
+            
+            if chunk_end > data.len() {
+                if !self.lenient {
+                    return Err(PNGError::InsufficientData("Truncated chunk data".to_string()));
+                }
+                
+                // 宽松模式：保留被截断的chunk数据，无法校验CRC
+                let available = data.len().min(data_end);
+                let chunk_type = ChunkType::from_u32(chunk_type);
+                self.truncated_chunk = Some(chunk_type.clone());
+                self.chunk_order.push(chunk_type.clone());
+                self.chunks.entry(chunk_type.clone()).or_insert_with(Vec::new).push(PNGChunk {
+                    length: (available - offset) as u32,
+                    chunk_type,
+                    data: data[offset..available].to_vec(),
+                    crc: 0,
+                    after_idat: seen_idat,
+                });
+                break;
             }
             
-            let chunk_data = data[offset..offset + length as usize].to_vec();
-            offset += length as usize;
+            let chunk_data = data[offset..data_end].to_vec();
             
             let crc = u32::from_be_bytes([
-                data[offset], data[offset + 1], data[offset + 2], data[offset + 3]
+                data[data_end], data[data_end + 1], data[data_end + 2], data[data_end + 3]
             ]);
-            offset += 4;
+            offset = chunk_end;
             
-            let chunk = PNGChunk {
+            let mut chunk = PNGChunk {
                 length,
                 chunk_type: ChunkType::from_u32(chunk_type),
                 data: chunk_data,
                 crc,
+                after_idat: seen_idat && chunk_type != TYPE_IDAT,
             };
+            seen_idat |= chunk_type == TYPE_IDAT;
+            
+            let type_bytes = chunk_type.to_be_bytes();
+            let crc_ok = chunk.verify_crc();
+            self.chunk_infos.push(ChunkInfo {
+                offset: chunk_start,
+                length,
+                type_code: String::from_utf8_lossy(&type_bytes).into_owned(),
+                crc_ok,
+                critical: type_bytes[0].is_ascii_uppercase(),
+            });
             
-            // 验证CRC
-            if !chunk.verify_crc() {
-                return Err(format!("Invalid CRC for chunk {:?}", chunk.chunk_type));
+            // 验证CRC，宽松模式下辅助chunk的错误只记录警告
+            if !crc_ok {
+                if !self.lenient || type_bytes[0].is_ascii_uppercase() {
+                    return Err(PNGError::ParseError(format!("Invalid CRC for chunk {:?}", chunk.chunk_type)));
+                }
+                self.crc_warnings.push(chunk.chunk_type.clone());
+                self.crc_repairs.push(String::from_utf8_lossy(&type_bytes).into_owned());
+                chunk.crc = PNGChunk::calculate_crc(&chunk.chunk_type, &chunk.data);
+            }
+            
+            // 宽或高为0的IHDR不符合规范
+            if chunk.chunk_type == ChunkType::IHDR {
+                let ihdr = IHDRData::from_bytes(&chunk.data).map_err(PNGError::ParseError)?;
+                if ihdr.width == 0 || ihdr.height == 0 {
+                    return Err(PNGError::InvalidFormat(format!("Invalid image size {}x{}", ihdr.width, ihdr.height)));
+                }
             }
             
             // 处理chunk
-            self.process_chunk(chunk)?;
+            self.process_chunk(chunk).map_err(PNGError::ParseError)?;
         }
         
         Ok(())
@@ -567,13 +1160,18 @@ impl PNGChunkParser {
                 }
             }
             ChunkType::GAMA => {
-                self.gamma = Some(GAMAData::from_bytes(&chunk.data)?);
+                // 重复的gAMA忽略，以第一个为准
+                if self.gamma.is_none() {
+                    self.gamma = Some(GAMAData::from_bytes(&chunk.data)?);
+                }
             }
             ChunkType::CHRM => {
                 self.chroma = Some(CHRMData::from_bytes(&chunk.data)?);
             }
             ChunkType::SRGB => {
-                self.srgb = Some(SRGBData::from_bytes(&chunk.data)?);
+                if self.srgb.is_none() {
+                    self.srgb = Some(SRGBData::from_bytes(&chunk.data)?);
+                }
             }
             ChunkType::TEXT => {
                 self.text_chunks.push(TEXTData::from_bytes(&chunk.data)?);
@@ -584,10 +1182,33 @@ impl PNGChunkParser {
             ChunkType::ITXT => {
                 self.itxt_chunks.push(ITXTData::from_bytes(&chunk.data)?);
             }
+            ChunkType::EXIF => {
+                self.exif = Some(EXIFData::from_bytes(&chunk.data)?);
+            }
+            ChunkType::HIST => {
+                let palette = self.palette.as_ref().ok_or("hIST chunk requires a preceding PLTE chunk")?;
+                self.histogram = Some(HISTData::from_bytes(&chunk.data, palette.palette.len())?);
+            }
+            ChunkType::PHYS => {
+                self.physical = Some(PHYSData::from_bytes(&chunk.data)?);
+            }
+            ChunkType::SPLT => {
+                self.suggested_palettes.push(SPLTData::from_bytes(&chunk.data)?);
+            }
+            ChunkType::OFFS => {
+                self.offset = Some(OFFSData::from_bytes(&chunk.data)?);
+            }
+            ChunkType::ACTL => {
+                self.animation_control = Some(ACTLData::from_bytes(&chunk.data)?);
+            }
+            ChunkType::FCTL => {
+                self.frame_controls.push(FCTLData::from_bytes(&chunk.data)?);
+            }
             _ => {}
         }
         
         // 存储chunk
+        self.chunk_order.push(chunk.chunk_type.clone());
         self.chunks.entry(chunk.chunk_type.clone()).or_insert_with(Vec::new).push(chunk);
         
         Ok(())
@@ -598,6 +1219,24 @@ impl PNGChunkParser {
         self.chunks.get(chunk_type)
     }
     
+    /// 按4字符类型码获取第一个chunk的原始数据，可用于读取私有chunk
+    pub fn get_chunk_data(&self, name: &str) -> Option<&[u8]> {
+        let chunk_type = ChunkType::from_name(name).ok()?;
+        self.get_chunks(&chunk_type)?.first().map(|chunk| &chunk.data[..])
+    }
+    
+    /// 替换EXIF数据，同时更新保留的eXIf chunk
+    pub fn set_exif(&mut self, exif: EXIFData) {
+        self.chunks.insert(ChunkType::EXIF, vec![PNGChunk::new(ChunkType::EXIF, exif.to_bytes())]);
+        self.exif = Some(exif);
+    }
+    
+    /// 替换pHYs数据，同时更新保留的pHYs chunk
+    pub fn set_physical(&mut self, physical: PHYSData) {
+        self.chunks.insert(ChunkType::PHYS, vec![PNGChunk::new(ChunkType::PHYS, physical.to_bytes())]);
+        self.physical = Some(physical);
+    }
+    
     /// 检查是否包含特定chunk
     pub fn has_chunk(&self, chunk_type: &ChunkType) -> bool {
         self.chunks.contains_key(chunk_type)
@@ -607,4 +1246,281 @@ impl PNGChunkParser {
     pub fn get_chunk_types(&self) -> Vec<ChunkType> {
         self.chunks.keys().cloned().collect()
     }
+    
+    /// 获取解析时记录的chunk索引（按文件顺序）
+    pub fn chunk_index(&self) -> Vec<ChunkInfo> {
+        self.chunk_infos.clone()
+    }
+    
+    /// 合并所有IDAT chunk的数据
+    pub fn idat_data(&self) -> Vec<u8> {
+        self.chunks.get(&ChunkType::IDAT)
+            .map(|chunks| chunks.iter().flat_map(|chunk| chunk.data.iter().copied()).collect())
+            .unwrap_or_default()
+    }
+    
+    /// 合并后的IDAT数据，缺少IDAT或全部为空时返回错误
+    fn require_idat_data(&self) -> Result<Vec<u8>, PNGError> {
+        let idat = self.idat_data();
+        if idat.is_empty() {
+            return Err(PNGError::InvalidFormat("Missing or empty IDAT chunk".to_string()));
+        }
+        Ok(idat)
+    }
+    
+    /// 解码像素数据：合并IDAT、zlib解压、逐行反滤镜，交错图像再去交错
+    /// 返回原始颜色类型和位深度下的像素，每行按字节对齐且不含滤镜字节
+    pub fn decode_pixels(&self) -> Result<Vec<u8>, PNGError> {
+        let ihdr = self.ihdr.as_ref()
+            .ok_or_else(|| PNGError::InvalidFormat("Missing IHDR chunk".to_string()))?;
+        let raw = inflate_zlib(&self.require_idat_data()?)?;
+        
+        let bits_per_pixel = ihdr.bits_per_pixel();
+        let bpp = ((bits_per_pixel + 7) / 8).max(1);
+        let stride = ihdr.stride();
+        
+        if ihdr.interlace_method == 0 {
+            return unfilter_rows(&raw, stride, ihdr.height as usize, bpp);
+        }
+        
+        // Adam7：各通道依次存储，分别反滤镜后写回完整图像
+        // 先确认解压数据覆盖所有pass，再按IHDR声明的尺寸分配输出
+        let passes = get_interlace_passes_with_bpp(ihdr.width, ihdr.height, bits_per_pixel);
+        let mut end = 0;
+        for pass in &passes {
+            end += pass.data_size;
+            if raw.len() < end {
+                return Err(PNGError::InsufficientData(format!("Interlace pass {} is truncated", pass.pass + 1)));
+            }
+        }
+        let size = stride.checked_mul(ihdr.height as usize)
+            .ok_or_else(|| PNGError::MemoryError(format!("Image size {}x{} overflows usize", ihdr.width, ihdr.height)))?;
+        let mut output = vec![0u8; size];
+        let mut offset = 0;
+        
+        for pass in passes {
+            let pass_stride = (pass.width as usize * bits_per_pixel + 7) / 8;
+            let pass_len = pass.data_size;
+            let pass_raw = &raw[offset..offset + pass_len];
+            
+            let pass_pixels = unfilter_rows(pass_raw, pass_stride, pass.height as usize, bpp)?;
+            scatter_pass_pixels(&pass_pixels, &pass, &mut output, stride, bits_per_pixel);
+            offset += pass_len;
+        }
+        
+        Ok(output)
+    }
+    
+    /// 获取每行扫描线的滤镜字节（交错图像按通道顺序排列）
+    pub fn filter_bytes(&self) -> Result<Vec<u8>, PNGError> {
+        let ihdr = self.ihdr.as_ref()
+            .ok_or_else(|| PNGError::InvalidFormat("Missing IHDR chunk".to_string()))?;
+        let raw = inflate_zlib(&self.require_idat_data()?)?;
+        
+        let mut filters = Vec::new();
+        let mut offset = 0;
+        for (line_len, rows) in Self::scanline_layout(ihdr) {
+            for _ in 0..rows {
+                let filter_type = *raw.get(offset)
+                    .ok_or_else(|| PNGError::InsufficientData(format!("Scanline {} is truncated", filters.len())))?;
+                filters.push(filter_type);
+                offset += line_len;
+            }
+        }
+        
+        Ok(filters)
+    }
+    
+    /// 实际生效的gamma：存在sRGB时按规范忽略gAMA，固定为 1/2.2 (0.45455)
+    pub fn effective_gamma(&self) -> Option<f64> {
+        if self.srgb.is_some() {
+            return Some(SRGB_GAMMA as f64 / 100000.0);
+        }
+        self.gamma.as_ref().map(|gamma| gamma.get_gamma_value())
+    }
+    
+    /// 读取原始扫描线（含滤镜字节，不做反滤镜），交错图像按pass顺序排列
+    pub fn raw_scanlines(&self) -> Result<Vec<Vec<u8>>, String> {
+        let ihdr = self.ihdr.as_ref().ok_or("Missing IHDR chunk")?;
+        let raw = inflate_zlib(&self.require_idat_data().map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+        
+        let mut scanlines = Vec::new();
+        let mut offset = 0;
+        for (line_len, rows) in Self::scanline_layout(ihdr) {
+            for _ in 0..rows {
+                let line = raw.get(offset..offset + line_len)
+                    .ok_or_else(|| format!("Scanline {} is truncated", scanlines.len()))?;
+                scanlines.push(line.to_vec());
+                offset += line_len;
+            }
+        }
+        
+        Ok(scanlines)
+    }
+    
+    /// 扫描线布局：(每行长度含滤镜字节, 行数)，交错图像每个pass一项
+    fn scanline_layout(ihdr: &IHDRData) -> Vec<(usize, usize)> {
+        if ihdr.interlace_method == 0 {
+            vec![(ihdr.stride() + 1, ihdr.height as usize)]
+        } else {
+            get_interlace_passes_with_bpp(ihdr.width, ihdr.height, ihdr.bits_per_pixel())
+                .iter()
+                .map(|pass| (pass.data_size / pass.height as usize, pass.height as usize))
+                .collect()
+        }
+    }
+    
+    /// 只解码前rows行：非交错图像只解压到第rows行为止，之后的数据不解压也不反滤镜
+    /// 交错图像的各pass覆盖所有行，退化为完整解码后截取
+    pub fn decode_top_rows(&self, rows: u32) -> Result<Vec<u8>, PNGError> {
+        let ihdr = self.ihdr.as_ref()
+            .ok_or_else(|| PNGError::InvalidFormat("Missing IHDR chunk".to_string()))?;
+        let rows = rows.min(ihdr.height) as usize;
+        let stride = ihdr.stride();
+        
+        if ihdr.interlace_method != 0 {
+            let mut pixels = self.decode_pixels()?;
+            pixels.truncate(stride * rows);
+            return Ok(pixels);
+        }
+        
+        let raw = inflate_zlib_prefix(&self.require_idat_data()?, (stride + 1) * rows)?;
+        let bpp = ((ihdr.bits_per_pixel() + 7) / 8).max(1);
+        unfilter_rows(&raw, stride, rows, bpp)
+    }
+    
+    /// 部分解码：IDAT被截断或损坏时返回已成功解码的行
+    /// 仅支持非交错图像，交错图像退化为完整解码
+    pub fn decode_pixels_partial(&self) -> Result<PartialDecode, PNGError> {
+        let ihdr = self.ihdr.as_ref()
+            .ok_or_else(|| PNGError::InvalidFormat("Missing IHDR chunk".to_string()))?;
+        
+        if ihdr.interlace_method != 0 {
+            return self.decode_pixels().map(|pixels| PartialDecode {
+                pixels,
+                decoded_rows: ihdr.height,
+            });
+        }
+        
+        let (raw, _) = inflate_zlib_partial(&self.idat_data());
+        let bpp = ((ihdr.bits_per_pixel() + 7) / 8).max(1);
+        let stride = ihdr.stride();
+        let size = stride.checked_mul(ihdr.height as usize)
+            .ok_or_else(|| PNGError::MemoryError(format!("Image size {}x{} overflows usize", ihdr.width, ihdr.height)))?;
+        let (mut pixels, decoded_rows, _) = unfilter_available_rows(&raw, stride, ihdr.height as usize, bpp);
+        pixels.resize(size, 0);
+        
+        Ok(PartialDecode {
+            pixels,
+            decoded_rows: decoded_rows as u32,
+        })
+    }
+    
+    /// 获取除IHDR/IDAT/IEND外需要原样保留的chunks
+    /// 按PNG规范的位置要求稳定排序：颜色空间chunk、PLTE、其余chunk，同一位置内保持文件顺序
+    /// （APNG的fcTL和fdAT依赖序列号顺序）；解析后直接存入、没有记录顺序的chunk排在各自位置的最后
+    pub fn passthrough_chunks(&self) -> Vec<PNGChunk> {
+        let is_passthrough = |chunk_type: &ChunkType| !matches!(chunk_type, ChunkType::IHDR | ChunkType::IDAT | ChunkType::IEND);
+        
+        let mut taken: HashMap<&ChunkType, usize> = HashMap::new();
+        let mut chunks = Vec::new();
+        for chunk_type in self.chunk_order.iter().filter(|chunk_type| is_passthrough(chunk_type)) {
+            let index = taken.entry(chunk_type).or_insert(0);
+            if let Some(chunk) = self.chunks.get(chunk_type).and_then(|chunks| chunks.get(*index)) {
+                chunks.push(chunk.clone());
+            }
+            *index += 1;
+        }
+        
+        let mut unordered: Vec<PNGChunk> = self.chunks.iter()
+            .filter(|(chunk_type, _)| is_passthrough(chunk_type))
+            .flat_map(|(chunk_type, chunks)| chunks.iter().skip(taken.get(chunk_type).copied().unwrap_or(0)).cloned())
+            .collect();
+        unordered.sort_by_key(|chunk| chunk.chunk_type.to_u32());
+        chunks.extend(unordered);
+        
+        chunks.sort_by_key(|chunk| chunk_placement_rank(&chunk.chunk_type));
+        chunks
+    }
+    
+    /// 去掉APNG的acTL、fcTL和fdAT，只保留IDAT中的默认图像
+    /// 无论第一个fcTL是否位于IDAT之前（默认图像是否属于动画），不支持APNG的查看器显示的都是IDAT图像
+    pub fn strip_animation(&mut self) {
+        self.chunks.retain(|chunk_type, _| !matches!(chunk_type, ChunkType::ACTL | ChunkType::FCTL | ChunkType::FDAT));
+        self.animation_control = None;
+        self.frame_controls.clear();
+    }
+    
+    /// 重新序列化为PNG数据
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut output = PNG_SIGNATURE.to_vec();
+        
+        if let Some(ref ihdr) = self.ihdr {
+            output.extend_from_slice(&PNGChunk::new(ChunkType::IHDR, ihdr.to_bytes()).to_bytes());
+        }
+        
+        let (after_idat, before_idat): (Vec<PNGChunk>, Vec<PNGChunk>) = self.passthrough_chunks()
+            .into_iter()
+            .partition(PNGChunk::is_after_idat);
+        for chunk in before_idat {
+            output.extend_from_slice(&chunk.to_bytes());
+        }
+        
+        if let Some(idat_chunks) = self.chunks.get(&ChunkType::IDAT) {
+            for chunk in idat_chunks {
+                output.extend_from_slice(&chunk.to_bytes());
+            }
+        }
+        
+        for chunk in after_idat {
+            output.extend_from_slice(&chunk.to_bytes());
+        }
+        
+        output.extend_from_slice(&PNGChunk::new(ChunkType::IEND, Vec::new()).to_bytes());
+        output
+    }
+}
+
+/// 逐行反滤镜，raw中每行以滤镜字节开头
+fn unfilter_rows(raw: &[u8], stride: usize, rows: usize, bpp: usize) -> Result<Vec<u8>, PNGError> {
+    match unfilter_available_rows(raw, stride, rows, bpp) {
+        (pixels, _, None) => Ok(pixels),
+        (_, _, Some(error)) => Err(error),
+    }
+}
+
+/// 逐行反滤镜直到数据耗尽或遇到错误
+/// 返回像素数据、成功解码的行数以及遇到的错误；只为raw中实际存在的行分配像素，不信任IHDR声明的行数
+fn unfilter_available_rows(raw: &[u8], stride: usize, rows: usize, bpp: usize) -> (Vec<u8>, usize, Option<PNGError>) {
+    let available = rows.min(raw.len() / (stride + 1));
+    let mut pixels = vec![0u8; stride * available];
+    
+    for y in 0..rows {
+        let line = match raw.get(y * (stride + 1)..(y + 1) * (stride + 1)) {
+            Some(line) => line,
+            None => return (pixels, y, Some(PNGError::InsufficientData(format!("Scanline {} is truncated", y)))),
+        };
+        
+        let (done, rest) = pixels.split_at_mut(y * stride);
+        let row = &mut rest[..stride];
+        row.copy_from_slice(&line[1..]);
+        
+        let prev_row = if y > 0 { Some(&done[(y - 1) * stride..]) } else { None };
+        if let Err(e) = unfilter_scanline(line[0], row, prev_row, bpp) {
+            row.fill(0);
+            return (pixels, y, Some(PNGError::DecodeError(e)));
+        }
+    }
+    
+    (pixels, rows, None)
+}
+
+/// chunk在IDAT之前的排列顺序
+/// 0: 必须位于PLTE之前的颜色空间chunk，1: PLTE，2: 其余chunk
+pub fn chunk_placement_rank(chunk_type: &ChunkType) -> u8 {
+    match chunk_type {
+        ChunkType::GAMA | ChunkType::CHRM | ChunkType::SRGB | ChunkType::ICCP => 0,
+        ChunkType::PLTE => 1,
+        _ => 2,
+    }
 }
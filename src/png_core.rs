@@ -0,0 +1,148 @@
+//! PNG核心解码逻辑
+//! 仅依赖core和alloc：CRC、Paeth预测、反滤镜和chunk切分
+//! 关闭std特性时库以no_std方式编译，只保留本模块和常量
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::constants::*;
+
+pub use crate::crc::{adler32, crc32, Adler32, Crc32};
+
+/// Paeth预测器算法
+/// 匹配原始pngjs库的paeth-predictor.js
+pub fn paeth_predictor(a: u8, b: u8, c: u8) -> u8 {
+    let p = (a as i16) + (b as i16) - (c as i16);
+    let pa = (p - (a as i16)).abs();
+    let pb = (p - (b as i16)).abs();
+    let pc = (p - (c as i16)).abs();
+    
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// 对单行扫描线反向应用滤镜（解码），原地重建像素
+/// prev_row为已重建的上一行，None表示首行（单行图像的Up/Average/Paeth按上一行全为0处理）
+/// 行宽小于bpp时（如单列图像）Sub不改变数据
+pub fn unfilter_scanline(filter_type: u8, row: &mut [u8], prev_row: Option<&[u8]>, bpp: usize) -> Result<(), String> {
+    if bpp == 0 {
+        return Err("Bytes per pixel must be at least 1".into());
+    }
+    if let Some(prev) = prev_row {
+        if prev.len() < row.len() {
+            return Err(format!("Previous row has {} bytes, expected {}", prev.len(), row.len()));
+        }
+    }
+    
+    match filter_type {
+        FILTER_NONE => {}
+        FILTER_SUB => {
+            for x in bpp..row.len() {
+                row[x] = row[x].wrapping_add(row[x - bpp]);
+            }
+        }
+        FILTER_UP => {
+            if let Some(prev) = prev_row {
+                for x in 0..row.len() {
+                    row[x] = row[x].wrapping_add(prev[x]);
+                }
+            }
+        }
+        FILTER_AVERAGE => {
+            for x in 0..row.len() {
+                let left = if x >= bpp { row[x - bpp] } else { 0 };
+                let up = prev_row.map_or(0, |prev| prev[x]);
+                row[x] = row[x].wrapping_add(((left as u16 + up as u16) / 2) as u8);
+            }
+        }
+        FILTER_PAETH => {
+            for x in 0..row.len() {
+                let left = if x >= bpp { row[x - bpp] } else { 0 };
+                let up = prev_row.map_or(0, |prev| prev[x]);
+                let up_left = if x >= bpp { prev_row.map_or(0, |prev| prev[x - bpp]) } else { 0 };
+                row[x] = row[x].wrapping_add(paeth_predictor(left, up, up_left));
+            }
+        }
+        _ => return Err(format!("Invalid filter type: {}", filter_type)),
+    }
+    
+    Ok(())
+}
+
+/// 对整幅图像反向应用滤镜（解码），原地重建像素
+/// data为去掉滤镜字节后的扫描线（每行 width * bytes_per_pixel 字节），filter_bytes为每行的滤镜类型
+pub fn defilter_image(data: &mut [u8], width: usize, height: usize, bytes_per_pixel: usize, filter_bytes: &[u8]) -> Result<(), String> {
+    let bytes_per_row = width.checked_mul(bytes_per_pixel)
+        .ok_or("Row size overflows usize")?;
+    let required = bytes_per_row.checked_mul(height)
+        .ok_or("Image size overflows usize")?;
+    
+    if data.len() < required {
+        return Err(format!("Expected {} bytes of scanline data, got {}", required, data.len()));
+    }
+    if filter_bytes.len() < height {
+        return Err(format!("Expected {} filter bytes, got {}", height, filter_bytes.len()));
+    }
+    
+    for (y, &filter_type) in filter_bytes[..height].iter().enumerate() {
+        // 已重建部分和当前行分开借用，上一行始终是done的最后一行
+        let (done, rest) = data.split_at_mut(y * bytes_per_row);
+        let prev_row = if y > 0 { Some(&done[done.len() - bytes_per_row..]) } else { None };
+        unfilter_scanline(filter_type, &mut rest[..bytes_per_row], prev_row, bytes_per_pixel)
+            .map_err(|e| format!("Row {}: {}", y, e))?;
+    }
+    
+    Ok(())
+}
+/// 切分出的原始chunk，数据借用自输入缓冲区
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawChunk<'a> {
+    pub chunk_type: [u8; 4],
+    pub data: &'a [u8],
+    pub crc: u32,
+    pub crc_ok: bool,
+}
+
+/// 校验PNG签名并按长度字段切分chunk，不解析chunk内容
+/// 遇到IEND后停止，数据截断时返回错误
+pub fn split_chunks(data: &[u8]) -> Result<Vec<RawChunk<'_>>, String> {
+    if data.len() < PNG_SIGNATURE.len() || data[..PNG_SIGNATURE.len()] != PNG_SIGNATURE {
+        return Err("Invalid PNG signature".into());
+    }
+    
+    let mut chunks = Vec::new();
+    let mut offset = PNG_SIGNATURE.len();
+    
+    while offset < data.len() {
+        let header = data.get(offset..offset + 8)
+            .ok_or("Insufficient data for chunk header")?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let chunk_type = [header[4], header[5], header[6], header[7]];
+        
+        let data_start = offset + 8;
+        let data_end = data_start.checked_add(length)
+            .filter(|end| end.checked_add(4).is_some_and(|crc_end| crc_end <= data.len()))
+            .ok_or_else(|| format!("Insufficient data for chunk at offset {}", offset))?;
+        
+        let crc = u32::from_be_bytes([
+            data[data_end], data[data_end + 1], data[data_end + 2], data[data_end + 3]
+        ]);
+        // CRC覆盖类型码和数据
+        let crc_ok = crc32(&data[offset + 4..data_end]) == crc;
+        
+        chunks.push(RawChunk { chunk_type, data: &data[data_start..data_end], crc, crc_ok });
+        offset = data_end + 4;
+        
+        if &chunk_type == b"IEND" {
+            break;
+        }
+    }
+    
+    Ok(chunks)
+}
@@ -1,40 +1,136 @@
 //! PNG打包器模块
 //! 实现PNG编码和打包功能，匹配原始pngjs库的packer.js
 
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::io::{Write, Cursor};
-use flate2::write::DeflateEncoder;
+use flate2::write::ZlibEncoder;
 use flate2::Compression;
 use crate::constants::*;
-use crate::crc::crc32;
-use crate::filter_extensible::*;
+use crate::crc::Crc32;
+use crate::filter::{filter_scanline, select_row_filters};
+use crate::png_chunks::{ChunkType, CHRMData, GAMAData, HISTData, PNGChunk, SRGBData, TRNSData, chunk_placement_rank};
 use crate::bitmap::*;
+use crate::bitmapper::{index_bit_depth, minimal_grayscale_depth, minimal_index_depth};
 
 /// PNG打包选项
 #[derive(Debug, Clone)]
 pub struct PackerOptions {
+    /// 压缩输出缓冲区的初始容量
     pub deflate_chunk_size: usize,
+    /// 每个IDAT chunk的最大数据长度，None表示只写一个IDAT
+    pub idat_chunk_size: Option<usize>,
     pub deflate_level: u8,
+    /// Deflate策略，取值同zlib (0-4)，通过滤镜选择近似实现
     pub deflate_strategy: u8,
     pub input_has_alpha: bool,
     pub bit_depth: u8,
     pub color_type: u8,
     pub input_color_type: u8,
+    /// 输入数据的位深度；为8而bit_depth低于8时，输入按每样本一字节提供，编码前按位打包
+    pub input_bit_depth: u8,
     pub width: u32,
     pub height: u32,
+    /// 固定使用的滤镜类型，None表示逐行自适应选择
+    pub filter_type: Option<u8>,
+    /// 自适应选择时允许尝试的滤镜（0-4），与deflate策略允许的滤镜取交集
+    pub candidate_filters: Vec<u8>,
+    /// 附加chunks（PLTE及辅助chunk），标记after_idat的辅助chunk写在IDAT之后，其余写在IDAT之前
+    pub chunks: Vec<PNGChunk>,
+    /// 调色板图像根据索引使用次数自动生成hIST
+    pub auto_histogram: bool,
+    /// 透明关键色或调色板alpha，按颜色类型和位深度校验后写入tRNS，替换chunks中的tRNS
+    pub transparency: Option<TRNSData>,
 }
 
 impl Default for PackerOptions {
     fn default() -> Self {
         Self {
             deflate_chunk_size: 32 * 1024,
+            idat_chunk_size: Some(32 * 1024),
             deflate_level: 9,
-            deflate_strategy: 3,
+            deflate_strategy: DEFLATE_STRATEGY_RLE,
             input_has_alpha: true,
             bit_depth: 8,
             color_type: COLORTYPE_COLOR_ALPHA,
             input_color_type: COLORTYPE_COLOR_ALPHA,
+            input_bit_depth: 8,
             width: 0,
             height: 0,
+            filter_type: None,
+            candidate_filters: vec![FILTER_NONE, FILTER_SUB, FILTER_UP, FILTER_AVERAGE, FILTER_PAETH],
+            chunks: Vec::new(),
+            auto_histogram: false,
+            transparency: None,
+        }
+    }
+}
+
+impl PackerOptions {
+    /// 可复现输出的固定配置
+    ///
+    /// 压缩级别9、DEFAULT策略、逐行自适应滤镜、32KB的IDAT分块。
+    /// flate2使用纯Rust的miniz_oxide后端，相同版本下不依赖平台的zlib实现，
+    /// 因此相同像素在任何机器上都产生逐字节相同的PNG
+    pub fn deterministic() -> Self {
+        Self {
+            deflate_level: 9,
+            deflate_strategy: DEFLATE_STRATEGY_DEFAULT,
+            filter_type: None,
+            idat_chunk_size: Some(32 * 1024),
+            ..Default::default()
+        }
+    }
+
+    /// 根据deflate策略确定候选滤镜
+    ///
+    /// flate2/miniz_oxide不支持直接设置zlib策略，这里用滤镜选择近似：
+    /// - DEFAULT / FIXED: 尝试全部五种滤镜
+    /// - FILTERED: 只尝试Sub/Up/Average/Paeth，数据总是经过滤镜
+    /// - HUFFMAN_ONLY: 只用None，并使用最快的压缩级别
+    /// - RLE: 只尝试None/Up，纯色或合成图像会产生大量重复字节；所有行相同时统一用None
+    pub fn strategy_filters(&self) -> &'static [u8] {
+        match self.deflate_strategy {
+            DEFLATE_STRATEGY_FILTERED => &[FILTER_SUB, FILTER_UP, FILTER_AVERAGE, FILTER_PAETH],
+            DEFLATE_STRATEGY_HUFFMAN_ONLY => &[FILTER_NONE],
+            DEFLATE_STRATEGY_RLE => &[FILTER_NONE, FILTER_UP],
+            _ => &[FILTER_NONE, FILTER_SUB, FILTER_UP, FILTER_AVERAGE, FILTER_PAETH],
+        }
+    }
+
+    /// 自适应滤镜实际尝试的候选：candidate_filters中被deflate策略允许的部分
+    /// 候选为空、含非法滤镜类型或与策略没有交集时返回错误
+    pub fn adaptive_filters(&self) -> Result<Vec<u8>, String> {
+        if self.candidate_filters.is_empty() {
+            return Err("Candidate filter set is empty".to_string());
+        }
+        if let Some(&invalid) = self.candidate_filters.iter().find(|&&filter_type| filter_type > FILTER_PAETH) {
+            return Err(format!("Invalid candidate filter type: {}", invalid));
+        }
+        
+        let filters: Vec<u8> = self.strategy_filters().iter()
+            .copied()
+            .filter(|filter_type| self.candidate_filters.contains(filter_type))
+            .collect();
+        if filters.is_empty() {
+            return Err(format!(
+                "Candidate filters {:?} exclude every filter allowed by deflate strategy {}",
+                self.candidate_filters, self.deflate_strategy
+            ));
+        }
+        
+        Ok(filters)
+    }
+
+    /// 根据压缩级别和策略确定flate2压缩参数
+    pub fn compression(&self) -> Compression {
+        if self.filter_type == Some(FILTER_NONE) {
+            return Compression::fast();
+        }
+
+        match self.deflate_strategy {
+            DEFLATE_STRATEGY_HUFFMAN_ONLY => Compression::fast(),
+            _ => Compression::new(self.deflate_level.min(9) as u32),
         }
     }
 }
@@ -52,27 +148,84 @@ impl PNGPacker {
     /// 打包PNG数据
     pub fn pack(&self, data: &[u8]) -> Result<Vec<u8>, String> {
         let mut output = Vec::new();
+        self.pack_to(data, &mut output)?;
+        Ok(output)
+    }
+    
+    /// 打包PNG数据，按已滤镜的扫描线比例（0.0-1.0）回调进度
+    /// 约每2%调用一次，总次数不超过 PROGRESS_STEPS + 1
+    pub fn pack_with_progress<F: FnMut(f64)>(&self, data: &[u8], mut on_progress: F) -> Result<Vec<u8>, String> {
+        let mut output = Vec::new();
+        self.write_png(data, &mut output, &mut on_progress)?;
+        Ok(output)
+    }
+    
+    /// 打包PNG数据并直接写入output
+    /// 扫描线逐行滤镜、压缩，压缩输出每满idat_chunk_size就写出一个IDAT，
+    /// 峰值内存只与chunk大小和扫描线长度有关，与图像大小无关
+    pub fn pack_to<W: Write>(&self, data: &[u8], output: &mut W) -> Result<(), String> {
+        self.write_png(data, output, &mut |_| {})
+    }
+    
+    fn write_png<W: Write>(&self, data: &[u8], output: &mut W, on_progress: &mut dyn FnMut(f64)) -> Result<(), String> {
+        let data = self.prepare_samples(data)?;
+        let data = &data[..];
         
         // 写入PNG签名
-        output.extend_from_slice(&PNG_SIGNATURE);
+        output.write_all(&PNG_SIGNATURE).map_err(|e| e.to_string())?;
         
         // 写入IHDR chunk
-        self.write_ihdr_chunk(&mut output)?;
+        self.write_ihdr_chunk(output)?;
         
-        // 处理像素数据
-        let processed_data = self.process_pixel_data(data)?;
+        // 写入附加chunks
+        self.write_extra_chunks(output, data)?;
         
-        // 写入IDAT chunks
-        self.write_idat_chunks(&mut output, &processed_data)?;
+        // 处理像素数据并写入IDAT chunks
+        self.write_image_data(output, data, on_progress)?;
+        
+        // 写入位于IDAT之后的辅助chunks
+        for chunk in self.options.chunks.iter().filter(|chunk| chunk.is_after_idat()) {
+            write_chunk(output, chunk.chunk_type.to_u32(), &chunk.data)?;
+        }
         
         // 写入IEND chunk
-        self.write_iend_chunk(&mut output)?;
+        self.write_iend_chunk(output)?;
         
-        Ok(output)
+        Ok(())
+    }
+    
+    /// 每样本一字节的低位深度数据先按位打包，其余数据原样使用
+    fn prepare_samples<'a>(&self, data: &'a [u8]) -> Result<Cow<'a, [u8]>, String> {
+        if self.options.bit_depth < 8 && self.options.input_bit_depth == 8 {
+            Ok(Cow::Owned(self.pack_samples(data)?))
+        } else {
+            Ok(Cow::Borrowed(data))
+        }
+    }
+    
+    /// 将每样本一字节的灰度或调色板数据按bit_depth打包为扫描线
+    fn pack_samples(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let samples = self.options.width as usize * self.options.height as usize;
+        if data.len() < samples {
+            return Err("Insufficient pixel data".to_string());
+        }
+        let data = &data[..samples];
+        
+        if let Some(&max) = data.iter().max() {
+            if max >> self.options.bit_depth != 0 {
+                return Err(format!("Sample value {} does not fit in {} bits", max, self.options.bit_depth));
+            }
+        }
+        
+        BitPacker::new(self.options.bit_depth, self.options.color_type).pack_bits(data, self.options.width, self.options.height)
     }
     
     /// 写入IHDR chunk
-    fn write_ihdr_chunk(&self, output: &mut Vec<u8>) -> Result<(), String> {
+    fn write_ihdr_chunk<W: Write>(&self, output: &mut W) -> Result<(), String> {
+        if !is_valid_color_bitdepth(self.options.color_type, self.options.bit_depth) {
+            return Err(format!("Invalid bit depth {} for color type {}", self.options.bit_depth, self.options.color_type));
+        }
+        
         let mut ihdr_data = Vec::new();
         
         // 写入IHDR数据
@@ -85,38 +238,103 @@ impl PNGPacker {
         ihdr_data.write_all(&[0]).map_err(|e| e.to_string())?; // interlace method
         
         // 写入chunk
-        self.write_chunk(output, TYPE_IHDR, &ihdr_data)?;
+        write_chunk(output, TYPE_IHDR, &ihdr_data)?;
         
         Ok(())
     }
     
-    /// 处理像素数据
-    fn process_pixel_data(&self, data: &[u8]) -> Result<Vec<u8>, String> {
-        let bytes_per_row = self.calculate_bytes_per_row();
-        let mut processed_data = Vec::new();
+    /// 滤镜并压缩像素数据，以IDAT chunks写出
+    fn write_image_data<W: Write>(&self, output: &mut W, data: &[u8], on_progress: &mut dyn FnMut(f64)) -> Result<(), String> {
+        let idat_writer = IdatWriter::new(output, self.options.idat_chunk_size, self.options.deflate_chunk_size);
+        let mut encoder = ZlibEncoder::new(idat_writer, self.options.compression());
+        self.write_scanlines(&mut encoder, data, on_progress)?;
+        encoder.finish().map_err(|e| e.to_string())?.finish()
+    }
+    
+    /// 滤镜后、压缩前的扫描线流：每行一个滤镜字节加滤镜后的数据
+    /// 滤镜选择与pack相同，可以交给外部压缩器（如zopfli）处理
+    pub fn filtered_scanlines(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let data = self.prepare_samples(data)?;
         
-        // 按行处理数据
-        for y in 0..self.options.height {
-            let row_start = (y * bytes_per_row) as usize;
-            let row_end = row_start + bytes_per_row;
-            
-            if row_end > data.len() {
-                return Err("Insufficient pixel data".to_string());
+        let mut output = Vec::new();
+        self.write_scanlines(&mut output, &data, &mut |_| {})?;
+        Ok(output)
+    }
+    
+    /// 不实际压缩，估算pack输出中IDAT数据的总大小，见 [`estimate_deflate_size`]
+    pub fn estimate_compressed_size(&self, data: &[u8]) -> Result<u64, String> {
+        Ok(estimate_deflate_size(&self.filtered_scanlines(data)?))
+    }
+    
+    /// 按filter_type或自适应选择滤镜，逐行写出滤镜字节和滤镜后的数据
+    fn write_scanlines<W: Write>(&self, output: &mut W, data: &[u8], on_progress: &mut dyn FnMut(f64)) -> Result<(), String> {
+        if self.options.width == 0 || self.options.height == 0 {
+            return Err(format!("Invalid image size {}x{}", self.options.width, self.options.height));
+        }
+        let bytes_per_row = self.calculate_bytes_per_row() as usize;
+        let required = bytes_per_row * self.options.height as usize;
+        
+        if data.len() < required {
+            return Err("Insufficient pixel data".to_string());
+        }
+        
+        match self.options.filter_type {
+            // 快速路径：不滤镜，逐行直接写入
+            Some(FILTER_NONE) => {
+                let rows = self.options.height as usize;
+                for (y, row_data) in data[..required].chunks_exact(bytes_per_row.max(1)).enumerate() {
+                    output.write_all(&[FILTER_NONE]).map_err(|e| e.to_string())?;
+                    output.write_all(row_data).map_err(|e| e.to_string())?;
+                    report_progress(on_progress, y, rows);
+                }
             }
+            Some(filter_type) => {
+                self.write_filtered_rows(output, &data[..required], bytes_per_row, &[filter_type], on_progress)?;
+            }
+            None => {
+                let candidates = self.options.adaptive_filters()?;
+                self.write_filtered_rows(output, &data[..required], bytes_per_row, &candidates, on_progress)?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// 逐行在候选滤镜中选择代价最小的并写入压缩器
+    fn write_filtered_rows<W: Write>(&self, output: &mut W, data: &[u8], bytes_per_row: usize, candidates: &[u8], on_progress: &mut dyn FnMut(f64)) -> Result<(), String> {
+        let bpp = self.get_bytes_per_pixel();
+        let filters = self.row_filters(data, bytes_per_row, candidates);
+        let mut filtered = Vec::with_capacity(bytes_per_row);
+        
+        // 按行处理数据
+        for (y, &filter_type) in filters.iter().enumerate() {
+            let row_start = y * bytes_per_row;
+            let row_data = &data[row_start..row_start + bytes_per_row];
+            let prev_row = if y > 0 { Some(&data[row_start - bytes_per_row..row_start]) } else { None };
             
-            let row_data = &data[row_start..row_end];
-            
-            // 选择最佳滤镜
-            let best_filter = self.choose_best_filter(row_data, y as usize);
-            processed_data.push(best_filter);
-            
-            // 应用滤镜
-            let filtered_row = self.apply_filter(row_data, best_filter, y as usize)?;
-            processed_data.extend_from_slice(&filtered_row);
+            filter_scanline(filter_type, row_data, prev_row, bpp, &mut filtered);
+            output.write_all(&[filter_type]).map_err(|e| e.to_string())?;
+            output.write_all(&filtered).map_err(|e| e.to_string())?;
+            report_progress(on_progress, y, filters.len());
+        }
+        
+        Ok(())
+    }
+    
+    /// 确定每行使用的滤镜
+    ///
+    /// RLE策略下若每行都与上一行相同，整幅图统一用None：滤镜字节随像素一起按行重复，
+    /// deflate用跨行匹配覆盖全部后续行，比首行None、其余行Up的零字节串更短
+    fn row_filters(&self, data: &[u8], bytes_per_row: usize, candidates: &[u8]) -> Vec<u8> {
+        let first_row = &data[..bytes_per_row];
+        if self.options.deflate_strategy == DEFLATE_STRATEGY_RLE
+            && candidates.contains(&FILTER_NONE)
+            && data.chunks_exact(bytes_per_row).all(|row| row == first_row)
+        {
+            return vec![FILTER_NONE; data.len() / bytes_per_row];
         }
         
-        // 压缩数据
-        self.compress_data(&processed_data)
+        select_row_filters(data, bytes_per_row, self.get_bytes_per_pixel(), candidates)
     }
     
     /// 计算每行字节数
@@ -125,7 +343,7 @@ impl PNGPacker {
             COLORTYPE_GRAYSCALE => self.options.bit_depth,
             COLORTYPE_COLOR => self.options.bit_depth * 3,
             COLORTYPE_PALETTE_COLOR => self.options.bit_depth,
-            COLORTYPE_GRAYSCALE | COLORTYPE_ALPHA => self.options.bit_depth * 2,
+            COLORTYPE_GRAYSCALE_ALPHA => self.options.bit_depth * 2,
             COLORTYPE_COLOR_ALPHA => self.options.bit_depth * 4,
             _ => 8,
         };
@@ -133,98 +351,767 @@ impl PNGPacker {
         ((self.options.width * bits_per_pixel as u32 + 7) / 8) as u32
     }
     
-    /// 选择最佳滤镜
-    fn choose_best_filter(&self, row_data: &[u8], row_index: usize) -> u8 {
-        let context = FilterContext {
-            width: self.options.width as usize,
-            height: self.options.height as usize,
-            bytes_per_pixel: self.get_bytes_per_pixel(),
-            row_index,
-            column_index: 0,
-            previous_row: None,
-        };
-        
-        let processor = FilterProcessor::new();
-        processor.choose_best_filter(row_data, &context).unwrap_or(FILTER_NONE)
-    }
-    
-    /// 获取每像素字节数
+    /// 获取滤镜使用的每像素字节数（不足1字节按1计算）
     fn get_bytes_per_pixel(&self) -> usize {
-        match self.options.color_type {
+        let channels = match self.options.color_type {
             COLORTYPE_GRAYSCALE => 1,
             COLORTYPE_COLOR => 3,
             COLORTYPE_PALETTE_COLOR => 1,
-            COLORTYPE_GRAYSCALE | COLORTYPE_ALPHA => 2,
+            COLORTYPE_GRAYSCALE_ALPHA => 2,
             COLORTYPE_COLOR_ALPHA => 4,
             _ => 4,
+        };
+        
+        ((channels * self.options.bit_depth as usize + 7) / 8).max(1)
+    }
+    
+    /// 按规范顺序写入附加chunks
+    fn write_extra_chunks<W: Write>(&self, output: &mut W, data: &[u8]) -> Result<(), String> {
+        let find_chunk = |chunk_type: ChunkType| self.options.chunks.iter().find(|chunk| chunk.chunk_type == chunk_type);
+        if find_chunk(ChunkType::SRGB).is_some() && find_chunk(ChunkType::ICCP).is_some() {
+            return Err("sRGB and iCCP chunks are mutually exclusive".to_string());
+        }
+        
+        let palette = find_chunk(ChunkType::PLTE);
+        if palette.is_none() && (self.options.auto_histogram || find_chunk(ChunkType::HIST).is_some()) {
+            return Err("hIST chunk requires a PLTE chunk".to_string());
         }
+        
+        let histogram = match palette {
+            Some(palette) if self.options.auto_histogram && self.options.color_type == COLORTYPE_PALETTE_COLOR && find_chunk(ChunkType::HIST).is_none() => {
+                let indices = unpack_indices(data, self.options.width as usize, self.options.height as usize, self.options.bit_depth);
+                let histogram = HISTData::from_indices(&indices, palette.data.len() / 3);
+                Some(PNGChunk::new(ChunkType::HIST, histogram.to_bytes()))
+            }
+            _ => None,
+        };
+        
+        let transparency = self.transparency_chunk(palette)?;
+        let mut chunks: Vec<&PNGChunk> = self.options.chunks.iter()
+            .filter(|chunk| transparency.is_none() || chunk.chunk_type != ChunkType::TRNS)
+            .chain(histogram.as_ref())
+            .chain(transparency.as_ref())
+            .filter(|chunk| !chunk.is_after_idat())
+            .collect();
+        chunks.sort_by_key(|chunk| chunk_placement_rank(&chunk.chunk_type));
+        
+        for chunk in chunks {
+            write_chunk(output, chunk.chunk_type.to_u32(), &chunk.data)?;
+        }
+        
+        Ok(())
     }
     
-    /// 应用滤镜
-    fn apply_filter(&self, row_data: &[u8], filter_type: u8, row_index: usize) -> Result<Vec<u8>, String> {
-        let mut filtered_data = row_data.to_vec();
-        let context = FilterContext {
-            width: self.options.width as usize,
-            height: self.options.height as usize,
-            bytes_per_pixel: self.get_bytes_per_pixel(),
-            row_index,
-            column_index: 0,
-            previous_row: None,
+    /// 根据transparency生成tRNS chunk
+    /// 关键色的每个样本固定占2字节，取值不能超过位深度的范围；调色板alpha不能多于调色板条目，
+    /// 写出时截断到最后一个非不透明条目（之后的条目默认不透明），全部不透明时不写tRNS
+    fn transparency_chunk(&self, palette: Option<&PNGChunk>) -> Result<Option<PNGChunk>, String> {
+        let transparency = match &self.options.transparency {
+            Some(transparency) => transparency,
+            None => return Ok(None),
         };
         
-        let processor = FilterProcessor::new();
-        processor.apply_filter(filter_type, &mut filtered_data, &context)?;
+        let max_sample = ((1u32 << self.options.bit_depth) - 1) as u16;
+        match (transparency, self.options.color_type) {
+            (TRNSData::Grayscale { value }, COLORTYPE_GRAYSCALE) => {
+                if *value > max_sample {
+                    return Err(format!("tRNS gray value {} exceeds {}-bit range", value, self.options.bit_depth));
+                }
+            }
+            (TRNSData::RGB { r, g, b }, COLORTYPE_COLOR) => {
+                if [r, g, b].iter().any(|&&sample| sample > max_sample) {
+                    return Err(format!("tRNS color ({}, {}, {}) exceeds {}-bit range", r, g, b, self.options.bit_depth));
+                }
+            }
+            (TRNSData::Palette { alpha }, COLORTYPE_PALETTE_COLOR) => {
+                let entries = palette.ok_or("Palette tRNS requires a PLTE chunk")?.data.len() / 3;
+                if alpha.len() > entries {
+                    return Err(format!("tRNS has {} entries but palette has only {}", alpha.len(), entries));
+                }
+                let used = alpha.iter().rposition(|&value| value != 255).map_or(0, |last| last + 1);
+                if used == 0 {
+                    return Ok(None);
+                }
+                return Ok(Some(PNGChunk::new(ChunkType::TRNS, alpha[..used].to_vec())));
+            }
+            _ => return Err(format!("tRNS does not match color type {}", self.options.color_type)),
+        }
         
-        Ok(filtered_data)
+        Ok(Some(PNGChunk::new(ChunkType::TRNS, transparency.to_bytes())))
     }
     
-    /// 压缩数据
-    fn compress_data(&self, data: &[u8]) -> Result<Vec<u8>, String> {
-        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::new(self.options.deflate_level as u32));
-        encoder.write_all(data).map_err(|e| e.to_string())?;
-        encoder.finish().map_err(|e| e.to_string())
+    /// 写入IEND chunk
+    fn write_iend_chunk<W: Write>(&self, output: &mut W) -> Result<(), String> {
+        write_chunk(output, TYPE_IEND, &[])?;
+        Ok(())
     }
     
-    /// 写入IDAT chunks
-    fn write_idat_chunks(&self, output: &mut Vec<u8>, data: &[u8]) -> Result<(), String> {
-        let chunk_size = self.options.deflate_chunk_size;
+}
+
+// 估算时计入的最短匹配长度和每个匹配的代价（长度码+距离码，约20位）
+const ESTIMATE_MIN_MATCH: usize = 6;
+const ESTIMATE_MATCH_BITS: f64 = 20.0;
+
+/// 估算zlib压缩后的大小：贪心查找32KB窗口内的重复串，其余字节按0阶熵计算
+/// 比实际deflate快得多。照片类内容通常比实际结果小5%~15%，
+/// 大面积重复的纹理和图形误差一般在20%以内
+pub fn estimate_deflate_size(data: &[u8]) -> u64 {
+    const HASH_BITS: u32 = 15;
+    const WINDOW: usize = 32768;
+    
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut counts = [0u64; 256];
+    let mut matches = 0u64;
+    let mut i = 0;
+    while i < data.len() {
+        if i + ESTIMATE_MIN_MATCH <= data.len() {
+            let key = u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+            let hash = (key.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize;
+            let candidate = std::mem::replace(&mut head[hash], i);
+            if candidate != usize::MAX && i - candidate <= WINDOW {
+                let length = data[i..].iter().zip(&data[candidate..]).take(258)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if length >= ESTIMATE_MIN_MATCH {
+                    matches += 1;
+                    i += length;
+                    continue;
+                }
+            }
+        }
+        counts[data[i] as usize] += 1;
+        i += 1;
+    }
+    
+    let literals: u64 = counts.iter().sum();
+    let literal_bits: f64 = counts.iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| -(count as f64) * (count as f64 / literals as f64).log2())
+        .sum();
+    let bits = literal_bits + matches as f64 * ESTIMATE_MATCH_BITS;
+    
+    // zlib头2字节，Adler-32校验4字节
+    (bits / 8.0).ceil() as u64 + 6
+}
+
+/// 进度回调的最大间隔数，约每2%回调一次
+pub const PROGRESS_STEPS: usize = 50;
+
+/// 第row行处理完后按间隔回调进度，最后一行总是回调1.0
+fn report_progress(on_progress: &mut dyn FnMut(f64), row: usize, rows: usize) {
+    let step = rows.div_ceil(PROGRESS_STEPS).max(1);
+    let done = row + 1;
+    if done % step == 0 || done == rows {
+        on_progress(done as f64 / rows as f64);
+    }
+}
+
+/// 写入一个完整的chunk：长度、类型、数据和CRC
+fn write_chunk<W: Write>(output: &mut W, chunk_type: u32, data: &[u8]) -> Result<(), String> {
+    // 写入长度
+    output.write_all(&(data.len() as u32).to_be_bytes()).map_err(|e| e.to_string())?;
+    
+    // 写入chunk类型
+    output.write_all(&chunk_type.to_be_bytes()).map_err(|e| e.to_string())?;
+    
+    // 写入数据
+    output.write_all(data).map_err(|e| e.to_string())?;
+    
+    // 计算并写入CRC，类型码和数据分别输入，不需要拼接
+    let mut crc = Crc32::new();
+    crc.update(&chunk_type.to_be_bytes());
+    crc.update(data);
+    output.write_all(&crc.finalize().to_be_bytes()).map_err(|e| e.to_string())?;
+    
+    Ok(())
+}
+
+/// 压缩输出的IDAT切分器
+/// 压缩数据每累积满chunk_size字节就写出一个IDAT，chunk_size为None时只在结束时写出一个IDAT
+struct IdatWriter<'a, W: Write> {
+    output: &'a mut W,
+    buffer: Vec<u8>,
+    chunk_size: Option<usize>,
+    chunks_written: usize,
+}
+
+impl<'a, W: Write> IdatWriter<'a, W> {
+    fn new(output: &'a mut W, chunk_size: Option<usize>, capacity: usize) -> Self {
+        Self {
+            output,
+            buffer: Vec::with_capacity(capacity),
+            chunk_size: chunk_size.filter(|&size| size > 0),
+            chunks_written: 0,
+        }
+    }
+    
+    /// 写出剩余数据，至少保证有一个IDAT
+    fn finish(self) -> Result<(), String> {
+        if !self.buffer.is_empty() || self.chunks_written == 0 {
+            write_chunk(self.output, TYPE_IDAT, &self.buffer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for IdatWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
         
-        for chunk in data.chunks(chunk_size) {
-            self.write_chunk(output, TYPE_IDAT, chunk)?;
+        if let Some(chunk_size) = self.chunk_size {
+            while self.buffer.len() >= chunk_size {
+                write_chunk(self.output, TYPE_IDAT, &self.buffer[..chunk_size])
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+                self.buffer.drain(..chunk_size);
+                self.chunks_written += 1;
+            }
         }
         
-        Ok(())
+        Ok(buf.len())
     }
     
-    /// 写入IEND chunk
-    fn write_iend_chunk(&self, output: &mut Vec<u8>) -> Result<(), String> {
-        self.write_chunk(output, TYPE_IEND, &[])?;
+    fn flush(&mut self) -> std::io::Result<()> {
         Ok(())
     }
+}
+
+/// PNG编码器
+/// 基于PackerOptions的链式配置接口
+#[derive(Debug, Clone)]
+pub struct PngEncoder {
+    options: PackerOptions,
+    // 根据RGBA输入内容自动选择颜色类型
+    auto_color_type: bool,
+    // 灰度和调色板图像使用无损的最小位深度
+    minimal_bit_depth: bool,
+    // alpha二值化阈值，启用时用tRNS关键色代替alpha通道
+    binarize_alpha: Option<u8>,
+    // 最大压缩模式下额外尝试的压缩配置数量
+    max_compression: Option<u32>,
+    // 链式配置中产生的错误，编码时返回
+    config_error: Option<String>,
+}
+
+impl PngEncoder {
+    /// 创建RGBA 8位编码器
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            options: PackerOptions {
+                width,
+                height,
+                ..Default::default()
+            },
+            auto_color_type: false,
+            minimal_bit_depth: false,
+            binarize_alpha: None,
+            max_compression: None,
+            config_error: None,
+        }
+    }
+    
+    /// 设置输出颜色类型和位深度（输入数据需为相同格式）
+    /// 低于8位的灰度和调色板数据按每样本一字节输入，编码时按位打包
+    pub fn with_color_type(mut self, color_type: u8, bit_depth: u8) -> Self {
+        self.options.color_type = color_type;
+        self.options.input_color_type = color_type;
+        self.options.bit_depth = bit_depth;
+        self
+    }
+    
+    /// 设置输入数据的颜色类型，与输出颜色类型不同时编码前转换为输出格式（目前仅支持转换为RGBA）
+    /// 灰度+Alpha输入直接编码为灰度+Alpha PNG，不经过RGB展开
+    pub fn with_input_color_type(mut self, color_type: u8) -> Self {
+        self.options.input_color_type = color_type;
+        if color_type == COLORTYPE_GRAYSCALE_ALPHA {
+            self.options.color_type = color_type;
+        }
+        self
+    }
     
-    /// 写入chunk
-    fn write_chunk(&self, output: &mut Vec<u8>, chunk_type: u32, data: &[u8]) -> Result<(), String> {
-        // 写入长度
-        output.write_all(&(data.len() as u32).to_be_bytes()).map_err(|e| e.to_string())?;
+    /// 快速模式：跳过滤镜选择并使用最快的压缩级别
+    /// 适合纯色填充、UI素材等机器生成的图像
+    pub fn fast(mut self) -> Self {
+        self.options.filter_type = Some(FILTER_NONE);
+        self
+    }
+    
+    /// 最大压缩模式：除当前配置外，再以压缩级别9尝试最多iterations种滤镜/策略组合，
+    /// 保留最小的输出。输出不会大于当前配置的结果，解码像素相同，但编码耗时成倍增加
+    pub fn with_max_compression(mut self, iterations: u32) -> Self {
+        self.max_compression = Some(iterations);
+        self
+    }
+    
+    /// 限制自适应滤镜选择时尝试的滤镜，减少候选可以显著加快编码
+    pub fn with_candidate_filters(mut self, filters: &[u8]) -> Self {
+        self.options.candidate_filters = filters.to_vec();
+        self
+    }
+    
+    /// 使用可复现的固定压缩配置，见 [`PackerOptions::deterministic`]
+    pub fn deterministic(mut self) -> Self {
+        let preset = PackerOptions::deterministic();
+        self.options.deflate_level = preset.deflate_level;
+        self.options.deflate_strategy = preset.deflate_strategy;
+        self.options.filter_type = preset.filter_type;
+        self.options.idat_chunk_size = preset.idat_chunk_size;
+        self
+    }
+    
+    /// 写入标准sRGB色彩管理chunks：sRGB（感知意图）、gAMA和cHRM
+    /// 与自定义iCCP互斥，同时存在时编码失败
+    pub fn with_srgb(self) -> Self {
+        self.with_srgb_intent(SRGB_INTENT_PERCEPTUAL)
+    }
+    
+    /// 同with_srgb，使用指定的渲染意图（0感知、1相对色度、2饱和度、3绝对色度），超出范围时编码失败
+    pub fn with_srgb_intent(mut self, rendering_intent: u8) -> Self {
+        if rendering_intent > SRGB_INTENT_ABSOLUTE {
+            self.config_error.get_or_insert(format!("Invalid sRGB rendering intent: {}", rendering_intent));
+            return self;
+        }
         
-        // 写入chunk类型
-        output.write_all(&chunk_type.to_be_bytes()).map_err(|e| e.to_string())?;
+        self.options.chunks.retain(|chunk| {
+            !matches!(chunk.chunk_type, ChunkType::SRGB | ChunkType::GAMA | ChunkType::CHRM)
+        });
+        self.options.chunks.push(PNGChunk::new(ChunkType::SRGB, SRGBData { rendering_intent }.to_bytes()));
+        self.options.chunks.push(PNGChunk::new(ChunkType::GAMA, GAMAData { gamma: SRGB_GAMMA }.to_bytes()));
+        self.options.chunks.push(PNGChunk::new(ChunkType::CHRM, CHRMData::srgb().to_bytes()));
+        self
+    }
+    
+    /// 设置调色板（每3字节一个RGB条目），写入PLTE chunk
+    pub fn with_palette(mut self, palette: &[u8]) -> Self {
+        self.options.chunks.retain(|chunk| chunk.chunk_type != ChunkType::PLTE);
+        self.options.chunks.push(PNGChunk::new(ChunkType::PLTE, palette.to_vec()));
+        self
+    }
+    
+    /// 设置透明关键色（灰度/RGB，按输出位深度取值）或调色板alpha，编码时写入tRNS
+    pub fn with_transparency(mut self, transparency: TRNSData) -> Self {
+        self.options.transparency = Some(transparency);
+        self
+    }
+    
+    /// 调色板图像自动写入hIST（需在chunks中提供PLTE）
+    pub fn with_histogram(mut self) -> Self {
+        self.options.auto_histogram = true;
+        self
+    }
+    
+    /// 添加自定义chunk（如私有的"myAp"），写在IDAT之前，CRC在写出时计算
+    /// 类型码必须是4个ASCII字母，且为辅助chunk（首字母小写）、保留位为大写（第三个字母），
+    /// 不符合时编码失败
+    pub fn add_raw_chunk(mut self, name: &str, data: &[u8]) -> Self {
+        match ChunkType::from_name(name).and_then(|chunk_type| {
+            if !chunk_type.is_ancillary() {
+                Err(format!("Raw chunk {:?} must be ancillary (lowercase first letter)", name))
+            } else if !chunk_type.is_reserved_bit_valid() {
+                Err(format!("Raw chunk {:?} must have an uppercase third letter", name))
+            } else {
+                Ok(chunk_type)
+            }
+        }) {
+            Ok(chunk_type) => self.options.chunks.push(PNGChunk::new(chunk_type, data.to_vec())),
+            Err(e) => {
+                self.config_error.get_or_insert(e);
+            }
+        }
+        self
+    }
+    
+    /// 获取编码选项
+    pub fn options(&self) -> &PackerOptions {
+        &self.options
+    }
+    
+    /// 编码像素数据为PNG
+    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        if let Some(e) = &self.config_error {
+            return Err(e.clone());
+        }
+        if self.auto_color_type {
+            return self.encode_auto(data);
+        }
         
-        // 写入数据
-        output.write_all(data).map_err(|e| e.to_string())?;
+        let mut options = self.options.clone();
+        let normalized = if options.input_color_type != options.color_type {
+            let normalizer = FormatNormalizer::new(options.input_color_type, options.color_type, options.bit_depth, options.bit_depth);
+            options.input_color_type = options.color_type;
+            Some(normalizer.normalize(data, options.width, options.height)?)
+        } else {
+            None
+        };
+        let data = normalized.as_deref().unwrap_or(data);
         
-        // 计算并写入CRC
-        let mut crc_data = Vec::new();
-        crc_data.write_all(&chunk_type.to_be_bytes()).map_err(|e| e.to_string())?;
-        crc_data.write_all(data).map_err(|e| e.to_string())?;
+        let binarized = match self.binarize_alpha {
+            Some(threshold) => binarize_alpha(&mut options, data, threshold)?,
+            None => None,
+        };
+        let data = binarized.as_deref().unwrap_or(data);
         
-        let crc = crc32(&crc_data);
-        output.write_all(&crc.to_be_bytes()).map_err(|e| e.to_string())?;
+        if self.minimal_bit_depth && options.bit_depth == 8 {
+            let data = reduce_bit_depth(&mut options, data)?;
+            return self.pack(options, &data);
+        }
         
-        Ok(())
+        self.pack(options, data)
+    }
+    
+    /// 按配置打包，最大压缩模式下依次尝试候选配置并保留最小的输出
+    fn pack(&self, options: PackerOptions, data: &[u8]) -> Result<Vec<u8>, String> {
+        let iterations = match self.max_compression {
+            Some(iterations) => iterations as usize,
+            None => return PNGPacker::new(options).pack(data),
+        };
+        
+        let fixed_filters = [FILTER_NONE, FILTER_SUB, FILTER_UP, FILTER_AVERAGE, FILTER_PAETH]
+            .into_iter()
+            .map(|filter_type| (Some(filter_type), DEFLATE_STRATEGY_DEFAULT));
+        let adaptive = [DEFLATE_STRATEGY_DEFAULT, DEFLATE_STRATEGY_FILTERED, DEFLATE_STRATEGY_RLE]
+            .into_iter()
+            .map(|strategy| (None, strategy));
+        
+        let mut best = PNGPacker::new(options.clone()).pack(data)?;
+        for (filter_type, deflate_strategy) in adaptive.chain(fixed_filters).take(iterations) {
+            let candidate = PackerOptions {
+                deflate_level: 9,
+                deflate_strategy,
+                filter_type,
+                candidate_filters: vec![FILTER_NONE, FILTER_SUB, FILTER_UP, FILTER_AVERAGE, FILTER_PAETH],
+                ..options.clone()
+            };
+            let packed = PNGPacker::new(candidate).pack(data)?;
+            if packed.len() < best.len() {
+                best = packed;
+            }
+        }
+        
+        Ok(best)
+    }
+    
+    /// 将掩码编码为1位灰度PNG：0为黑色，非0为白色
+    /// 每行单独按位打包，宽度不是8的倍数时行末补0位
+    pub fn encode_bilevel(mask: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
+        let pixels = (width as usize).checked_mul(height as usize)
+            .ok_or_else(|| format!("Image size {}x{} is too large", width, height))?;
+        if mask.len() != pixels {
+            return Err(format!("Mask length {} does not match {}x{}", mask.len(), width, height));
+        }
+        
+        let samples: Vec<u8> = mask.iter().map(|&value| (value != 0) as u8).collect();
+        PngEncoder::new(width, height)
+            .with_color_type(COLORTYPE_GRAYSCALE, 1)
+            .encode(&samples)
+    }
+    
+    /// 8位灰度和调色板图像自动降低到能无损表示的最小位深度（1/2/4/8）
+    /// 输入仍为每样本一字节，编码时按位打包
+    pub fn minimal_bit_depth(mut self) -> Self {
+        self.minimal_bit_depth = true;
+        self
+    }
+    
+    /// 8位RGBA和灰度+Alpha图像按阈值二值化alpha：alpha < threshold 视为全透明，其余视为不透明
+    /// 编码为去掉alpha的颜色类型，透明像素写成图像中未使用的关键色并记录在tRNS中。
+    /// 不透明像素已用尽全部颜色、找不到关键色时保留原颜色类型
+    pub fn binarize_alpha(mut self, threshold: u8) -> Self {
+        self.binarize_alpha = Some(threshold);
+        self
+    }
+    
+    /// 根据输入内容选择最小的无损颜色类型（输入需为RGBA 8位）
+    /// 灰度不透明 → 灰度；不超过256种颜色 → 调色板；灰度带alpha → 灰度+Alpha；不透明 → RGB；其余 → RGBA
+    pub fn auto_color_type(mut self) -> Self {
+        self.auto_color_type = true;
+        self
+    }
+    
+    fn encode_auto(&self, rgba: &[u8]) -> Result<Vec<u8>, String> {
+        let pixels = self.options.width as usize * self.options.height as usize;
+        if rgba.len() < pixels * 4 {
+            return Err("Insufficient pixel data".to_string());
+        }
+        let rgba = &rgba[..pixels * 4];
+        
+        let color_type = select_color_type(rgba);
+        let mut options = self.options.clone();
+        options.color_type = color_type;
+        options.input_color_type = color_type;
+        options.bit_depth = 8;
+        
+        let data = match color_type {
+            COLORTYPE_GRAYSCALE => rgba.chunks_exact(4).map(|pixel| pixel[0]).collect(),
+            COLORTYPE_GRAYSCALE_ALPHA => rgba.chunks_exact(4).flat_map(|pixel| [pixel[0], pixel[3]]).collect(),
+            COLORTYPE_COLOR => rgba.chunks_exact(4).flat_map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect(),
+            COLORTYPE_PALETTE_COLOR => {
+                let (indices, palette, alpha) = build_palette(rgba)
+                    .ok_or_else(|| "Too many colors for a palette".to_string())?;
+                options.chunks.retain(|chunk| !matches!(chunk.chunk_type, ChunkType::PLTE | ChunkType::TRNS));
+                options.chunks.push(PNGChunk::new(ChunkType::PLTE, palette));
+                options.transparency = Some(TRNSData::Palette { alpha });
+                indices
+            }
+            _ => rgba.to_vec(),
+        };
+        
+        let data = match self.binarize_alpha {
+            Some(threshold) => binarize_alpha(&mut options, &data, threshold)?.unwrap_or(data),
+            None => data,
+        };
+        let data = if self.minimal_bit_depth { reduce_bit_depth(&mut options, &data)? } else { data };
+        self.pack(options, &data)
     }
 }
 
+/// 16位灰度图像，用于保存高度图等浮点数据
+#[derive(Debug, Clone)]
+pub struct Png16 {
+    width: u32,
+    height: u32,
+    samples: Vec<u16>,
+}
+
+impl Png16 {
+    /// 将[min, max]范围内的浮点数据线性映射到[0, 65535]
+    /// 超出范围的值截断到两端，NaN映射为0
+    pub fn from_f32(data: &[f32], width: u32, height: u32, min: f32, max: f32) -> Result<Self, String> {
+        let pixels = width as usize * height as usize;
+        if data.len() != pixels {
+            return Err(format!("Expected {} samples, got {}", pixels, data.len()));
+        }
+        if !(min.is_finite() && max.is_finite() && min < max) {
+            return Err(format!("Invalid sample range [{}, {}]", min, max));
+        }
+        
+        let scale = 65535.0 / (max as f64 - min as f64);
+        let samples = data.iter()
+            .map(|&value| ((value as f64 - min as f64) * scale).round().clamp(0.0, 65535.0) as u16)
+            .collect();
+        Ok(Self { width, height, samples })
+    }
+    
+    /// 映射后的16位样本
+    pub fn samples(&self) -> &[u16] {
+        &self.samples
+    }
+    
+    /// 编码为16位灰度PNG
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let data: Vec<u8> = self.samples.iter().flat_map(|sample| sample.to_be_bytes()).collect();
+        PngEncoder::new(self.width, self.height)
+            .with_color_type(COLORTYPE_GRAYSCALE, 16)
+            .encode(&data)
+    }
+}
+
+/// 按阈值去掉8位RGBA / 灰度+Alpha数据的alpha通道，透明像素替换为tRNS关键色
+/// 同时更新options中的颜色类型和tRNS；不适用或找不到关键色时返回None
+fn binarize_alpha(options: &mut PackerOptions, data: &[u8], threshold: u8) -> Result<Option<Vec<u8>>, String> {
+    let channels = match (options.color_type, options.bit_depth) {
+        (COLORTYPE_COLOR_ALPHA, 8) => 4,
+        (COLORTYPE_GRAYSCALE_ALPHA, 8) => 2,
+        _ => return Ok(None),
+    };
+    
+    let pixels = options.width as usize * options.height as usize;
+    if data.len() < pixels * channels {
+        return Err("Insufficient pixel data".to_string());
+    }
+    let data = &data[..pixels * channels];
+    let color_channels = channels - 1;
+    
+    let has_transparent = data.chunks_exact(channels).any(|pixel| pixel[color_channels] < threshold);
+    let key = if has_transparent {
+        match unused_key_color(data, channels, threshold) {
+            Some(key) => key.to_be_bytes(),
+            None => return Ok(None),
+        }
+    } else {
+        [0; 4]
+    };
+    let key = &key[4 - color_channels..];
+    
+    let mut output = Vec::with_capacity(pixels * color_channels);
+    for pixel in data.chunks_exact(channels) {
+        if pixel[color_channels] < threshold {
+            output.extend_from_slice(key);
+        } else {
+            output.extend_from_slice(&pixel[..color_channels]);
+        }
+    }
+    
+    options.color_type = if channels == 4 { COLORTYPE_COLOR } else { COLORTYPE_GRAYSCALE };
+    options.input_color_type = options.color_type;
+    options.input_has_alpha = false;
+    options.chunks.retain(|chunk| chunk.chunk_type != ChunkType::TRNS);
+    options.transparency = has_transparent.then(|| match color_channels {
+        1 => TRNSData::Grayscale { value: key[0] as u16 },
+        _ => TRNSData::RGB { r: key[0] as u16, g: key[1] as u16, b: key[2] as u16 },
+    });
+    
+    Ok(Some(output))
+}
+
+/// 查找不透明像素未使用的最小颜色值（RGB按0xRRGGBB编码），颜色已用尽时返回None
+fn unused_key_color(data: &[u8], channels: usize, threshold: u8) -> Option<u32> {
+    let color_channels = channels - 1;
+    let mut used: Vec<u32> = data.chunks_exact(channels)
+        .filter(|pixel| pixel[color_channels] >= threshold)
+        .map(|pixel| pixel[..color_channels].iter().fold(0, |acc, &sample| acc << 8 | sample as u32))
+        .collect();
+    used.sort_unstable();
+    used.dedup();
+    
+    // 排序去重后第一个值与下标不等的位置就是最小的空缺
+    let key = used.iter().enumerate()
+        .find(|&(index, &color)| index as u32 != color)
+        .map_or(used.len() as u32, |(index, _)| index as u32);
+    (key < 1 << (8 * color_channels)).then_some(key)
+}
+
+/// 灰度和调色板8位数据降到最小位深度，同时更新options中的位深度，由打包器按位打包
+/// 其他颜色类型原样返回
+fn reduce_bit_depth(options: &mut PackerOptions, data: &[u8]) -> Result<Vec<u8>, String> {
+    if !matches!(options.color_type, COLORTYPE_GRAYSCALE | COLORTYPE_PALETTE_COLOR) {
+        return Ok(data.to_vec());
+    }
+    
+    let pixels = options.width as usize * options.height as usize;
+    if data.len() < pixels {
+        return Err("Insufficient pixel data".to_string());
+    }
+    let data = &data[..pixels];
+    
+    let (bit_depth, samples): (u8, Vec<u8>) = if options.color_type == COLORTYPE_PALETTE_COLOR {
+        (minimal_index_depth(data), data.to_vec())
+    } else {
+        let bit_depth = minimal_grayscale_depth(data);
+        let step = 255 / ((1u16 << bit_depth) - 1) as u8;
+        (bit_depth, data.iter().map(|&value| value / step).collect())
+    };
+    
+    options.bit_depth = bit_depth;
+    if options.color_type == COLORTYPE_GRAYSCALE {
+        // 灰度tRNS关键值同样按新位深度缩放
+        let step = 255 / ((1u16 << bit_depth) - 1);
+        for chunk in options.chunks.iter_mut().filter(|chunk| chunk.chunk_type == ChunkType::TRNS && chunk.data.len() == 2) {
+            let value = u16::from_be_bytes([chunk.data[0], chunk.data[1]]) / step;
+            *chunk = PNGChunk::new(ChunkType::TRNS, value.to_be_bytes().to_vec());
+        }
+        if let Some(TRNSData::Grayscale { value }) = &mut options.transparency {
+            *value /= step;
+        }
+    }
+    Ok(samples)
+}
+
+/// 为RGBA数据选择最小的无损颜色类型
+pub fn select_color_type(rgba: &[u8]) -> u8 {
+    let is_gray = rgba.chunks_exact(4).all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2]);
+    let opaque = is_opaque(rgba);
+    
+    if is_gray && opaque {
+        COLORTYPE_GRAYSCALE
+    } else if build_palette(rgba).is_some() {
+        COLORTYPE_PALETTE_COLOR
+    } else if is_gray {
+        COLORTYPE_GRAYSCALE_ALPHA
+    } else if opaque {
+        COLORTYPE_COLOR
+    } else {
+        COLORTYPE_COLOR_ALPHA
+    }
+}
+
+/// 无损编码RGBA数据的推荐格式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatRecommendation {
+    pub color_type: u8,
+    pub bit_depth: u8,
+    /// 选择该格式的原因
+    pub reason: String,
+}
+
+/// 根据RGBA内容推荐最小的无损颜色类型和位深度
+/// 在select_color_type的基础上同时确定位深度：灰度按样本取值降低位深度，
+/// 灰度级数很少时改用更低位深度的调色板，调色板按颜色数选择索引位深度
+pub fn recommend_format(rgba: &[u8]) -> FormatRecommendation {
+    let recommend = |color_type: u8, bit_depth: u8, reason: String| FormatRecommendation { color_type, bit_depth, reason };
+    let is_gray = rgba.chunks_exact(4).all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2]);
+    let opaque = is_opaque(rgba);
+    let colors = distinct_color_count(rgba, 256);
+    // 调色板索引位深度，颜色超过256种时为None
+    let index_depth = (colors <= 256).then(|| index_bit_depth(colors.saturating_sub(1) as u8));
+    
+    if is_gray && opaque {
+        let samples: Vec<u8> = rgba.chunks_exact(4).map(|pixel| pixel[0]).collect();
+        let depth = minimal_grayscale_depth(&samples);
+        return match index_depth {
+            Some(index_depth) if depth == 8 && index_depth < 8 => recommend(
+                COLORTYPE_PALETTE_COLOR,
+                index_depth,
+                format!("Opaque grayscale with only {} levels; a {}-bit palette is smaller", colors, index_depth),
+            ),
+            _ => recommend(
+                COLORTYPE_GRAYSCALE,
+                depth,
+                format!("Opaque grayscale; samples fit in {} bits", depth),
+            ),
+        };
+    }
+    
+    if let Some(index_depth) = index_depth {
+        return recommend(
+            COLORTYPE_PALETTE_COLOR,
+            index_depth,
+            format!("{} distinct colors fit in a {}-bit palette", colors, index_depth),
+        );
+    }
+    
+    let alpha_note = match alpha_levels(rgba) {
+        2 => "; alpha is binary, binarize_alpha can store it as a tRNS key color",
+        _ => "",
+    };
+    if is_gray {
+        recommend(COLORTYPE_GRAYSCALE_ALPHA, 8, format!("Grayscale with transparency{}", alpha_note))
+    } else if opaque {
+        recommend(COLORTYPE_COLOR, 8, "Opaque with more than 256 colors".to_string())
+    } else {
+        recommend(COLORTYPE_COLOR_ALPHA, 8, format!("More than 256 colors with transparency{}", alpha_note))
+    }
+}
+
+/// 由RGBA数据构建调色板，颜色超过256种时返回None
+/// 返回 (索引, PLTE数据, 每个条目的alpha)
+fn build_palette(rgba: &[u8]) -> Option<(Vec<u8>, Vec<u8>, Vec<u8>)> {
+    let mut lookup: HashMap<[u8; 4], u8> = HashMap::new();
+    let mut colors: Vec<[u8; 4]> = Vec::new();
+    let mut indices = Vec::with_capacity(rgba.len() / 4);
+    
+    for pixel in rgba.chunks_exact(4) {
+        let color = [pixel[0], pixel[1], pixel[2], pixel[3]];
+        let index = match lookup.get(&color) {
+            Some(&index) => index,
+            None => {
+                if colors.len() == 256 {
+                    return None;
+                }
+                let index = colors.len() as u8;
+                lookup.insert(color, index);
+                colors.push(color);
+                index
+            }
+        };
+        indices.push(index);
+    }
+    
+    let palette = colors.iter().flat_map(|color| [color[0], color[1], color[2]]).collect();
+    let alpha = colors.iter().map(|color| color[3]).collect();
+    
+    Some((indices, palette, alpha))
+}
+
 /// 位打包器
 pub struct BitPacker {
     bit_depth: u8,
@@ -383,7 +1270,7 @@ impl BitPacker {
             COLORTYPE_GRAYSCALE => 1,
             COLORTYPE_COLOR => 3,
             COLORTYPE_PALETTE_COLOR => 1,
-            COLORTYPE_GRAYSCALE | COLORTYPE_ALPHA => 2,
+            COLORTYPE_GRAYSCALE_ALPHA => 2,
             COLORTYPE_COLOR_ALPHA => 4,
             _ => 4,
         }
@@ -452,6 +1339,12 @@ impl FormatNormalizer {
             (COLORTYPE_COLOR, COLORTYPE_COLOR_ALPHA) => {
                 self.rgb_to_rgba(data, width, height)
             }
+            (COLORTYPE_GRAYSCALE_ALPHA, COLORTYPE_COLOR_ALPHA) => {
+                self.grayscale_alpha_to_rgba(data, width, height)
+            }
+            (input, output) if output != COLORTYPE_COLOR_ALPHA => {
+                Err(format!("Unsupported color type conversion {} -> {}", input, output))
+            }
             _ => {
                 // 默认转换为RGBA
                 self.convert_to_rgba(data, width, height)
@@ -494,6 +1387,23 @@ impl FormatNormalizer {
         Ok(output)
     }
     
+    /// 灰度+Alpha转RGBA
+    fn grayscale_alpha_to_rgba(&self, data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
+        let mut output = vec![0; (width * height * 4) as usize];
+        
+        for (i, chunk) in data.chunks_exact(2).enumerate() {
+            let output_index = i * 4;
+            if output_index + 3 < output.len() {
+                output[output_index] = chunk[0];     // R
+                output[output_index + 1] = chunk[0]; // G
+                output[output_index + 2] = chunk[0]; // B
+                output[output_index + 3] = chunk[1]; // A
+            }
+        }
+        
+        Ok(output)
+    }
+    
     /// RGB转RGBA
     fn rgb_to_rgba(&self, data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
         let mut output = vec![0; (width * height * 4) as usize];
@@ -517,6 +1427,7 @@ impl FormatNormalizer {
             COLORTYPE_GRAYSCALE => self.grayscale_to_rgba(data, width, height),
             COLORTYPE_COLOR => self.rgb_to_rgba(data, width, height),
             COLORTYPE_PALETTE_COLOR => self.palette_to_rgba(data, width, height),
+            COLORTYPE_GRAYSCALE_ALPHA => self.grayscale_alpha_to_rgba(data, width, height),
             _ => Ok(data.to_vec()),
         }
     }
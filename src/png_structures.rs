@@ -5,6 +5,7 @@ use wasm_bindgen::prelude::*;
 use js_sys::{Array, Uint8Array, Uint8ClampedArray, Object};
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
+use crate::constants::{get_interlace_pass_size, ADAM7_OFFSETS};
 
 /// PNG图像尺寸信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,19 +71,20 @@ impl ColorInfo {
     pub fn new(color_type: u8, bit_depth: u8) -> Self {
         let color_type_enum = match color_type {
             0 => ColorType::Grayscale,
-            1 => ColorType::Palette,
+            1 | 3 => ColorType::Palette,
             2 => ColorType::RGB,
             4 => ColorType::GrayscaleAlpha,
             6 => ColorType::RGBA,
             _ => ColorType::RGB,
         };
         
+        // 调色板图像最大位深度为8
         let bit_depth_enum = match bit_depth {
             1 => BitDepth::One,
             2 => BitDepth::Two,
             4 => BitDepth::Four,
             8 => BitDepth::Eight,
-            16 => BitDepth::Sixteen,
+            16 if !matches!(color_type_enum, ColorType::Palette) => BitDepth::Sixteen,
             _ => BitDepth::Eight,
         };
         
@@ -95,10 +97,9 @@ impl ColorInfo {
             ColorType::RGBA => 4,
         };
         
+        // 不足1字节的像素按1字节计算（与滤镜的bpp一致）
         let bytes_per_pixel = match bit_depth_enum {
-            BitDepth::One => 1,
-            BitDepth::Two => 1,
-            BitDepth::Four => 1,
+            BitDepth::One | BitDepth::Two | BitDepth::Four => 1,
             BitDepth::Eight => channels,
             BitDepth::Sixteen => channels * 2,
         };
@@ -207,6 +208,34 @@ impl InterlaceInfo {
             progressive_loading: is_interlaced,
         }
     }
+    
+    /// Adam7七个pass的尺寸和位置，图像太小时部分pass的宽或高为0
+    pub fn adam7_passes(width: u32, height: u32) -> Vec<PassGeometry> {
+        ADAM7_OFFSETS.iter().enumerate().map(|(pass, &(x_offset, y_offset, x_step, y_step))| {
+            let (pass_width, pass_height) = get_interlace_pass_size(width, height, pass);
+            PassGeometry {
+                width: pass_width,
+                height: pass_height,
+                x_offset,
+                y_offset,
+                x_step,
+                y_step,
+                byte_size: pass_width as usize * pass_height as usize * 4,
+            }
+        }).collect()
+    }
+}
+
+/// Adam7单个pass的几何信息，byte_size为该pass按8位RGBA展开后的字节数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PassGeometry {
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: u32,
+    pub y_offset: u32,
+    pub x_step: u32,
+    pub y_step: u32,
+    pub byte_size: usize,
 }
 
 /// PNG调色板信息
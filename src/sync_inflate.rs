@@ -1,8 +1,44 @@
 //! 同步解压缩器模块
 //! 实现PNG数据的同步解压缩，匹配原始pngjs库的sync-inflate.js
 
-use flate2::read::DeflateDecoder;
+use flate2::read::{DeflateDecoder, ZlibDecoder};
 use std::io::Read;
+use crate::error_handling::PNGError;
+
+/// zlib解压（IDAT数据流为zlib格式）
+pub fn inflate_zlib(data: &[u8]) -> Result<Vec<u8>, PNGError> {
+    let mut decoder = ZlibDecoder::new(data);
+    let mut decompressed = Vec::new();
+    
+    decoder.read_to_end(&mut decompressed)
+        .map_err(|e| PNGError::DecodeError(format!("Decompression error: {}", e)))?;
+    
+    Ok(decompressed)
+}
+
+/// zlib解压，出错时保留已解压的数据
+/// 返回已解压数据以及遇到的错误（数据流被截断或损坏时）
+pub fn inflate_zlib_partial(data: &[u8]) -> (Vec<u8>, Option<PNGError>) {
+    let mut decoder = ZlibDecoder::new(data);
+    let mut decompressed = Vec::new();
+    
+    let error = decoder.read_to_end(&mut decompressed).err()
+        .map(|e| PNGError::DecodeError(format!("Decompression error: {}", e)));
+    
+    (decompressed, error)
+}
+
+/// zlib解压，只输出前len字节，之后的数据流不再解压
+/// 解压结果不足len字节时返回已解压的部分，由调用者检查长度
+pub fn inflate_zlib_prefix(data: &[u8], len: usize) -> Result<Vec<u8>, PNGError> {
+    let mut decoder = ZlibDecoder::new(data).take(len as u64);
+    let mut decompressed = Vec::with_capacity(len);
+    
+    decoder.read_to_end(&mut decompressed)
+        .map_err(|e| PNGError::DecodeError(format!("Decompression error: {}", e)))?;
+    
+    Ok(decompressed)
+}
 
 /// 同步解压缩器
 pub struct SyncInflate {
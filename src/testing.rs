@@ -2,7 +2,12 @@
 //! 实现全面的测试覆盖和验证
 
 use std::collections::HashMap;
+use std::io::Write;
 use std::time::Instant;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use crate::constants::*;
+use crate::crc::calculate_chunk_crc;
 
 /// 测试结果
 #[derive(Debug, Clone)]
@@ -366,7 +371,12 @@ impl TestCase for CompatibilityTest {
 /// 测试数据生成器
 pub struct TestDataGenerator;
 
+// 生成的纯色图像使用的RGB颜色
+const SOLID_COLOR: [u8; 3] = [0x40, 0x80, 0xc0];
+
 impl TestDataGenerator {
+    /// 生成纯色的8位PNG数据：CRC正确、IDAT为zlib压缩的无滤镜扫描线，可以被真实解码器解码
+    /// 灰度为128，RGB为(64, 128, 192)，带alpha时完全不透明；调色板图像附带单条目PLTE
     pub fn generate_png_data(width: u32, height: u32, color_type: u8) -> Vec<u8> {
         let mut data = Vec::new();
         
@@ -377,8 +387,13 @@ impl TestDataGenerator {
         let ihdr_data = Self::generate_ihdr_data(width, height, color_type);
         data.extend_from_slice(&ihdr_data);
         
-        // IDAT chunk (简化)
-        let idat_data = Self::generate_idat_data(width, height);
+        // PLTE chunk
+        if color_type == COLORTYPE_PALETTE_COLOR {
+            data.extend_from_slice(&Self::generate_chunk(b"PLTE", &SOLID_COLOR));
+        }
+        
+        // IDAT chunk
+        let idat_data = Self::generate_idat_data(width, height, color_type);
         data.extend_from_slice(&idat_data);
         
         // IEND chunk
@@ -390,14 +405,6 @@ impl TestDataGenerator {
     
     fn generate_ihdr_data(width: u32, height: u32, color_type: u8) -> Vec<u8> {
         let mut data = Vec::new();
-        
-        // 长度
-        data.extend_from_slice(&13u32.to_be_bytes());
-        
-        // 类型
-        data.extend_from_slice(b"IHDR");
-        
-        // 数据
         data.extend_from_slice(&width.to_be_bytes());
         data.extend_from_slice(&height.to_be_bytes());
         data.push(8); // bit depth
@@ -406,42 +413,45 @@ impl TestDataGenerator {
         data.push(0); // filter
         data.push(0); // interlace
         
-        // CRC (简化)
-        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
-        
-        data
+        Self::generate_chunk(b"IHDR", &data)
     }
     
-    fn generate_idat_data(width: u32, height: u32) -> Vec<u8> {
-        let mut data = Vec::new();
-        
-        // 长度
-        data.extend_from_slice(&4u32.to_be_bytes());
-        
-        // 类型
-        data.extend_from_slice(b"IDAT");
-        
-        // 数据 (简化)
-        data.extend_from_slice(&[0x78, 0x9c, 0x00, 0x00]);
+    fn generate_idat_data(width: u32, height: u32, color_type: u8) -> Vec<u8> {
+        let pixel: &[u8] = match color_type {
+            COLORTYPE_GRAYSCALE => &[0x80],
+            COLORTYPE_GRAYSCALE_ALPHA => &[0x80, 0xff],
+            COLORTYPE_COLOR => &SOLID_COLOR,
+            COLORTYPE_PALETTE_COLOR => &[0],
+            _ => &[SOLID_COLOR[0], SOLID_COLOR[1], SOLID_COLOR[2], 0xff],
+        };
+        
+        // 每行以滤镜类型0开头
+        let mut scanlines = Vec::with_capacity((1 + width as usize * pixel.len()) * height as usize);
+        for _ in 0..height {
+            scanlines.push(0);
+            for _ in 0..width {
+                scanlines.extend_from_slice(pixel);
+            }
+        }
         
-        // CRC (简化)
-        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&scanlines).expect("writing to a Vec cannot fail");
+        let compressed = encoder.finish().expect("writing to a Vec cannot fail");
         
-        data
+        Self::generate_chunk(b"IDAT", &compressed)
     }
     
     fn generate_iend_data() -> Vec<u8> {
-        let mut data = Vec::new();
-        
-        // 长度
-        data.extend_from_slice(&0u32.to_be_bytes());
-        
-        // 类型
-        data.extend_from_slice(b"IEND");
-        
-        // CRC (简化)
-        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
-        
+        Self::generate_chunk(b"IEND", &[])
+    }
+    
+    /// 长度 + 类型 + 数据 + CRC
+    fn generate_chunk(chunk_type: &[u8; 4], chunk_data: &[u8]) -> Vec<u8> {
+        let mut data = Vec::with_capacity(12 + chunk_data.len());
+        data.extend_from_slice(&(chunk_data.len() as u32).to_be_bytes());
+        data.extend_from_slice(chunk_type);
+        data.extend_from_slice(chunk_data);
+        data.extend_from_slice(&calculate_chunk_crc(chunk_type, chunk_data).to_be_bytes());
         data
     }
 }
@@ -108,6 +108,36 @@ pub fn u8_to_u16_array(data: &[u8]) -> Vec<u16> {
     result
 }
 
+/// 将整数通道值截断到0..=255（饱和而不是回绕）
+pub fn clamp_u8(value: i32) -> u8 {
+    value.clamp(0, 255) as u8
+}
+
+/// 将浮点通道值四舍五入并截断到0..=255，NaN视为0
+pub fn clamp_u8_f64(value: f64) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+/// FNV-1a 64位哈希的初始值
+pub const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+
+/// FNV-1a 64位哈希，可从上一次的结果继续累加
+pub fn fnv1a_64(mut hash: u64, data: &[u8]) -> u64 {
+    for &byte in data {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// 像素内容指纹：尺寸 + 规范化的RGBA数据，与编码方式无关
+pub fn content_fingerprint(width: u32, height: u32, rgba: &[u8]) -> u64 {
+    let mut hash = fnv1a_64(FNV_OFFSET_BASIS, &width.to_be_bytes());
+    hash = fnv1a_64(hash, &height.to_be_bytes());
+    hash = fnv1a_64(hash, &[crate::constants::COLORTYPE_COLOR_ALPHA, 8]);
+    fnv1a_64(hash, rgba)
+}
+
 /// 计算行字节数
 pub fn calculate_row_bytes(width: u32, bits_per_pixel: u8) -> usize {
     ((width * bits_per_pixel as u32 + 7) / 8) as usize
@@ -0,0 +1,37 @@
+//! 高级PNG颜色类型转换测试
+
+use rust_png::advanced_png::*;
+use rust_png::constants::*;
+
+#[test]
+fn test_rgba_to_grayscale_alpha() {
+    // 0.299*200 + 0.587*100 + 0.114*50 = 124.2，alpha保留
+    let mut png = AdvancedPNG::new(AdvancedPNGOptions { width: 1, height: 1, ..Default::default() });
+    png.set_8bit_data(&[200, 100, 50, 128]).unwrap();
+    png.convert_color_type(COLORTYPE_GRAYSCALE_ALPHA).unwrap();
+    assert_eq!(png.get_data().unwrap(), &vec![124, 128]);
+
+    // 16位同时保留16位灰度和16位alpha
+    let rgba16: Vec<u8> = [200u16 * 257, 100 * 257, 50 * 257, 0x1234].iter().flat_map(|v| v.to_be_bytes()).collect();
+    let converted = ColorTypeConverter::convert(&rgba16, COLORTYPE_COLOR_ALPHA, COLORTYPE_GRAYSCALE_ALPHA, 16).unwrap();
+    let gray = u16::from_be_bytes([converted[0], converted[1]]);
+    assert_eq!(converted.len(), 4);
+    assert_eq!(gray, (124.2 * 257.0) as u16);
+    assert_eq!(converted[2..], [0x12, 0x34]);
+}
+
+#[test]
+fn test_set_data_checks_packed_size() {
+    // 1位灰度按位打包：10像素一行占2字节
+    let options = AdvancedPNGOptions { width: 10, height: 2, bit_depth: 1, color_type: COLORTYPE_GRAYSCALE, ..Default::default() };
+    let mut png = AdvancedPNG::new(options);
+    assert!(png.set_data(vec![0; 4]).is_ok());
+    assert!(png.set_data(vec![0; 20]).is_err());
+
+    let options = AdvancedPNGOptions { width: 1, height: 1, bit_depth: 16, color_type: COLORTYPE_COLOR_ALPHA, ..Default::default() };
+    assert!(AdvancedPNG::new(options).set_data(vec![0; 8]).is_ok());
+
+    // 尺寸乘积超出范围时返回错误而不是回绕
+    let options = AdvancedPNGOptions { width: u32::MAX, height: u32::MAX, bit_depth: 16, color_type: COLORTYPE_COLOR_ALPHA, ..Default::default() };
+    assert!(AdvancedPNG::new(options).set_data(Vec::new()).is_err());
+}
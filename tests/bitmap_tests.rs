@@ -0,0 +1,164 @@
+//! 位图处理测试用例
+
+use rust_png::bitmap::*;
+use rust_png::constants::*;
+
+#[test]
+fn test_grayscale_8bit_transparent_key() {
+    // 8位灰度关键色透明
+    let input = [10u8, 20, 30, 20];
+    let mut output = vec![0u8; 4 * 4];
+    replace_transparent_color(&input, &mut output, 2, 2, COLORTYPE_GRAYSCALE, 8, &[20]);
+
+    assert_eq!(&output[0..4], &[10, 10, 10, 255]);
+    assert_eq!(&output[4..8], &[0, 0, 0, 0]);
+    assert_eq!(&output[8..12], &[30, 30, 30, 255]);
+    assert_eq!(&output[12..16], &[0, 0, 0, 0]);
+}
+
+#[test]
+fn test_grayscale_16bit_transparent_key() {
+    // 16位灰度关键色必须按16位精度比较
+    let input = [0x12u8, 0x34, 0x12, 0x35, 0xff, 0xff];
+    let mut output = vec![0u8; 3 * 4];
+    replace_transparent_color(&input, &mut output, 3, 1, COLORTYPE_GRAYSCALE, 16, &[0x1234]);
+
+    assert_eq!(&output[0..4], &[0, 0, 0, 0]);
+    // 高字节相同但低字节不同的像素不应透明
+    assert_eq!(&output[4..8], &[0x12, 0x12, 0x12, 255]);
+    assert_eq!(&output[8..12], &[0xff, 0xff, 0xff, 255]);
+}
+
+#[test]
+fn test_rgb_transparent_key() {
+    // RGB关键色，按3通道索引
+    let input = [1u8, 2, 3, 4, 5, 6];
+    let mut output = vec![0u8; 2 * 4];
+    replace_transparent_color(&input, &mut output, 2, 1, COLORTYPE_COLOR, 8, &[4, 5, 6]);
+
+    assert_eq!(&output[0..4], &[1, 2, 3, 255]);
+    assert_eq!(&output[4..8], &[0, 0, 0, 0]);
+}
+
+#[test]
+fn test_convert_16bit_grayscale_to_rgba() {
+    // 16位灰度渐变：0, 0x8080, 0xffff
+    let data = [0x00u8, 0x00, 0x80, 0x80, 0xff, 0xff];
+    let rgba = convert_to_rgba(&data, 3, 1, COLORTYPE_GRAYSCALE, 16, None);
+
+    assert_eq!(rgba, vec![0, 0, 0, 255, 128, 128, 128, 255, 255, 255, 255, 255]);
+    assert_eq!(to_native_16(&data), vec![0x0000, 0x8080, 0xffff]);
+
+    // 与16位RGB一样取高字节
+    let gray = convert_to_rgba(&[0x01, 0xff], 1, 1, COLORTYPE_GRAYSCALE, 16, None);
+    let rgb = convert_to_rgba(&[0x01, 0xff, 0x01, 0xff, 0x01, 0xff], 1, 1, COLORTYPE_COLOR, 16, None);
+    assert_eq!(gray, vec![1, 1, 1, 255]);
+    assert_eq!(gray, rgb);
+}
+
+#[test]
+fn test_convert_16bit_grayscale_alpha_to_rgba() {
+    let data = [0xffu8, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff];
+    let rgba = convert_to_rgba(&data, 2, 1, COLORTYPE_GRAYSCALE_ALPHA, 16, None);
+
+    assert_eq!(rgba, vec![255, 255, 255, 0, 0, 0, 0, 255]);
+}
+
+#[test]
+fn test_convert_8bit_grayscale_alpha_to_rgba() {
+    // 灰度+Alpha每像素2字节，alpha必须保留
+    let data = [10u8, 0, 20, 128, 30, 255];
+    let rgba = convert_to_rgba(&data, 3, 1, COLORTYPE_GRAYSCALE_ALPHA, 8, None);
+
+    assert_eq!(rgba, vec![10, 10, 10, 0, 20, 20, 20, 128, 30, 30, 30, 255]);
+    assert_eq!(get_bytes_per_pixel(COLORTYPE_GRAYSCALE_ALPHA), 2);
+}
+
+#[test]
+fn test_unpack_and_render_palette() {
+    // 2位索引：0,1,2,3 打包在一个字节中
+    let indices = unpack_indices(&[0b00_01_10_11], 4, 1, 2);
+    assert_eq!(indices, vec![0, 1, 2, 3]);
+
+    let palette = [255, 0, 0, 0, 255, 0, 0, 0, 255];
+    let rgba = render_palette(&indices, &palette, &[128]);
+    assert_eq!(rgba, vec![
+        255, 0, 0, 128, // 条目0使用tRNS alpha
+        0, 255, 0, 255,
+        0, 0, 255, 255,
+        0, 0, 0, 255,   // 超出调色板范围
+    ]);
+}
+
+#[test]
+fn test_alpha_classification() {
+    // 完全不透明
+    let opaque = [10u8, 20, 30, 255, 40, 50, 60, 255];
+    assert!(is_opaque(&opaque));
+    assert!(!has_translucency(&opaque));
+
+    // 二值透明：只有0和255
+    let masked = [10u8, 20, 30, 255, 0, 0, 0, 0];
+    assert!(!is_opaque(&masked));
+    assert!(!has_translucency(&masked));
+
+    // 平滑alpha
+    let smooth = [10u8, 20, 30, 255, 40, 50, 60, 128, 0, 0, 0, 0];
+    assert!(!is_opaque(&smooth));
+    assert!(has_translucency(&smooth));
+}
+
+#[test]
+fn test_distinct_color_count() {
+    let three = [1u8, 2, 3, 255, 4, 5, 6, 255, 1, 2, 3, 255, 4, 5, 6, 0];
+    assert_eq!(distinct_color_count(&three, 256), 3);
+    assert_eq!(distinct_color_count(&three, 2), 3);
+    assert_eq!(distinct_color_count(&three, 1), 2);
+
+    // 伪随机噪声：超过上限后立即停止
+    let mut state = 1u32;
+    let photo: Vec<u8> = (0..512 * 512 * 4)
+        .map(|_| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            (state >> 16) as u8
+        })
+        .collect();
+    assert_eq!(distinct_color_count(&photo, 256), 257);
+    assert_eq!(distinct_color_count(&[], 256), 0);
+}
+
+#[test]
+fn test_alpha_levels() {
+    // 完全不透明
+    assert_eq!(alpha_levels(&[10, 20, 30, 255, 40, 50, 60, 255]), 1);
+
+    // 硬边遮罩
+    assert_eq!(alpha_levels(&[10, 20, 30, 255, 0, 0, 0, 0, 1, 2, 3, 255]), 2);
+
+    // 渐变alpha
+    let gradient: Vec<u8> = (0..=255u8).flat_map(|alpha| [0, 0, 0, alpha]).collect();
+    assert_eq!(alpha_levels(&gradient), 256);
+
+    assert_eq!(alpha_levels(&[]), 0);
+}
+
+#[test]
+fn test_rgba_to_planar() {
+    let rgba = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let [r, g, b, a] = rgba_to_planar(&rgba);
+    assert_eq!(r, vec![1, 5, 9, 13]);
+    assert_eq!(g, vec![2, 6, 10, 14]);
+    assert_eq!(b, vec![3, 7, 11, 15]);
+    assert_eq!(a, vec![4, 8, 12, 16]);
+}
+
+#[test]
+fn test_rgba_to_rgb() {
+    let rgba = [200u8, 100, 50, 255, 200, 100, 50, 0, 255, 255, 255, 128];
+
+    // 不合成：直接丢弃alpha
+    assert_eq!(rgba_to_rgb(&rgba, None), vec![200, 100, 50, 200, 100, 50, 255, 255, 255]);
+
+    // 合成到黑色背景：不透明像素不变，全透明像素变为背景
+    assert_eq!(rgba_to_rgb(&rgba, Some([0, 0, 0])), vec![200, 100, 50, 0, 0, 0, 128, 128, 128]);
+}
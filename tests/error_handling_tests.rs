@@ -0,0 +1,114 @@
+//! 错误处理与校验测试用例
+
+use rust_png::constants::*;
+use rust_png::error_handling::*;
+use rust_png::png_chunks::*;
+
+/// 用给定的IHDR参数构造PNG，IDAT为1行全0扫描线
+fn build_png(width: u32, bit_depth: u8, color_type: u8) -> Vec<u8> {
+    use flate2::write::ZlibEncoder;
+    use std::io::Write;
+
+    let ihdr = IHDRData {
+        width,
+        height: 1,
+        bit_depth,
+        color_type,
+        compression_method: 0,
+        filter_method: 0,
+        interlace_method: 0,
+    };
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&vec![0; ihdr.stride() + 1]).unwrap();
+    let idat = encoder.finish().unwrap();
+
+    let mut data = PNG_SIGNATURE.to_vec();
+    data.extend_from_slice(&PNGChunk::new(ChunkType::IHDR, ihdr.to_bytes()).to_bytes());
+    if color_type == COLORTYPE_PALETTE_COLOR {
+        data.extend_from_slice(&PNGChunk::new(ChunkType::PLTE, vec![0, 0, 0]).to_bytes());
+    }
+    data.extend_from_slice(&PNGChunk::new(ChunkType::IDAT, idat).to_bytes());
+    data.extend_from_slice(&PNGChunk::new(ChunkType::IEND, Vec::new()).to_bytes());
+    data
+}
+
+#[test]
+fn test_validate_report_valid_file() {
+    let data = build_png(4, 8, COLORTYPE_COLOR_ALPHA);
+    let report = ErrorValidator::new().validate_report(&data);
+
+    assert!(report.valid, "{:?}", report.errors);
+    assert!(report.errors.is_empty());
+    assert!(report.warnings.is_empty());
+}
+
+#[test]
+fn test_validate_report_indexed_16bit() {
+    // 调色板图像不允许16位
+    let data = build_png(4, 16, COLORTYPE_PALETTE_COLOR);
+    let report = ErrorValidator::new().validate_report(&data);
+
+    assert!(!report.valid);
+    assert!(report.errors.iter().any(|e| e.contains("bit depth 16") && e.contains("color type 3")), "{:?}", report.errors);
+}
+
+#[test]
+fn test_validate_report_bad_signature_and_truncation() {
+    let mut data = build_png(4, 8, COLORTYPE_GRAYSCALE);
+    data[1] = b'X';
+    assert!(!ErrorValidator::new().validate_report(&data).valid);
+
+    let mut data = build_png(4, 8, COLORTYPE_GRAYSCALE);
+    data.truncate(data.len() - 6);
+    assert!(!ErrorValidator::new().validate_report(&data).valid);
+}
+
+#[test]
+fn test_validate_report_invalid_filter_byte() {
+    // 首行滤镜字节为5
+    let mut data = build_png(2, 8, COLORTYPE_GRAYSCALE);
+    let ihdr_end = 8 + 25;
+    let idat = {
+        use flate2::write::ZlibEncoder;
+        use std::io::Write;
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&[5, 0, 0]).unwrap();
+        encoder.finish().unwrap()
+    };
+    data.truncate(ihdr_end);
+    data.extend_from_slice(&PNGChunk::new(ChunkType::IDAT, idat).to_bytes());
+    data.extend_from_slice(&PNGChunk::new(ChunkType::IEND, Vec::new()).to_bytes());
+
+    let report = ErrorValidator::new().validate_report(&data);
+    assert!(!report.valid);
+    assert!(report.errors[0].contains("first scanline"));
+}
+
+#[test]
+fn test_sniff_other_image_formats() {
+    let cases: [(&[u8], &str); 4] = [
+        (&[0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10, b'J', b'F', b'I', b'F'], "JPEG"),
+        (b"GIF89a\x01\x00\x01\x00", "GIF"),
+        (b"BM\x3e\x00\x00\x00\x00\x00\x00\x00", "BMP"),
+        (b"RIFF\x24\x00\x00\x00WEBPVP8 ", "WebP"),
+    ];
+    for (header, format) in cases {
+        assert_eq!(sniff_format(header), Some(format));
+        let message = format!("Data is a {}, not a PNG", format);
+
+        // 签名检查、chunk解析和校验都给出具体格式
+        match check_png_signature(header) {
+            Err(PNGError::InvalidFormat(error)) => assert_eq!(error, message),
+            other => panic!("unexpected result for {}: {:?}", format, other),
+        }
+        let error = PNGChunkParser::new().parse(header).unwrap_err();
+        assert!(error.to_string().contains(&message), "{}", error);
+        assert!(ErrorValidator::new().validate_report(header).errors.iter().any(|e| e.contains(&message)));
+    }
+
+    assert_eq!(sniff_format(&PNG_SIGNATURE), Some("PNG"));
+    assert!(check_png_signature(&PNG_SIGNATURE).is_ok());
+    // 无法识别的数据仍报告签名错误，过短时报告数据不足
+    assert!(matches!(check_png_signature(b"hello world!"), Err(PNGError::InvalidFormat(_))));
+    assert!(matches!(check_png_signature(b"RIFF"), Err(PNGError::InsufficientData(_))));
+}
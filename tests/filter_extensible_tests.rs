@@ -275,6 +275,22 @@ fn test_filter_compression_ratio_calculation() {
     assert!(ratio <= 1.0);
 }
 
+#[test]
+fn test_edge_strength_saturates() {
+    // 右下角像素与左侧、上方各差150，边缘强度300截断为255（回绕则为44，低于阈值）
+    let data = vec![100, 100, 100, 250];
+    let context = FilterContext {
+        width: 2,
+        height: 2,
+        bytes_per_pixel: 1,
+        row_index: 0,
+        column_index: 0,
+        previous_row: None,
+    };
+    let ratio = EdgeDetectionFilter::new(50).calculate_compression_ratio(&data, &context);
+    assert_eq!(ratio, 0.75);
+}
+
 #[test]
 fn test_filter_priority_system() {
     let adaptive_filter = AdaptiveFilter::new();
@@ -298,3 +314,108 @@ fn test_filter_parallel_support() {
     assert!(edge_filter.supports_parallel());
     assert!(standard_filter.supports_parallel());
 }
+
+#[test]
+fn test_standard_filter_apply_reconstructs_rows() {
+    // 2x2 单通道，第2行用Paeth编码：原始 [10, 20, 30, 45]
+    let mut data = vec![10, 10, 20, 15];
+    let filter = StandardFilter::new(FILTER_PAETH);
+
+    for row_index in 0..2 {
+        let context = FilterContext {
+            width: 2,
+            height: 2,
+            bytes_per_pixel: 1,
+            row_index,
+            column_index: 0,
+            previous_row: None,
+        };
+        if row_index == 0 {
+            StandardFilter::new(FILTER_SUB).apply(&mut data, &context).unwrap();
+        } else {
+            filter.apply(&mut data, &context).unwrap();
+        }
+    }
+
+    assert_eq!(data, vec![10, 20, 30, 45]);
+}
+
+#[test]
+fn test_paeth_single_row_image() {
+    // 单行图像没有上一行，Paeth退化为Sub
+    let context = FilterContext {
+        width: 4,
+        height: 1,
+        bytes_per_pixel: 1,
+        row_index: 0,
+        column_index: 0,
+        previous_row: None,
+    };
+    let original = vec![10u8, 30, 25, 200];
+    let filter = StandardFilter::new(FILTER_PAETH);
+
+    let mut data = original.clone();
+    filter.reverse(&mut data, &context).unwrap();
+    assert_eq!(data, vec![10, 20, 251, 175]);
+
+    filter.apply(&mut data, &context).unwrap();
+    assert_eq!(data, original);
+
+    // 自定义滤镜的首行处理同样不会下溢
+    let mut data = original.clone();
+    AdaptiveFilter::new().apply(&mut data, &context).unwrap();
+    EdgeDetectionFilter::new(10).reverse(&mut data, &context).unwrap();
+}
+
+#[test]
+fn test_adaptive_filter_strategies_round_trip() {
+    // 4x3 双通道图像；reverse从最后一行向上处理，保证上一行仍是原始值，apply再从首行向下重建
+    let (width, height, bytes_per_pixel) = (4, 3, 2);
+    let original: Vec<u8> = (0..width * height * bytes_per_pixel).map(|i| (i * 37 % 251) as u8).collect();
+    let context = |row_index| FilterContext {
+        width,
+        height,
+        bytes_per_pixel,
+        row_index,
+        column_index: 0,
+        previous_row: None,
+    };
+
+    for strategy in [FilterStrategy::Horizontal, FilterStrategy::Vertical, FilterStrategy::Diagonal, FilterStrategy::Gradient] {
+        let filter = AdaptiveFilter::new().with_strategy(strategy);
+        let mut data = original.clone();
+        for row_index in (0..height).rev() {
+            filter.reverse(&mut data, &context(row_index)).unwrap();
+        }
+        assert_ne!(data, original, "{:?}", strategy);
+
+        for row_index in 0..height {
+            filter.apply(&mut data, &context(row_index)).unwrap();
+        }
+        assert_eq!(data, original, "{:?}", strategy);
+    }
+}
+
+#[test]
+fn test_context_defilter_row_matches_defilter_image() {
+    // 3x2 单通道，每种滤镜逐行重建的结果与defilter_image一致
+    let encoded = vec![10, 5, 7, 3, 250, 9];
+    for filter_type in [FILTER_NONE, FILTER_SUB, FILTER_UP, FILTER_AVERAGE, FILTER_PAETH] {
+        let mut expected = encoded.clone();
+        rust_png::png_core::defilter_image(&mut expected, 3, 2, 1, &[filter_type; 2]).unwrap();
+
+        let mut data = encoded.clone();
+        for row_index in 0..2 {
+            let context = FilterContext {
+                width: 3,
+                height: 2,
+                bytes_per_pixel: 1,
+                row_index,
+                column_index: 0,
+                previous_row: None,
+            };
+            context.defilter_row(&mut data, filter_type).unwrap();
+        }
+        assert_eq!(data, expected, "filter {}", filter_type);
+    }
+}
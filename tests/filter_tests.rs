@@ -0,0 +1,130 @@
+//! PNG滤镜测试用例
+
+use rust_png::constants::*;
+use rust_png::filter::*;
+
+/// 生成带噪声和渐变的测试图像
+fn test_image(width: usize, height: usize, bpp: usize) -> Vec<u8> {
+    (0..width * height * bpp)
+        .map(|i| {
+            let x = (i / bpp) % width;
+            let y = i / (width * bpp);
+            ((x * 3 + y * 5) as u8).wrapping_add((i * 31 % 17) as u8)
+        })
+        .collect()
+}
+
+#[test]
+fn test_select_row_filters_one_per_row() {
+    let (width, height, bpp) = (16, 9, 4);
+    let data = test_image(width, height, bpp);
+    let candidates = [FILTER_NONE, FILTER_SUB, FILTER_UP, FILTER_AVERAGE, FILTER_PAETH];
+
+    let filters = select_row_filters_serial(&data, width * bpp, bpp, &candidates);
+    assert_eq!(filters.len(), height);
+    assert!(filters.iter().all(|f| candidates.contains(f)));
+}
+
+#[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+#[test]
+fn test_parallel_and_serial_filter_selection_agree() {
+    let (width, height, bpp) = (97, 64, 3);
+    let data = test_image(width, height, bpp);
+    let candidates = [FILTER_NONE, FILTER_SUB, FILTER_UP, FILTER_AVERAGE, FILTER_PAETH];
+
+    let serial = select_row_filters_serial(&data, width * bpp, bpp, &candidates);
+    let parallel = select_row_filters_parallel(&data, width * bpp, bpp, &candidates);
+    assert_eq!(serial, parallel);
+}
+
+#[test]
+fn test_flat_scanline_filter() {
+    let all = [FILTER_NONE, FILTER_SUB, FILTER_UP, FILTER_AVERAGE, FILTER_PAETH];
+    let row = [10u8, 20, 30, 10, 20, 30];
+
+    // 与上一行相同：Up
+    assert_eq!(flat_scanline_filter(&row, Some(&row), 3, &all), Some(FILTER_UP));
+    // 像素全部相同：None
+    assert_eq!(flat_scanline_filter(&row, None, 3, &all), Some(FILTER_NONE));
+    // 候选中没有None时退回完整估算
+    assert_eq!(flat_scanline_filter(&row, None, 3, &[FILTER_SUB, FILTER_PAETH]), None);
+    // 非平坦行
+    assert_eq!(flat_scanline_filter(&[1, 2, 3, 4, 5, 6], None, 3, &all), None);
+}
+
+#[test]
+fn test_defilter_image_all_filter_types() {
+    let (width, height, bpp) = (5, 2, 3);
+    let original = test_image(width, height, bpp);
+    let bytes_per_row = width * bpp;
+
+    for filter_type in [FILTER_NONE, FILTER_SUB, FILTER_UP, FILTER_AVERAGE, FILTER_PAETH] {
+        // 每行都使用同一种滤镜编码
+        let mut filtered = Vec::new();
+        let mut out = Vec::new();
+        for y in 0..height {
+            let row = &original[y * bytes_per_row..(y + 1) * bytes_per_row];
+            let prev_row = if y > 0 { Some(&original[(y - 1) * bytes_per_row..y * bytes_per_row]) } else { None };
+            filter_scanline(filter_type, row, prev_row, bpp, &mut out);
+            filtered.extend_from_slice(&out);
+        }
+
+        defilter_image(&mut filtered, width, height, bpp, &vec![filter_type; height]).unwrap();
+        assert_eq!(filtered, original, "filter_type={}", filter_type);
+    }
+}
+
+#[test]
+fn test_defilter_image_mixed_filters_and_errors() {
+    let (width, height, bpp) = (4, 5, 4);
+    let original = test_image(width, height, bpp);
+    let bytes_per_row = width * bpp;
+    let filter_bytes = [FILTER_PAETH, FILTER_AVERAGE, FILTER_UP, FILTER_SUB, FILTER_NONE];
+
+    let mut filtered = Vec::new();
+    let mut out = Vec::new();
+    for (y, &filter_type) in filter_bytes.iter().enumerate() {
+        let row = &original[y * bytes_per_row..(y + 1) * bytes_per_row];
+        let prev_row = if y > 0 { Some(&original[(y - 1) * bytes_per_row..y * bytes_per_row]) } else { None };
+        filter_scanline(filter_type, row, prev_row, bpp, &mut out);
+        filtered.extend_from_slice(&out);
+    }
+
+    let mut decoded = filtered.clone();
+    defilter_image(&mut decoded, width, height, bpp, &filter_bytes).unwrap();
+    assert_eq!(decoded, original);
+
+    // 非法滤镜类型、数据不足、滤镜字节不足
+    assert!(defilter_image(&mut filtered.clone(), width, height, bpp, &[0, 0, 5, 0, 0]).is_err());
+    assert!(defilter_image(&mut filtered[..bytes_per_row].to_vec(), width, height, bpp, &filter_bytes).is_err());
+    assert!(defilter_image(&mut filtered, width, height, bpp, &filter_bytes[..2]).is_err());
+}
+
+#[test]
+fn test_defilter_single_row_and_column_images() {
+    // 1x5（单列）和5x1（单行）图像，每种滤镜都能正确重建
+    for (width, height) in [(1, 5), (5, 1)] {
+        for bpp in [1, 4] {
+            let original = test_image(width, height, bpp);
+            let bytes_per_row = width * bpp;
+
+            for filter_type in [FILTER_NONE, FILTER_SUB, FILTER_UP, FILTER_AVERAGE, FILTER_PAETH] {
+                let mut filtered = Vec::new();
+                let mut out = Vec::new();
+                for y in 0..height {
+                    let row = &original[y * bytes_per_row..(y + 1) * bytes_per_row];
+                    let prev_row = if y > 0 { Some(&original[(y - 1) * bytes_per_row..y * bytes_per_row]) } else { None };
+                    filter_scanline(filter_type, row, prev_row, bpp, &mut out);
+                    filtered.extend_from_slice(&out);
+                }
+
+                defilter_image(&mut filtered, width, height, bpp, &vec![filter_type; height]).unwrap();
+                assert_eq!(filtered, original, "{}x{} bpp={} filter_type={}", width, height, bpp, filter_type);
+            }
+        }
+    }
+
+    // 上一行长度不足、bpp为0时返回错误而不是越界
+    assert!(unfilter_scanline(FILTER_PAETH, &mut [1, 2, 3], Some(&[1, 2]), 1).is_err());
+    assert!(unfilter_scanline(FILTER_SUB, &mut [1, 2, 3], None, 0).is_err());
+}
@@ -0,0 +1,285 @@
+//! 图像几何变换测试用例
+
+use rust_png::image_ops::*;
+use rust_png::utils::{clamp_u8, clamp_u8_f64};
+
+/// 2x3图像，每个像素的R通道为其行优先序号
+fn numbered_2x3() -> Vec<u8> {
+    (0..6u8).flat_map(|i| [i, 0, 0, 255]).collect()
+}
+
+/// 取出每个像素的R通道
+fn red_channel(data: &[u8]) -> Vec<u8> {
+    data.chunks_exact(4).map(|pixel| pixel[0]).collect()
+}
+
+#[test]
+fn test_orientation_6_rotates_clockwise() {
+    let (rotated, width, height) = apply_orientation(&numbered_2x3(), 2, 3, 6).unwrap();
+    assert_eq!((width, height), (3, 2));
+    assert_eq!(red_channel(&rotated), vec![4, 2, 0, 5, 3, 1]);
+}
+
+#[test]
+fn test_orientation_3_rotates_180() {
+    let (rotated, width, height) = apply_orientation(&numbered_2x3(), 2, 3, 3).unwrap();
+    assert_eq!((width, height), (2, 3));
+    assert_eq!(red_channel(&rotated), vec![5, 4, 3, 2, 1, 0]);
+}
+
+#[test]
+fn test_orientation_transpose_and_transverse() {
+    // 5: 沿主对角线翻转
+    let (data, width, height) = apply_orientation(&numbered_2x3(), 2, 3, 5).unwrap();
+    assert_eq!((width, height), (3, 2));
+    assert_eq!(red_channel(&data), vec![0, 2, 4, 1, 3, 5]);
+
+    // 7: 沿副对角线翻转
+    let (data, width, height) = apply_orientation(&numbered_2x3(), 2, 3, 7).unwrap();
+    assert_eq!((width, height), (3, 2));
+    assert_eq!(red_channel(&data), vec![5, 3, 1, 4, 2, 0]);
+}
+
+#[test]
+fn test_orientation_rejects_overflowing_size() {
+    // 65536 * 16384 * 4 在u32中回绕为0，不能被空缓冲区通过
+    assert!(apply_orientation(&[], 65536, 16384, 2).is_err());
+    assert!(apply_orientation(&numbered_2x3(), 3, 3, 1).is_err());
+}
+
+#[test]
+fn test_orientation_flips_and_ccw() {
+    let data = numbered_2x3();
+    assert_eq!(red_channel(&apply_orientation(&data, 2, 3, 1).unwrap().0), vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(red_channel(&apply_orientation(&data, 2, 3, 2).unwrap().0), vec![1, 0, 3, 2, 5, 4]);
+    assert_eq!(red_channel(&apply_orientation(&data, 2, 3, 4).unwrap().0), vec![4, 5, 2, 3, 0, 1]);
+    assert_eq!(red_channel(&apply_orientation(&data, 2, 3, 8).unwrap().0), vec![1, 3, 5, 0, 2, 4]);
+
+    assert!(apply_orientation(&data, 2, 3, 9).is_err());
+}
+
+#[test]
+fn test_resize_checkerboard_gamma() {
+    // 2x2黑白棋盘格缩小为1x1
+    let checkerboard = [
+        0, 0, 0, 255, 255, 255, 255, 255,
+        255, 255, 255, 255, 0, 0, 0, 255,
+    ];
+
+    // gamma编码空间直接平均：约128
+    let naive = resize(&checkerboard, 2, 2, 1, 1, false).unwrap();
+    assert_eq!(naive, vec![128, 128, 128, 255]);
+
+    // 线性空间平均后编码回sRGB：约188，与棋盘格的感知亮度一致
+    let corrected = resize(&checkerboard, 2, 2, 1, 1, true).unwrap();
+    assert_eq!(corrected[3], 255);
+    assert!((187..=189).contains(&corrected[0]), "{}", corrected[0]);
+}
+
+#[test]
+fn test_resize_upscale_preserves_solid_color() {
+    let data: Vec<u8> = (0..4).flat_map(|_| [30, 120, 200, 128]).collect();
+    let resized = resize(&data, 2, 2, 5, 3, true).unwrap();
+
+    assert_eq!(resized.len(), 5 * 3 * 4);
+    assert!(resized.chunks_exact(4).all(|pixel| pixel == [30, 120, 200, 128]));
+    assert!(resize(&data, 2, 2, 0, 3, false).is_err());
+
+    // 源尺寸在u32中回绕、目标尺寸超出usize时报错而不是越界或分配失败
+    assert!(resize(&[], 65536, 16384, 1, 1, false).is_err());
+    assert!(resize(&data, 2, 2, u32::MAX, u32::MAX, false).is_err());
+}
+
+#[test]
+fn test_fit_within_preserves_aspect() {
+    assert_eq!(fit_within(100, 50, 20).unwrap(), (20, 10));
+    assert_eq!(fit_within(50, 100, 20).unwrap(), (10, 20));
+    // 不能整除时四舍五入：30 * 20 / 70 = 8.57
+    assert_eq!(fit_within(70, 30, 20).unwrap(), (20, 9));
+    // 极窄的图像较短边至少为1
+    assert_eq!(fit_within(1000, 1, 10).unwrap(), (10, 1));
+    // 小图同样放大到最长边
+    assert_eq!(fit_within(4, 2, 8).unwrap(), (8, 4));
+    assert!(fit_within(100, 50, 0).is_err());
+}
+
+#[test]
+fn test_copy_region_center() {
+    // 4x4图像，每个像素的R通道为其行优先序号
+    let data: Vec<u8> = (0..16u8).flat_map(|i| [i, 0, 0, 255]).collect();
+    let region = copy_region(&data, 4, 4, 1, 1, 2, 2).unwrap();
+    assert_eq!(region.len(), 16);
+    assert_eq!(red_channel(&region), vec![5, 6, 9, 10]);
+
+    assert!(copy_region(&data, 4, 4, 3, 0, 2, 1).is_err());
+    assert!(copy_region(&data, 4, 4, 0, u32::MAX, 1, 2).is_err());
+    assert!(copy_region(&data, 65536, 65536, 0, 0, 1, 1).is_err());
+}
+
+#[test]
+fn test_blend_pixel_source_over() {
+    // 50%透明的红色叠加在不透明蓝色上得到紫色
+    let mut dst = [0u8, 0, 255, 255];
+    blend_pixel(&[255, 0, 0, 128], &mut dst, BlendMode::SourceOver);
+    assert_eq!(dst, [128, 0, 127, 255]);
+
+    // 全透明源像素不改变目标
+    let mut dst = [0u8, 0, 255, 255];
+    blend_pixel(&[255, 0, 0, 0], &mut dst, BlendMode::SourceOver);
+    assert_eq!(dst, [0, 0, 255, 255]);
+
+    // Copy模式直接覆盖
+    let mut dst = [0u8, 0, 255, 255];
+    blend_pixel(&[255, 0, 0, 0], &mut dst, BlendMode::Copy);
+    assert_eq!(dst, [255, 0, 0, 0]);
+
+    // 两个全透明像素
+    let mut dst = [9u8, 9, 9, 0];
+    blend_pixel(&[1, 2, 3, 0], &mut dst, BlendMode::SourceOver);
+    assert_eq!(dst, [0, 0, 0, 0]);
+}
+
+#[test]
+fn test_tone_map_min_max_stretch() {
+    // 只使用30000-31000的16位灰度样本
+    let samples: Vec<u16> = (0..=10).map(|i| 30000 + i * 100).collect();
+
+    // 线性缩放后几乎都挤在同一个灰度级附近
+    let linear = tone_map_16_to_8(&samples, 1, false, ToneMap::Linear);
+    assert_eq!(linear.first(), Some(&117));
+    assert_eq!(linear.last(), Some(&121));
+
+    // 拉伸后覆盖整个0-255范围
+    let stretched = tone_map_16_to_8(&samples, 1, false, ToneMap::MinMaxStretch);
+    assert_eq!(stretched.first(), Some(&0));
+    assert_eq!(stretched.last(), Some(&255));
+    assert_eq!(stretched[5], 128);
+    assert!(stretched.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_tone_map_gamma_and_alpha() {
+    // 灰度+Alpha：alpha通道不参与拉伸
+    let samples = [30000u16, 65535, 31000, 0];
+    let stretched = tone_map_16_to_8(&samples, 2, true, ToneMap::MinMaxStretch);
+    assert_eq!(stretched, vec![0, 255, 255, 0]);
+
+    // gamma为1时等同于线性缩放
+    let samples = [0u16, 16384, 65535];
+    assert_eq!(tone_map_16_to_8(&samples, 1, false, ToneMap::Gamma(1.0)), vec![0, 64, 255]);
+    assert_eq!(tone_map_16_to_8(&samples, 1, false, ToneMap::Gamma(2.0)), vec![0, 128, 255]);
+}
+
+#[test]
+fn test_chroma_key_green_within_tolerance() {
+    // 绿色通道从235渐变到255，其余通道从0到20
+    let mut data: Vec<u8> = (0..=20u8).flat_map(|i| [i, 235 + i, i, 255]).collect();
+    let keyed = chroma_key(&mut data, [0, 255, 0], 10);
+
+    // 只有与纯绿色每通道相差不超过10的像素（i = 10）被抠除
+    assert_eq!(keyed, 1);
+    for (i, pixel) in data.chunks_exact(4).enumerate() {
+        assert_eq!(pixel[3], if i == 10 { 0 } else { 255 });
+        assert_eq!(pixel[..3], [i as u8, 235 + i as u8, i as u8]);
+    }
+
+    // 纯绿渐变：与纯绿色相差不超过10的都被抠除
+    let mut gradient: Vec<u8> = (0..=255u8).flat_map(|g| [0, g, 0, 255]).collect();
+    assert_eq!(chroma_key(&mut gradient, [0, 255, 0], 10), 11);
+    assert!(gradient.chunks_exact(4).all(|pixel| (pixel[3] == 0) == (pixel[1] >= 245)));
+
+    // tolerance为0时只匹配完全相同的颜色
+    let mut exact = vec![0, 255, 0, 255, 0, 254, 0, 255];
+    assert_eq!(chroma_key(&mut exact, [0, 255, 0], 0), 1);
+    assert_eq!(exact, vec![0, 255, 0, 0, 0, 254, 0, 255]);
+}
+
+#[test]
+fn test_feather_alpha_softens_hard_edge() {
+    // 4x2图像，左两列透明、右两列不透明
+    let mut data: Vec<u8> = (0..8u8)
+        .flat_map(|i| [i, 100, 200, if i % 4 < 2 { 0 } else { 255 }])
+        .collect();
+    feather_alpha(&mut data, 4, 2, 1).unwrap();
+
+    // 边界两侧变为渐变，边缘窗口截断后只平均图像内的像素
+    for row in data.chunks_exact(16) {
+        let alpha: Vec<u8> = row.chunks_exact(4).map(|pixel| pixel[3]).collect();
+        assert_eq!(alpha, vec![0, 85, 170, 255]);
+    }
+    // RGB保持不变
+    for (i, pixel) in data.chunks_exact(4).enumerate() {
+        assert_eq!(pixel[..3], [i as u8, 100, 200]);
+    }
+
+    // 半径为0不做处理，尺寸不符时报错
+    let mut copy = vec![1, 2, 3, 0, 4, 5, 6, 255];
+    feather_alpha(&mut copy, 2, 1, 0).unwrap();
+    assert_eq!(copy, vec![1, 2, 3, 0, 4, 5, 6, 255]);
+    assert!(feather_alpha(&mut copy, 3, 1, 1).is_err());
+    // width * height * 4 超出u32时报错而不是溢出
+    assert!(feather_alpha(&mut copy, 65536, 65536, 1).is_err());
+}
+
+#[test]
+fn test_slice_grid_sprite_sheet() {
+    // 64x32图像，R/G通道为像素坐标
+    let (width, height) = (64u32, 32u32);
+    let data: Vec<u8> = (0..width * height)
+        .flat_map(|i| [(i % width) as u8, (i / width) as u8, 0, 255])
+        .collect();
+    let cells = slice_grid(&data, width, height, 4, 2).unwrap();
+    assert_eq!(cells.len(), 8);
+    assert!(cells.iter().all(|cell| cell.len() == 16 * 16 * 4));
+
+    // 第3帧为第一行第4列，原点在(48, 0)；(5, 7)处的像素来自原图(53, 7)
+    let offset = (7 * 16 + 5) * 4;
+    assert_eq!(cells[3][offset..offset + 4], [53, 7, 0, 255]);
+    // 第5帧为第二行第2列，原点在(16, 16)
+    assert_eq!(cells[5][..4], [16, 16, 0, 255]);
+
+    // 不能整除或网格为空时报错
+    assert!(slice_grid(&data, width, height, 5, 2).is_err());
+    assert!(slice_grid(&data, width, height, 4, 0).is_err());
+    assert!(slice_grid(&data, 65536, 65536, 4, 2).is_err());
+}
+
+#[test]
+fn test_clamp_u8_saturates() {
+    // 300截断为255，而不是按u8回绕成44
+    assert_eq!(clamp_u8(300), 255);
+    assert_eq!(300i32 as u8, 44);
+    assert_eq!(clamp_u8(-20), 0);
+    assert_eq!(clamp_u8(128), 128);
+
+    assert_eq!(clamp_u8_f64(299.7), 255);
+    assert_eq!(clamp_u8_f64(-3.2), 0);
+    assert_eq!(clamp_u8_f64(12.5), 13);
+    assert_eq!(clamp_u8_f64(f64::NAN), 0);
+}
+
+#[test]
+fn test_brightness_contrast_posterize() {
+    // 亮度：超出范围时截断，alpha不变
+    let mut data = vec![10, 100, 250, 128];
+    adjust_brightness(&mut data, 300);
+    assert_eq!(data, vec![255, 255, 255, 128]);
+    adjust_brightness(&mut data, -300);
+    assert_eq!(data, vec![0, 0, 0, 128]);
+
+    // 对比度：以128为中心缩放
+    let mut data = vec![128, 100, 200, 7];
+    adjust_contrast(&mut data, 2.0);
+    assert_eq!(data, vec![128, 72, 255, 7]);
+    adjust_contrast(&mut data, 0.0);
+    assert_eq!(data, vec![128, 128, 128, 7]);
+
+    // 色调分离：两级时只剩0和255
+    let mut data = vec![100, 200, 127, 50];
+    posterize(&mut data, 2).unwrap();
+    assert_eq!(data, vec![0, 255, 0, 50]);
+    let mut data = vec![0, 90, 180, 255, 255, 255, 255, 255];
+    posterize(&mut data, 3).unwrap();
+    assert_eq!(data, vec![0, 127, 255, 255, 255, 255, 255, 255]);
+    assert!(posterize(&mut data, 1).is_err());
+}
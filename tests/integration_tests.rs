@@ -5,6 +5,33 @@ use rust_png::{PNG, PNGSync};
 
 wasm_bindgen_test_configure!(run_in_browser);
 
+/// 使用png crate生成测试用的PNG数据（快速压缩、不滤镜）
+fn encode_fixture(width: u32, height: u32, color: png::ColorType, depth: png::BitDepth, data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut output, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(depth);
+        encoder.set_compression(png::Compression::Fast);
+        encoder.set_filter(png::FilterType::NoFilter);
+        encoder.add_text_chunk("Title".to_string(), "fixture".to_string()).unwrap();
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(data).unwrap();
+    }
+    output
+}
+
+/// 使用png crate按原始格式解码像素
+fn decode_native(data: &[u8]) -> Vec<u8> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::IDENTITY);
+    let mut reader = decoder.read_info().unwrap();
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).unwrap();
+    buffer.truncate(info.buffer_size());
+    buffer
+}
+
 #[wasm_bindgen_test]
 fn test_png_interlace_detection() {
     // 创建PNG实例
@@ -86,3 +113,1065 @@ fn test_png_interlace_with_options() {
     let total_pixels = js_sys::Reflect::get(&stats, &"totalPixels".into()).unwrap();
     assert_eq!(total_pixels.as_f64().unwrap(), 1024.0); // 32*32
 }
+
+#[wasm_bindgen_test]
+fn test_png_reoptimize_is_lossless() {
+    // 低压缩级别生成的渐变图像
+    let (width, height) = (64, 64);
+    let data: Vec<u8> = (0..width * height)
+        .flat_map(|i| [(i % 64) as u8 * 4, (i / 64) as u8 * 4, 128])
+        .collect();
+    let original = encode_fixture(width, height, png::ColorType::Rgb, png::BitDepth::Eight, &data);
+
+    let mut png = PNG::new(None);
+    png.parse(&original, None).unwrap();
+    let optimized = png.reoptimize().unwrap();
+
+    // 体积不增加，像素完全一致
+    assert!(optimized.len() <= original.len());
+    assert_eq!(decode_native(&optimized), data);
+
+    // 颜色类型和位深度保持不变
+    let mut reparsed = PNG::new(None);
+    reparsed.parse(&optimized, None).unwrap();
+    assert_eq!(reparsed.color_type(), 2);
+    assert_eq!(reparsed.bit_depth(), 8);
+
+    // 无法再缩小时原样返回输入字节：重新编码会把gAMA移到私有chunk之前
+    use rust_png::png_chunks::{ChunkType, PNGChunk};
+    let mut input = optimized[..8 + 25].to_vec();
+    input.extend_from_slice(&PNGChunk::new(ChunkType::from_name("zzZz").unwrap(), vec![1, 2, 3]).to_bytes());
+    input.extend_from_slice(&PNGChunk::new(ChunkType::GAMA, 45455u32.to_be_bytes().to_vec()).to_bytes());
+    input.extend_from_slice(&optimized[8 + 25..]);
+    let mut png = PNG::new(None);
+    png.parse(&input, None).unwrap();
+    assert_eq!(png.reoptimize().unwrap(), input);
+
+    // APNG重新压缩后fcTL/fdAT保持原有顺序，每一帧都能解码
+    let frames: Vec<Vec<u8>> = (0..2u8).map(|i| vec![i * 100; 64 * 64 * 3]).collect();
+    let mut animated = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut animated, 64, 64);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_compression(png::Compression::Fast);
+        encoder.set_animated(2, 0).unwrap();
+        let mut writer = encoder.write_header().unwrap();
+        for frame in &frames {
+            writer.write_image_data(frame).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+    let mut png = PNG::new(None);
+    png.parse(&animated, None).unwrap();
+    let optimized = png.reoptimize().unwrap();
+    let mut reader = png::Decoder::new(&optimized[..]).read_info().unwrap();
+    for frame in &frames {
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(&pixels, frame);
+    }
+}
+
+#[wasm_bindgen_test]
+fn test_png_parse_with_bad_ancillary_crc() {
+    // tEXt的CRC错误不影响像素解码
+    let mut data = encode_fixture(2, 1, png::ColorType::Rgb, png::BitDepth::Eight, &[10, 20, 30, 40, 50, 60]);
+    let text_pos = data.windows(4).position(|w| w == b"tEXt").unwrap();
+    let text_len = u32::from_be_bytes([data[text_pos - 4], data[text_pos - 3], data[text_pos - 2], data[text_pos - 1]]) as usize;
+    data[text_pos + 4 + text_len] ^= 0xff;
+
+    let mut png = PNG::new(None);
+    png.parse(&data, None).unwrap();
+
+    let pixel = png.get_pixel(1, 0).unwrap();
+    assert_eq!(pixel.get(0).as_f64().unwrap(), 40.0);
+    assert_eq!(pixel.get(3).as_f64().unwrap(), 255.0);
+
+    // chunk索引中标记该chunk的CRC错误
+    let index = png.get_chunk_index().unwrap();
+    let bad = (0..index.length())
+        .map(|i| index.get(i))
+        .find(|info| js_sys::Reflect::get(info, &"typeCode".into()).unwrap().as_string().unwrap() == "tEXt")
+        .unwrap();
+    assert_eq!(js_sys::Reflect::get(&bad, &"crcOk".into()).unwrap().as_bool(), Some(false));
+}
+
+#[wasm_bindgen_test]
+fn test_png_two_bit_grayscale_with_and_without_expand() {
+    // 2位灰度，样本0/1/2/3打包在一个字节中
+    let encoded = encode_fixture(4, 1, png::ColorType::Grayscale, png::BitDepth::Two, &[0b00_01_10_11]);
+    let expected_rgba = vec![0, 0, 0, 255, 85, 85, 85, 255, 170, 170, 170, 255, 255, 255, 255, 255];
+
+    // 默认展开为8位样本
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"keepRaw".into(), &true.into()).unwrap();
+    let mut png = PNG::new(Some(options.into()));
+    png.parse(&encoded, None).unwrap();
+    assert_eq!(png.stored_bit_depth(), 2);
+    assert_eq!(png.delivered_bit_depth(), 8);
+    assert_eq!(png.get_raw_data().unwrap().to_vec(), vec![0, 85, 170, 255]);
+    assert_eq!(png.to_rgba_bytes(), expected_rgba);
+
+    // 关闭展开时保留按位打包的原始数据
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"expand".into(), &false.into()).unwrap();
+    js_sys::Reflect::set(&options, &"keepRaw".into(), &true.into()).unwrap();
+    let mut raw = PNG::new(Some(options.into()));
+    raw.parse(&encoded, None).unwrap();
+    assert_eq!(raw.stored_bit_depth(), 2);
+    assert_eq!(raw.delivered_bit_depth(), 2);
+    assert_eq!(raw.get_raw_data().unwrap().to_vec(), vec![0b00_01_10_11]);
+    assert_eq!(raw.to_rgba_bytes(), expected_rgba);
+}
+
+#[wasm_bindgen_test]
+fn test_png_compact_grayscale() {
+    let (width, height) = (4u32, 3u32);
+    let data: Vec<u8> = (0..width * height).map(|i| (i * 20) as u8).collect();
+    let encoded = encode_fixture(width, height, png::ColorType::Grayscale, png::BitDepth::Eight, &data);
+
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"compactGrayscale".into(), &true.into()).unwrap();
+    let mut png = PNG::new(Some(options.into()));
+    png.parse(&encoded, None).unwrap();
+
+    // 内部只保存每像素1字节的灰度数据
+    assert_eq!(png.get_gray_data().unwrap().to_vec(), data);
+    let pixel: Vec<u32> = png.get_pixel(3, 2).unwrap().iter().map(|v| v.as_f64().unwrap() as u32).collect();
+    assert_eq!(pixel, vec![220, 220, 220, 255]);
+
+    // 读取RGBA时按需展开，结果与默认解码一致
+    let mut full = PNG::new(None);
+    full.parse(&encoded, None).unwrap();
+    assert!(full.get_gray_data().is_none());
+    assert_eq!(png.to_rgba_bytes(), full.to_rgba_bytes());
+
+    // 编辑像素前自动展开为RGBA
+    png.set_pixel(0, 0, 1, 2, 3, 4).unwrap();
+    assert!(png.get_gray_data().is_none());
+    assert_eq!(&png.to_rgba_bytes()[..8], &[1, 2, 3, 4, 20, 20, 20, 255]);
+}
+
+#[wasm_bindgen_test]
+fn test_png_compact_grayscale_consumers() {
+    let compact = || {
+        let options = js_sys::Object::new();
+        js_sys::Reflect::set(&options, &"compactGrayscale".into(), &true.into()).unwrap();
+        PNG::new(Some(options.into()))
+    };
+
+    // 灰度+Alpha：通道数在解析时确定，不依赖缓冲区长度
+    let data: Vec<u8> = (0..16).flat_map(|i| [i * 16, if i == 5 { 128 } else { 255 }]).collect();
+    let encoded = encode_fixture(4, 4, png::ColorType::GrayscaleAlpha, png::BitDepth::Eight, &data);
+    let mut png = compact();
+    png.parse(&encoded, None).unwrap();
+    assert_eq!(png.get_gray_data().unwrap().to_vec(), data);
+    assert!(!png.is_opaque());
+    assert!(png.has_translucency());
+    assert_eq!(png.alpha_levels(), 2);
+
+    // pack直接写出灰度样本，重新解码后像素不变
+    let mut full = PNG::new(None);
+    full.parse(&encoded, None).unwrap();
+    let mut repacked = PNG::new(None);
+    repacked.parse(&png.pack(None).unwrap(), None).unwrap();
+    assert_eq!(repacked.color_type(), 4);
+    assert_eq!(repacked.to_rgba_bytes(), full.to_rgba_bytes());
+
+    // 缩放前自动展开为RGBA
+    png.resize(2, 2, false).unwrap();
+    full.resize(2, 2, false).unwrap();
+    assert!(png.get_gray_data().is_none());
+    assert_eq!(png.to_rgba_bytes(), full.to_rgba_bytes());
+
+    let opaque = encode_fixture(2, 2, png::ColorType::Grayscale, png::BitDepth::Eight, &[0, 85, 170, 255]);
+    let mut png = compact();
+    png.parse(&opaque, None).unwrap();
+    assert!(png.is_opaque());
+    assert_eq!(png.to_rgb_bytes(None), vec![0, 0, 0, 85, 85, 85, 170, 170, 170, 255, 255, 255]);
+}
+
+#[wasm_bindgen_test]
+fn test_png_16bit_grayscale_ramp() {
+    // 16位灰度渐变（高度图）
+    let samples: Vec<u16> = (0..16u32).map(|i| (i * 4369) as u16).collect();
+    let data: Vec<u8> = samples.iter().flat_map(|v| v.to_be_bytes()).collect();
+    let encoded = encode_fixture(16, 1, png::ColorType::Grayscale, png::BitDepth::Sixteen, &data);
+
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"keepNativeDepth".into(), &true.into()).unwrap();
+    let mut png = PNG::new(Some(options.into()));
+    png.parse(&encoded, None).unwrap();
+
+    // 8位预览
+    let last = png.get_pixel(15, 0).unwrap();
+    assert_eq!(last.get(0).as_f64().unwrap(), 255.0);
+    let middle = png.get_pixel(8, 0).unwrap();
+    assert_eq!(middle.get(0).as_f64().unwrap(), 136.0);
+
+    // 16位原始值
+    let native = png.get_native_data().unwrap();
+    assert_eq!(native.to_vec(), samples);
+}
+
+#[wasm_bindgen_test]
+fn test_png_grayscale_alpha_keeps_alpha() {
+    let data = [50u8, 0, 100, 128, 150, 255];
+    let encoded = encode_fixture(3, 1, png::ColorType::GrayscaleAlpha, png::BitDepth::Eight, &data);
+
+    let mut png = PNG::new(None);
+    png.parse(&encoded, None).unwrap();
+
+    for (x, (gray, alpha)) in [(50.0, 0.0), (100.0, 128.0), (150.0, 255.0)].into_iter().enumerate() {
+        let pixel = png.get_pixel(x as u32, 0).unwrap();
+        assert_eq!(pixel.get(0).as_f64().unwrap(), gray);
+        assert_eq!(pixel.get(3).as_f64().unwrap(), alpha);
+    }
+}
+
+#[wasm_bindgen_test]
+fn test_png_partial_decode_fills_remaining_rows() {
+    // 10行图像截断IDAT后，未解码的行使用partialFill填充
+    let (width, height) = (16u32, 10u32);
+    let data: Vec<u8> = (0..width * height).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8).collect();
+    let mut encoded = encode_fixture(width, height, png::ColorType::Grayscale, png::BitDepth::Eight, &data);
+    let idat = encoded.windows(4).position(|w| w == b"IDAT").unwrap();
+    let idat_length = u32::from_be_bytes([encoded[idat - 4], encoded[idat - 3], encoded[idat - 2], encoded[idat - 1]]) as usize;
+    encoded.truncate(idat + 4 + idat_length / 2);
+
+    // 默认模式下解码失败
+    assert!(PNG::new(None).parse(&encoded, None).is_err());
+
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"partial".into(), &true.into()).unwrap();
+    let fill = js_sys::Array::of4(&255.into(), &0.into(), &255.into(), &255.into());
+    js_sys::Reflect::set(&options, &"partialFill".into(), &fill).unwrap();
+    let mut png = PNG::new(Some(options.into()));
+    // 先解析一幅调色板图像，截断的图像不能沿用它的索引
+    let palette_image = {
+        let mut output = Vec::new();
+        let mut encoder = png::Encoder::new(&mut output, 2, 1);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_palette(vec![0, 0, 0, 255, 255, 255]);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[0, 1]).unwrap();
+        writer.finish().unwrap();
+        output
+    };
+    png.parse(&palette_image, None).unwrap();
+    assert!(png.get_indices().is_some());
+
+    // 截断的图像同样调用回调
+    let callback = js_sys::Function::new_no_args("globalThis.partialParsed = true;");
+    png.parse(&encoded, Some(callback)).unwrap();
+    let called = js_sys::Reflect::get(&js_sys::global(), &"partialParsed".into()).unwrap();
+    assert_eq!(called.as_bool(), Some(true));
+    assert!(png.get_indices().is_none());
+
+    let rows = png.decoded_rows();
+    assert!(rows > 0 && rows < height, "decoded_rows={}", rows);
+    let warnings: Vec<String> = png.get_warnings().iter().map(|warning| warning.as_string().unwrap()).collect();
+    assert_eq!(warnings, vec![format!("Image data truncated: decoded {} of {} rows", rows, height)]);
+
+    let first = png.get_pixel(0, 0).unwrap();
+    assert_eq!(first.get(0).as_f64().unwrap(), data[0] as f64);
+
+    let filled = png.get_pixel(0, height - 1).unwrap();
+    assert_eq!(filled.get(0).as_f64().unwrap(), 255.0);
+    assert_eq!(filled.get(1).as_f64().unwrap(), 0.0);
+}
+
+#[wasm_bindgen_test]
+fn test_png_palette_cycling() {
+    // 4色调色板图像，每个像素使用不同索引
+    let colors: [[u8; 3]; 4] = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 0]];
+    let mut encoded = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut encoded, 4, 1);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(colors.concat());
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[0, 1, 2, 3]).unwrap();
+    }
+
+    let mut png = PNG::new(None);
+    png.parse(&encoded, None).unwrap();
+
+    // 调色板循环：每个条目向前移动一位
+    for shift in 1..=4 {
+        let cycled: Vec<u8> = (0..4).flat_map(|i| colors[(i + shift) % 4]).collect();
+        png.set_palette(&cycled).unwrap();
+
+        let rgba = png.get_rgba8_array().unwrap().to_vec();
+        for x in 0..4 {
+            assert_eq!(&rgba[x * 4..x * 4 + 3], &colors[(x + shift) % 4][..]);
+            assert_eq!(rgba[x * 4 + 3], 255);
+        }
+    }
+
+    png.set_palette_alpha(&[0]).unwrap();
+    assert_eq!(png.get_pixel(0, 0).unwrap().get(3).as_f64().unwrap(), 0.0);
+
+    // 长度必须是3的倍数
+    assert!(png.set_palette(&[1, 2]).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_png_get_indices() {
+    // 4色2位调色板图像，4x2像素
+    let colors: [[u8; 3]; 4] = [[0, 0, 0], [255, 0, 0], [0, 255, 0], [0, 0, 255]];
+    let indices = [0u8, 1, 2, 3, 3, 2, 1, 0];
+    let mut encoded = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut encoded, 4, 2);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Two);
+        encoder.set_palette(colors.concat());
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[0b00_01_10_11, 0b11_10_01_00]).unwrap();
+    }
+
+    let mut png = PNG::new(None);
+    png.parse(&encoded, None).unwrap();
+    assert_eq!(png.get_indices().unwrap().to_vec(), indices.to_vec());
+
+    // 关闭展开时结果相同
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"expand".into(), &false.into()).unwrap();
+    let mut raw = PNG::new(Some(options.into()));
+    raw.parse(&encoded, None).unwrap();
+    assert_eq!(raw.get_indices().unwrap().to_vec(), indices.to_vec());
+
+    // 非调色板图像没有索引
+    let mut rgb = PNG::new(None);
+    rgb.parse(&encode_fixture(1, 1, png::ColorType::Rgb, png::BitDepth::Eight, &[1, 2, 3]), None).unwrap();
+    assert!(rgb.get_indices().is_none());
+}
+
+#[wasm_bindgen_test]
+fn test_png_set_palette_on_non_indexed_fails() {
+    let encoded = encode_fixture(1, 1, png::ColorType::Rgb, png::BitDepth::Eight, &[1, 2, 3]);
+    let mut png = PNG::new(None);
+    png.parse(&encoded, None).unwrap();
+
+    assert!(png.set_palette(&[0, 0, 0]).is_err());
+    assert!(png.set_palette_alpha(&[0]).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_png_apply_exif_orientation() {
+    use rust_png::png_chunks::{ChunkType, PNGChunk};
+    use rust_png::png_packer::{PackerOptions, PNGPacker};
+
+    // 小端TIFF，Orientation = 6（需顺时针旋转90度）
+    let exif = vec![
+        0x49, 0x49, 0x2a, 0x00, 0x08, 0x00, 0x00, 0x00,
+        0x01, 0x00, 0x12, 0x01, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+    ];
+    let data: Vec<u8> = (0..6u8).flat_map(|i| [i * 40, 0, 0, 255]).collect();
+    let options = PackerOptions {
+        width: 2,
+        height: 3,
+        chunks: vec![PNGChunk::new(ChunkType::EXIF, exif)],
+        ..Default::default()
+    };
+    let encoded = PNGPacker::new(options).pack(&data).unwrap();
+
+    let mut png = PNG::new(None);
+    png.parse(&encoded, None).unwrap();
+    png.apply_exif_orientation().unwrap();
+
+    assert_eq!((png.width(), png.height()), (3, 2));
+    assert_eq!(png.get_pixel(0, 0).unwrap().get(0).as_f64().unwrap(), 160.0);
+    assert_eq!(png.get_pixel(2, 1).unwrap().get(0).as_f64().unwrap(), 40.0);
+
+    // 方向标签重置为1
+    let exif = png.get_exif().unwrap().to_vec();
+    assert_eq!(&exif[18..20], &[1, 0]);
+}
+
+#[wasm_bindgen_test]
+fn test_png_validate() {
+    let encoded = encode_fixture(2, 2, png::ColorType::Rgb, png::BitDepth::Eight, &[0; 12]);
+    let report = PNG::validate(&encoded).unwrap();
+    assert_eq!(js_sys::Reflect::get(&report, &"valid".into()).unwrap().as_bool(), Some(true));
+
+    let report = PNG::validate(&encoded[..20]).unwrap();
+    assert_eq!(js_sys::Reflect::get(&report, &"valid".into()).unwrap().as_bool(), Some(false));
+    let errors: js_sys::Array = js_sys::Reflect::get(&report, &"errors".into()).unwrap().into();
+    assert!(errors.length() > 0);
+}
+
+#[wasm_bindgen_test]
+fn test_png_decode_into_reused_buffer() {
+    let rgb: Vec<u8> = (0..12u8).map(|i| i * 20).collect();
+    let color = encode_fixture(2, 2, png::ColorType::Rgb, png::BitDepth::Eight, &rgb);
+    let gray = encode_fixture(2, 2, png::ColorType::Grayscale, png::BitDepth::Eight, &[10, 20, 30, 40]);
+
+    let mut buffer = vec![0u8; 2 * 2 * 4];
+
+    // 同一缓冲区连续解码两次
+    let size: Vec<f64> = PNG::decode_into(&color, &mut buffer).unwrap().iter().map(|v| v.as_f64().unwrap()).collect();
+    assert_eq!(size, vec![2.0, 2.0]);
+    let first = buffer.clone();
+    assert_eq!(&first[..8], &[0, 20, 40, 255, 60, 80, 100, 255]);
+    PNG::decode_into(&color, &mut buffer).unwrap();
+    assert_eq!(buffer, first);
+
+    // 灰度图像在缓冲区内原地展开为RGBA
+    PNG::decode_into(&gray, &mut buffer).unwrap();
+    assert_eq!(buffer, vec![10, 10, 10, 255, 20, 20, 20, 255, 30, 30, 30, 255, 40, 40, 40, 255]);
+
+    // 缓冲区过小
+    let mut small = vec![0u8; 15];
+    assert!(PNG::decode_into(&color, &mut small).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_png_to_rgb_and_rgba_bytes() {
+    let rgba = [10u8, 20, 30, 255, 250, 250, 250, 0];
+    let encoded = encode_fixture(2, 1, png::ColorType::Rgba, png::BitDepth::Eight, &rgba);
+    let mut png = PNG::new(None);
+    png.parse(&encoded, None).unwrap();
+
+    assert_eq!(png.to_rgba_bytes(), rgba.to_vec());
+    assert_eq!(png.to_rgb_bytes(None), vec![10, 20, 30, 250, 250, 250]);
+    assert_eq!(png.to_rgb_bytes(Some(vec![0, 0, 255])), vec![10, 20, 30, 0, 0, 255]);
+}
+
+#[wasm_bindgen_test]
+fn test_png_content_hash_ignores_encoding() {
+    use rust_png::png_packer::{PackerOptions, PNGPacker};
+
+    let data: Vec<u8> = (0..8 * 8 * 4u32).map(|i| (i * 37 % 256) as u8).collect();
+    let encode = |filter_type: u8| PNGPacker::new(PackerOptions {
+        width: 8,
+        height: 8,
+        filter_type: Some(filter_type),
+        ..Default::default()
+    }).pack(&data).unwrap();
+
+    // 不同滤镜产生不同的文件字节
+    let sub = encode(1);
+    let paeth = encode(4);
+    assert_ne!(sub, paeth);
+
+    let hash = |encoded: &[u8]| {
+        let mut png = PNG::new(None);
+        png.parse(encoded, None).unwrap();
+        png.content_hash()
+    };
+    assert_eq!(hash(&sub), hash(&paeth));
+    assert_eq!(hash(&sub).len(), 16);
+
+    // 像素不同时哈希不同
+    let mut other = data.clone();
+    other[0] ^= 1;
+    let changed = PNGPacker::new(PackerOptions { width: 8, height: 8, ..Default::default() }).pack(&other).unwrap();
+    assert_ne!(hash(&sub), hash(&changed));
+}
+
+#[wasm_bindgen_test]
+fn test_png_get_filter_bytes() {
+    use rust_png::png_packer::{PackerOptions, PNGPacker};
+
+    let data: Vec<u8> = (0..6 * 5 * 4u32).map(|i| (i * 11 % 256) as u8).collect();
+    let encoded = PNGPacker::new(PackerOptions {
+        width: 6,
+        height: 5,
+        filter_type: Some(4),
+        ..Default::default()
+    }).pack(&data).unwrap();
+
+    assert_eq!(PNG::get_filter_bytes(&encoded).unwrap(), vec![4; 5]);
+}
+
+#[wasm_bindgen_test]
+fn test_png_bitblt_blend_source_over() {
+    use rust_png::{BlendMode, BlitRegion};
+
+    let sprite_data = [255u8, 0, 0, 128, 255, 0, 0, 0];
+    let sprite = encode_fixture(2, 1, png::ColorType::Rgba, png::BitDepth::Eight, &sprite_data);
+    let background = encode_fixture(2, 1, png::ColorType::Rgb, png::BitDepth::Eight, &[0, 0, 255, 0, 0, 255]);
+
+    let mut src = PNG::new(None);
+    src.parse(&sprite, None).unwrap();
+    let mut dst = PNG::new(None);
+    dst.parse(&background, None).unwrap();
+
+    src.bitblt_blend(&mut dst, BlitRegion::new(0, 0, 2, 1, 0, 0), BlendMode::SourceOver).unwrap();
+
+    // 半透明像素合成为紫色，全透明像素保持背景
+    assert_eq!(dst.to_rgba_bytes(), vec![128, 0, 127, 255, 0, 0, 255, 255]);
+}
+
+#[wasm_bindgen_test]
+fn test_png_clone_image_is_independent() {
+    let encoded = encode_fixture(2, 1, png::ColorType::Rgba, png::BitDepth::Eight, &[1, 2, 3, 255, 4, 5, 6, 255]);
+    let mut original = PNG::new(None);
+    original.parse(&encoded, None).unwrap();
+
+    let mut edited = original.clone_image();
+    edited.set_pixel(0, 0, 200, 100, 50, 255).unwrap();
+
+    // 修改副本不影响原图
+    assert_eq!(original.to_rgba_bytes(), vec![1, 2, 3, 255, 4, 5, 6, 255]);
+    assert_eq!(edited.to_rgba_bytes(), vec![200, 100, 50, 255, 4, 5, 6, 255]);
+    assert_eq!(edited.width(), original.width());
+    assert_ne!(edited.content_hash(), original.content_hash());
+}
+
+#[wasm_bindgen_test]
+fn test_png_tone_map_16_to_8() {
+    use rust_png::ToneMapMode;
+
+    // 16位灰度，取值只在30000-31000之间
+    let data: Vec<u8> = [30000u16, 30500, 31000].iter().flat_map(|v| v.to_be_bytes()).collect();
+    let encoded = encode_fixture(3, 1, png::ColorType::Grayscale, png::BitDepth::Sixteen, &data);
+    let mut image = PNG::new(None);
+    image.parse(&encoded, None).unwrap();
+
+    image.tone_map_16_to_8(ToneMapMode::MinMaxStretch, None).unwrap();
+    assert_eq!(image.to_rgba_bytes(), vec![0, 0, 0, 255, 128, 128, 128, 255, 255, 255, 255, 255]);
+
+    // 可以切换回线性映射
+    image.tone_map_16_to_8(ToneMapMode::Linear, None).unwrap();
+    assert_eq!(image.to_rgba_bytes()[..4], [117, 117, 117, 255]);
+
+    // 16位tRNS关键色在映射后仍为全透明；30000和30100映射为同一8位值，只有关键色透明
+    let data: Vec<u8> = [30000u16, 30100, 31000].iter().flat_map(|v| v.to_be_bytes()).collect();
+    let mut encoded = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut encoded, 3, 1);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Sixteen);
+        encoder.set_trns(30100u16.to_be_bytes().to_vec());
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&data).unwrap();
+    }
+    let mut keyed = PNG::new(None);
+    keyed.parse(&encoded, None).unwrap();
+    keyed.tone_map_16_to_8(ToneMapMode::Linear, None).unwrap();
+    assert_eq!(keyed.to_rgba_bytes(), vec![117, 117, 117, 255, 117, 117, 117, 0, 121, 121, 121, 255]);
+}
+
+#[wasm_bindgen_test]
+fn test_png_correct_pixel_aspect() {
+    // 2x2图像，pHYs只声明宽高比：像素宽:高 = 2:1
+    let mut encoded = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut encoded, 2, 2);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_pixel_dims(Some(png::PixelDimensions { xppu: 1, yppu: 2, unit: png::Unit::Unspecified }));
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255; 16]).unwrap();
+    }
+
+    let mut image = PNG::new(None);
+    image.parse(&encoded, None).unwrap();
+    assert_eq!(image.pixel_aspect_ratio(), Some(2.0));
+
+    // 宽度加倍，高度不变；再次调用不做任何修改
+    assert!(image.correct_pixel_aspect().unwrap());
+    assert_eq!((image.width(), image.height()), (4, 2));
+    assert_eq!(image.pixel_aspect_ratio(), Some(1.0));
+    assert!(!image.correct_pixel_aspect().unwrap());
+}
+
+#[wasm_bindgen_test]
+fn test_png_chroma_key_adds_alpha() {
+    let data = [0, 250, 5, 200, 0, 0, 0, 255, 0];
+    let mut png = PNG::new(None);
+    png.parse(&encode_fixture(3, 1, png::ColorType::Rgb, png::BitDepth::Eight, &data), None).unwrap();
+    png.chroma_key(0, 255, 0, 10).unwrap();
+
+    // 接近纯绿的两个像素变为透明，图像改为RGBA
+    assert_eq!(png.to_rgba_bytes(), vec![0, 250, 5, 0, 200, 0, 0, 255, 0, 255, 0, 0]);
+    assert!(png.alpha());
+    assert_eq!(png.color_type(), 6);
+}
+
+#[wasm_bindgen_test]
+fn test_png_slice_grid_frames() {
+    let data: Vec<u8> = (0..64 * 32).flat_map(|i| [(i % 64) as u8, (i / 64) as u8, 0]).collect();
+    let mut png = PNG::new(None);
+    png.parse(&encode_fixture(64, 32, png::ColorType::Rgb, png::BitDepth::Eight, &data), None).unwrap();
+
+    let frames = png.slice_grid(4, 2).unwrap();
+    assert_eq!(frames.length(), 8);
+    assert!(png.slice_grid(3, 2).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_decode_batch_reports_per_file_errors() {
+    let valid = encode_fixture(2, 2, png::ColorType::Rgb, png::BitDepth::Eight, &[7; 12]);
+    let truncated = &valid[..30];
+    let files = js_sys::Array::new();
+    files.push(&js_sys::Uint8Array::from(&valid[..]));
+    files.push(&js_sys::Uint8Array::from(truncated));
+
+    // 按输入顺序：第一个成功，第二个返回错误对象
+    let results = rust_png::decode_batch(&files);
+    assert_eq!(results.length(), 2);
+    assert!(!js_sys::Reflect::has(&results.get(0), &"error".into()).unwrap());
+    let error = js_sys::Reflect::get(&results.get(1), &"error".into()).unwrap();
+    assert!(error.as_string().is_some_and(|message| !message.is_empty()));
+}
+
+#[wasm_bindgen_test]
+fn test_png_make_thumbnail() {
+    let data: Vec<u8> = (0..100 * 50).flat_map(|_| [10, 200, 30, 255]).collect();
+    let mut png = PNG::new(None);
+    png.parse(&encode_fixture(100, 50, png::ColorType::Rgba, png::BitDepth::Eight, &data), None).unwrap();
+
+    let thumbnail = png.make_thumbnail(20).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (20, 10));
+    assert!(thumbnail.to_rgba_bytes().chunks_exact(4).all(|pixel| pixel == [10, 200, 30, 255]));
+    // 原图不变
+    assert_eq!((png.width(), png.height()), (100, 50));
+}
+
+#[wasm_bindgen_test]
+fn test_png_generate_preview_levels() {
+    let data: Vec<u8> = (0..64 * 64u32).flat_map(|i| [(i % 64) as u8 * 4, (i / 64) as u8 * 4, 0]).collect();
+    let mut png = PNG::new(None);
+    png.parse(&encode_fixture(64, 64, png::ColorType::Rgb, png::BitDepth::Eight, &data), None).unwrap();
+
+    let levels = png.generate_preview_levels(3).unwrap();
+    let dimension = |level: &JsValue, name: &str| js_sys::Reflect::get(level, &name.into()).unwrap().as_f64().unwrap() as u32;
+    let sizes: Vec<(u32, u32)> = levels.iter()
+        .map(|level| (dimension(&level, "width"), dimension(&level, "height")))
+        .collect();
+    assert_eq!(sizes, vec![(16, 16), (32, 32), (64, 64)]);
+    assert!(png.generate_preview_levels(0).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_png_free_pixel_data_reduces_footprint() {
+    let data: Vec<u8> = (0..8 * 8 * 3).map(|i| i as u8).collect();
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"keepRaw".into(), &true.into()).unwrap();
+    let mut png = PNG::new(Some(options.into()));
+    png.parse(&encode_fixture(8, 8, png::ColorType::Rgb, png::BitDepth::Eight, &data), None).unwrap();
+
+    let before = png.memory_footprint();
+    assert!(before >= 8 * 8 * 4);
+    png.free_pixel_data();
+    assert!(png.get_raw_data().is_none());
+    assert!(png.memory_footprint() <= before - 8 * 8 * 3);
+
+    // RGBA数据不受影响
+    let pixel: Vec<u32> = png.get_pixel(1, 0).unwrap().iter().map(|v| v.as_f64().unwrap() as u32).collect();
+    assert_eq!(pixel, vec![3, 4, 5, 255]);
+}
+
+#[wasm_bindgen_test]
+fn test_png_drops_raw_data_by_default() {
+    let data: Vec<u8> = (0..4 * 4 * 4).map(|i| i as u8).collect();
+    let encoded = encode_fixture(4, 4, png::ColorType::Rgba, png::BitDepth::Eight, &data);
+
+    let mut png = PNG::new(None);
+    png.parse(&encoded, None).unwrap();
+    assert!(png.get_raw_data().is_none());
+    assert_eq!(png.to_rgba_bytes(), data);
+    assert_eq!(png.memory_footprint(), data.len() as u64);
+
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"keepRaw".into(), &true.into()).unwrap();
+    let mut raw = PNG::new(Some(options.into()));
+    raw.parse(&encoded, None).unwrap();
+    assert_eq!(raw.get_raw_data().unwrap().to_vec(), data);
+}
+
+#[wasm_bindgen_test]
+fn test_png_analyze_optimal_format() {
+    // 两种颜色的不透明灰度图像，以RGBA存储
+    let data: Vec<u8> = (0..16).flat_map(|i| if i % 2 == 0 { [0, 0, 0, 255] } else { [255, 255, 255, 255] }).collect();
+    let mut png = PNG::new(None);
+    png.parse(&encode_fixture(4, 4, png::ColorType::Rgba, png::BitDepth::Eight, &data), None).unwrap();
+
+    let format = png.analyze_optimal_format().unwrap();
+    let get = |name: &str| js_sys::Reflect::get(&format, &name.into()).unwrap();
+    assert_eq!(get("colorType").as_f64(), Some(0.0));
+    assert_eq!(get("bitDepth").as_f64(), Some(1.0));
+    assert!(get("reason").as_string().unwrap().contains("grayscale"));
+}
+
+#[wasm_bindgen_test]
+fn test_png_offset_preserved_on_pack() {
+    use rust_png::png_chunks::{ChunkType, OFFSData, PNGChunk};
+    use rust_png::png_packer::{PackerOptions, PNGPacker};
+
+    let data: Vec<u8> = (0..4).flat_map(|i| [i * 60, 0, 0, 255]).collect();
+    let options = PackerOptions {
+        width: 2,
+        height: 2,
+        chunks: vec![PNGChunk::new(ChunkType::OFFS, OFFSData { x: -5, y: 7, unit: 1 }.to_bytes())],
+        ..Default::default()
+    };
+    let encoded = PNGPacker::new(options).pack(&data).unwrap();
+
+    let mut png = PNG::new(None);
+    png.parse(&encoded, None).unwrap();
+    let offset: Vec<f64> = png.get_offset().unwrap().iter().map(|v| v.as_f64().unwrap()).collect();
+    assert_eq!(offset, vec![-5.0, 7.0, 1.0]);
+
+    let mut repacked = PNG::new(None);
+    repacked.parse(&png.pack(None).unwrap(), None).unwrap();
+    let offset: Vec<f64> = repacked.get_offset().unwrap().iter().map(|v| v.as_f64().unwrap()).collect();
+    assert_eq!(offset, vec![-5.0, 7.0, 1.0]);
+}
+
+#[wasm_bindgen_test]
+fn test_png_set_data_validates_length() {
+    let data: Vec<u8> = (0..2 * 2 * 4).map(|i| i as u8).collect();
+    let mut png = PNG::new(None);
+    png.parse(&encode_fixture(2, 2, png::ColorType::Rgba, png::BitDepth::Eight, &data), None).unwrap();
+
+    // 长度不符时报错，原数据保持不变
+    assert!(png.set_data(&data[..12]).is_err());
+    assert!(png.set_data(&[0; 20]).is_err());
+    let pixel: Vec<u32> = png.get_pixel(1, 1).unwrap().iter().map(|v| v.as_f64().unwrap() as u32).collect();
+    assert_eq!(pixel, vec![12, 13, 14, 15]);
+
+    png.set_data(&[9; 16]).unwrap();
+    assert_eq!(png.to_rgba_bytes(), vec![9; 16]);
+}
+
+#[wasm_bindgen_test]
+fn test_png_from_rgba_packs_decodable_png() {
+    let data = [255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 255, 0, 10, 20, 30, 255];
+    let png = PNG::from_rgba(2, 2, &data).unwrap();
+    assert_eq!(png.color_type(), 6);
+
+    let encoded = png.pack(None).unwrap();
+    let mut decoded = PNG::new(None);
+    decoded.parse(&encoded, None).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (2, 2));
+    assert_eq!(decoded.to_rgba_bytes(), data.to_vec());
+
+    assert!(PNG::from_rgba(2, 2, &data[..15]).is_err());
+    assert!(PNG::from_rgba(3, 2, &data).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_png_rendering_intent_preserved() {
+    use rust_png::png_packer::PngEncoder;
+
+    let data = [200, 100, 50, 255].repeat(4);
+    let encoded = PngEncoder::new(2, 2).with_srgb_intent(1).encode(&data).unwrap();
+
+    let mut png = PNG::new(None);
+    png.parse(&encoded, None).unwrap();
+    assert_eq!(png.rendering_intent(), Some(1));
+
+    // 解码后重新编码保留渲染意图
+    let mut repacked = PNG::new(None);
+    repacked.parse(&png.pack(None).unwrap(), None).unwrap();
+    assert_eq!(repacked.rendering_intent(), Some(1));
+
+    let mut plain = PNG::new(None);
+    plain.parse(&encode_fixture(2, 2, png::ColorType::Rgba, png::BitDepth::Eight, &data), None).unwrap();
+    assert_eq!(plain.rendering_intent(), None);
+}
+
+#[wasm_bindgen_test]
+fn test_generated_png_data_decodes() {
+    use rust_png::testing::TestDataGenerator;
+
+    let data = TestDataGenerator::generate_png_data(4, 4, 6);
+    let mut png = PNG::new(None);
+    png.parse(&data, None).unwrap();
+    assert_eq!((png.width(), png.height(), png.color_type()), (4, 4, 6));
+    assert_eq!(png.to_rgba_bytes(), [0x40, 0x80, 0xc0, 0xff].repeat(16));
+
+    // 所有chunk的CRC正确
+    let index = png.get_chunk_index().unwrap();
+    assert!(index.iter().all(|info| js_sys::Reflect::get(&info, &"crcOk".into()).unwrap().as_bool() == Some(true)));
+}
+
+#[wasm_bindgen_test]
+fn test_png_get_region() {
+    let data: Vec<u8> = (0..16u8).flat_map(|i| [i, i * 2, i * 3, 255]).collect();
+    let png = PNG::from_rgba(4, 4, &data).unwrap();
+
+    let region = png.get_region(1, 1, 2, 2).unwrap().to_vec();
+    let expected: Vec<u8> = [5u8, 6, 9, 10].iter().flat_map(|&i| [i, i * 2, i * 3, 255]).collect();
+    assert_eq!(region, expected);
+    assert!(png.get_region(3, 3, 2, 2).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_png_decode_samples_native_layout() {
+    let data: Vec<u8> = (0..12u8).map(|i| i * 20).collect();
+    let encoded = encode_fixture(2, 2, png::ColorType::Rgb, png::BitDepth::Eight, &data);
+
+    let decoded = PNG::decode_samples(&encoded).unwrap();
+    let get = |key: &str| js_sys::Reflect::get(&decoded, &key.into()).unwrap();
+    let samples = js_sys::Uint8Array::new(&get("samples")).to_vec();
+    // RGB每像素3字节，不展开为RGBA
+    assert_eq!(samples.len(), 2 * 2 * 3);
+    assert_eq!(samples, data);
+    assert_eq!(get("colorType").as_f64(), Some(2.0));
+    assert_eq!(get("bitDepth").as_f64(), Some(8.0));
+    assert_eq!(get("channels").as_f64(), Some(3.0));
+}
+
+#[wasm_bindgen_test]
+fn test_png_decode_region_matches_full_decode() {
+    // 高图像：4x64 RGB
+    let data: Vec<u8> = (0..4 * 64 * 3).map(|i| (i * 7 % 251) as u8).collect();
+    let encoded = encode_fixture(4, 64, png::ColorType::Rgb, png::BitDepth::Eight, &data);
+
+    let mut full = PNG::new(None);
+    full.parse(&encoded, None).unwrap();
+
+    let region = PNG::decode_region(&encoded, 1, 2, 2, 3).unwrap().to_vec();
+    assert_eq!(region, full.get_region(1, 2, 2, 3).unwrap().to_vec());
+    assert!(PNG::decode_region(&encoded, 0, 62, 4, 3).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_adam7_pass_geometry_32x32() {
+    let passes = rust_png::adam7_pass_geometry(32, 32);
+    assert_eq!(passes.length(), 7);
+
+    // (width, height, xOffset, yOffset, xStep, yStep)
+    let expected = [
+        (4, 4, 0, 0, 8, 8),
+        (4, 4, 4, 0, 8, 8),
+        (8, 4, 0, 4, 4, 8),
+        (8, 8, 2, 0, 4, 4),
+        (16, 8, 0, 2, 2, 4),
+        (16, 16, 1, 0, 2, 2),
+        (32, 16, 0, 1, 1, 2),
+    ];
+    for (pass, &(width, height, x_offset, y_offset, x_step, y_step)) in passes.iter().zip(expected.iter()) {
+        let get = |key: &str| js_sys::Reflect::get(&pass, &key.into()).unwrap().as_f64().unwrap() as u32;
+        assert_eq!(
+            (get("width"), get("height"), get("xOffset"), get("yOffset"), get("xStep"), get("yStep")),
+            (width, height, x_offset, y_offset, x_step, y_step)
+        );
+        assert_eq!(get("byteSize"), width * height * 4);
+    }
+}
+
+#[wasm_bindgen_test]
+fn test_png_estimate_source_compression() {
+    use rust_png::png_packer::{PackerOptions, PNGPacker};
+
+    let rgb: Vec<u8> = (0..64u32 * 64)
+        .flat_map(|i| {
+            let (x, y) = (i % 64, i / 64);
+            [(x * 4) as u8, (y * 4) as u8, ((x ^ y) * 8) as u8]
+        })
+        .collect();
+    let encode = |deflate_level: u8| PNGPacker::new(PackerOptions {
+        width: 64,
+        height: 64,
+        color_type: 2,
+        input_color_type: 2,
+        deflate_level,
+        ..Default::default()
+    }).pack(&rgb).unwrap();
+
+    // store-only：IDAT比未压缩的扫描线还大
+    let stored = PNG::estimate_source_compression(&encode(0)).unwrap();
+    assert!(stored < 0.1, "stored score {}", stored);
+
+    let best = PNG::estimate_source_compression(&encode(9)).unwrap();
+    assert!(best > 0.9, "best score {}", best);
+}
+
+#[wasm_bindgen_test]
+fn test_png_get_animation_info() {
+    use js_sys::{Array, Reflect};
+
+    let mut encoded = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut encoded, 2, 2);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(3, 0).unwrap();
+        let mut writer = encoder.write_header().unwrap();
+        for delay in [40u16, 80, 120] {
+            writer.set_frame_delay(delay, 1000).unwrap();
+            writer.write_image_data(&[delay as u8; 16]).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    let mut png = PNG::new(None);
+    png.parse(&encoded, None).unwrap();
+    let info = png.get_animation_info().unwrap();
+    assert_eq!(Reflect::get(&info, &"frameCount".into()).unwrap().as_f64(), Some(3.0));
+    assert_eq!(Reflect::get(&info, &"loopCount".into()).unwrap().as_f64(), Some(0.0));
+    let delays: Vec<f64> = Array::from(&Reflect::get(&info, &"delays".into()).unwrap())
+        .iter()
+        .map(|v| v.as_f64().unwrap())
+        .collect();
+    assert_eq!(delays, vec![40.0, 80.0, 120.0]);
+
+    let mut still = PNG::new(None);
+    still.parse(&encode_fixture(2, 2, png::ColorType::Rgba, png::BitDepth::Eight, &[0; 16]), None).unwrap();
+    assert!(still.get_animation_info().is_none());
+}
+
+#[wasm_bindgen_test]
+fn test_png_to_static() {
+    let default_image: Vec<u8> = (0..16).map(|i| i as u8 * 15).collect();
+    let mut encoded = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut encoded, 2, 2);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(2, 0).unwrap();
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&default_image).unwrap();
+        writer.write_image_data(&[255; 16]).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut png = PNG::new(None);
+    png.parse(&encoded, None).unwrap();
+    let still = png.to_static().unwrap();
+
+    let mut reparsed = PNG::new(None);
+    reparsed.parse(&still, None).unwrap();
+    assert!(reparsed.get_animation_info().is_none());
+    assert_eq!(reparsed.get_rgba8_array().unwrap().to_vec(), default_image);
+}
+
+#[wasm_bindgen_test]
+fn test_png_constructor_fill_color() {
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"width".into(), &2.into()).unwrap();
+    js_sys::Reflect::set(&options, &"height".into(), &2.into()).unwrap();
+    let color = js_sys::Array::of4(&255.into(), &0.into(), &0.into(), &255.into());
+    js_sys::Reflect::set(&options, &"fillColor".into(), &color).unwrap();
+    let png = PNG::new(Some(options.clone().into()));
+    assert_eq!(png.get_rgba8_array().unwrap().to_vec(), [255, 0, 0, 255].repeat(4));
+
+    // 只有fill时填充不透明黑色
+    js_sys::Reflect::delete_property(&options, &"fillColor".into()).unwrap();
+    js_sys::Reflect::set(&options, &"fill".into(), &true.into()).unwrap();
+    let png = PNG::new(Some(options.into()));
+    assert_eq!(png.get_rgba8_array().unwrap().to_vec(), [0, 0, 0, 255].repeat(4));
+}
+
+#[wasm_bindgen_test]
+fn test_png_drop_redundant_alpha() {
+    let opaque = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 10, 20, 30, 255];
+    let mut png = PNG::from_rgba(2, 2, &opaque).unwrap();
+    assert!(png.drop_redundant_alpha());
+    assert_eq!(png.color_type(), 2);
+
+    // 重新编码为RGB，像素不变
+    let mut decoded = PNG::new(None);
+    decoded.parse(&png.pack(None).unwrap(), None).unwrap();
+    assert_eq!(decoded.color_type(), 2);
+    assert_eq!(decoded.to_rgba_bytes(), opaque.to_vec());
+
+    // 含半透明像素时不转换
+    let mut translucent = opaque;
+    translucent[7] = 128;
+    let mut png = PNG::from_rgba(2, 2, &translucent).unwrap();
+    assert!(!png.drop_redundant_alpha());
+    assert_eq!(png.color_type(), 6);
+
+    // 带tRNS关键色的RGB图像重新打包后仍保留tRNS，关键色像素保持透明且不产生警告
+    let mut encoded = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut encoded, 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_trns(vec![0, 10, 0, 20, 0, 30]);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[10, 20, 30, 40, 50, 60]).unwrap();
+    }
+    let mut keyed = PNG::new(None);
+    keyed.parse(&encoded, None).unwrap();
+    let mut repacked = PNG::new(None);
+    repacked.parse(&keyed.pack(None).unwrap(), None).unwrap();
+    assert_eq!(repacked.color_type(), 2);
+    assert_eq!(repacked.to_rgba_bytes(), vec![10, 20, 30, 0, 40, 50, 60, 255]);
+    assert_eq!(keyed.get_warnings().length(), 0);
+}
+
+#[wasm_bindgen_test]
+fn test_png_lossy_operation_warnings() {
+    let data: Vec<u8> = (0..16u8).flat_map(|i| [i * 16, 255 - i * 16, 128, 255]).collect();
+    let mut png = PNG::from_rgba(4, 4, &data).unwrap();
+    assert_eq!(png.get_warnings().length(), 0);
+
+    // 无损的读取不产生警告，有损操作按顺序记录
+    png.get_region(0, 0, 2, 2).unwrap();
+    png.posterize(4).unwrap();
+    png.resize(2, 2, false).unwrap();
+    let warnings: Vec<String> = png.get_warnings().iter().map(|warning| warning.as_string().unwrap()).collect();
+    assert_eq!(warnings, vec!["Posterized RGB channels to 4 levels", "Resampled image from 4x4 to 2x2"]);
+
+    // 丢弃alpha的RGB转换：不透明图像不记录
+    png.to_rgb_bytes(None);
+    assert_eq!(png.get_warnings().length(), 2);
+    let translucent = PNG::from_rgba(1, 1, &[10, 20, 30, 128]).unwrap();
+    translucent.to_rgb_bytes(None);
+    translucent.to_rgb_bytes(Some(vec![255, 255, 255]));
+    let warnings: Vec<String> = translucent.get_warnings().iter().map(|warning| warning.as_string().unwrap()).collect();
+    assert_eq!(warnings, vec![
+        "Dropped alpha channel during RGB conversion",
+        "Flattened alpha channel onto background during RGB conversion",
+    ]);
+
+    // 16位灰度在紧凑模式下缩放到8位
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"compactGrayscale".into(), &true.into()).unwrap();
+    let mut gray = PNG::new(Some(options.into()));
+    gray.parse(&encode_fixture(1, 1, png::ColorType::Grayscale, png::BitDepth::Sixteen, &[0x12, 0x34]), None).unwrap();
+    let warnings: Vec<String> = gray.get_warnings().iter().map(|warning| warning.as_string().unwrap()).collect();
+    assert_eq!(warnings, vec!["Reduced 16-bit grayscale samples to 8 bits"]);
+
+    // 失败的色调映射不记录警告
+    assert!(png.tone_map_16_to_8(rust_png::ToneMapMode::Linear, None).is_err());
+    assert_eq!(png.get_warnings().length(), 2);
+}
+
+#[wasm_bindgen_test]
+fn test_png_get_planar() {
+    let data = [255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 255, 0, 10, 20, 30, 40];
+    let mut png = PNG::new(None);
+    png.parse(&encode_fixture(2, 2, png::ColorType::Rgba, png::BitDepth::Eight, &data), None).unwrap();
+
+    let planar = png.get_planar().unwrap();
+    let plane = |name: &str| js_sys::Uint8Array::new(&js_sys::Reflect::get(&planar, &name.into()).unwrap()).to_vec();
+    assert_eq!(plane("r"), vec![255, 0, 0, 10]);
+    assert_eq!(plane("g"), vec![0, 255, 0, 20]);
+    assert_eq!(plane("b"), vec![0, 0, 255, 30]);
+    assert_eq!(plane("a"), vec![255, 128, 0, 40]);
+}
+
+#[wasm_bindgen_test]
+fn test_png_over_composites_layers() {
+    // 半透明红色叠加在不带alpha通道的纯蓝背景上
+    let red = encode_fixture(2, 1, png::ColorType::Rgba, png::BitDepth::Eight, &[255, 0, 0, 128, 255, 0, 0, 0]);
+    let blue = encode_fixture(2, 1, png::ColorType::Rgb, png::BitDepth::Eight, &[0, 0, 255, 0, 0, 255]);
+    let mut layer = PNG::new(None);
+    layer.parse(&red, None).unwrap();
+    let mut background = PNG::new(None);
+    background.parse(&blue, None).unwrap();
+
+    let composite = layer.over(&background).unwrap();
+    assert_eq!(composite.to_rgba_bytes(), vec![128, 0, 127, 255, 0, 0, 255, 255]);
+    assert!(!composite.alpha());
+    // 原图不变
+    assert_eq!(background.to_rgba_bytes(), vec![0, 0, 255, 255, 0, 0, 255, 255]);
+
+    // 尺寸不同时报错
+    let small = encode_fixture(1, 1, png::ColorType::Rgb, png::BitDepth::Eight, &[0, 0, 0]);
+    let mut other = PNG::new(None);
+    other.parse(&small, None).unwrap();
+    assert!(layer.over(&other).is_err());
+}
@@ -203,3 +203,21 @@ fn test_large_image_interlace() {
     let total_pixels: u32 = stats.pass_sizes.iter().sum();
     assert_eq!(total_pixels, width * height);
 }
+
+#[test]
+fn test_interlace_pass_data_sizes() {
+    // 16x16 8位RGB：每行 = 滤镜字节 + 通道宽度 * 3
+    let passes = get_interlace_passes_with_bpp(16, 16, 24);
+    let sizes: Vec<usize> = passes.iter().map(|pass| pass.data_size).collect();
+    assert_eq!(sizes, vec![14, 14, 26, 52, 100, 200, 392]);
+
+    for pass in &passes {
+        assert_eq!(pass.data_size, pass.height as usize * (1 + pass.width as usize * 3));
+    }
+    assert_eq!(sizes.iter().sum::<usize>(), 798);
+
+    // 不足1字节的像素按字节向上取整：8x8 1位灰度
+    let passes = get_interlace_passes_with_bpp(8, 8, 1);
+    let sizes: Vec<usize> = passes.iter().map(|pass| pass.data_size).collect();
+    assert_eq!(sizes, vec![2, 2, 2, 4, 4, 8, 8]);
+}
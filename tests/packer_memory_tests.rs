@@ -0,0 +1,64 @@
+//! PNG打包器流式输出的内存测试
+//! 使用计数分配器统计峰值内存，单独放在一个测试文件中避免其他测试干扰
+
+use rust_png::constants::*;
+use rust_png::png_packer::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// 记录当前和峰值分配字节数的分配器
+struct CountingAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(current, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_pack_to_memory_is_bounded() {
+    // 2000x1500 RGBA伪随机图像（12MB，几乎不可压缩）
+    let (width, height) = (2000u32, 1500u32);
+    let scanline = width as usize * 4;
+    let mut seed: u32 = 1;
+    let data: Vec<u8> = (0..scanline * height as usize)
+        .map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as u8
+        })
+        .collect();
+
+    let options = PackerOptions {
+        width,
+        height,
+        color_type: COLORTYPE_COLOR_ALPHA,
+        ..Default::default()
+    };
+    let packer = PNGPacker::new(options);
+
+    let baseline = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(baseline, Ordering::SeqCst);
+    packer.pack_to(&data, &mut std::io::sink()).unwrap();
+    let peak = PEAK.load(Ordering::SeqCst) - baseline;
+
+    // 峰值只包含压缩器状态、一个IDAT缓冲和少量扫描线，而不是整幅压缩图像
+    let bound = 1024 * 1024 + 32 * scanline;
+    assert!(peak < bound, "peak {} bytes exceeds bound {} bytes", peak, bound);
+    assert!(peak < data.len() / 8);
+}
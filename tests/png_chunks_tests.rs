@@ -0,0 +1,698 @@
+//! PNG Chunk解析测试用例
+
+use rust_png::constants::*;
+use rust_png::error_handling::PNGError;
+use rust_png::png_chunks::*;
+
+/// 构造最小的PNG文件（1x1 RGBA）
+fn minimal_png() -> Vec<u8> {
+    let ihdr = IHDRData {
+        width: 1,
+        height: 1,
+        bit_depth: 8,
+        color_type: COLORTYPE_COLOR_ALPHA,
+        compression_method: 0,
+        filter_method: 0,
+        interlace_method: 0,
+    };
+    // zlib压缩的 [0, 0, 0, 0, 0]（滤镜字节 + 1个RGBA像素）
+    let idat = vec![0x78, 0x9c, 0x63, 0x60, 0x00, 0x02, 0x00, 0x00, 0x05, 0x00, 0x01];
+
+    let mut data = PNG_SIGNATURE.to_vec();
+    data.extend_from_slice(&PNGChunk::new(ChunkType::IHDR, ihdr.to_bytes()).to_bytes());
+    data.extend_from_slice(&PNGChunk::new(ChunkType::IDAT, idat).to_bytes());
+    data.extend_from_slice(&PNGChunk::new(ChunkType::IEND, Vec::new()).to_bytes());
+    data
+}
+
+#[test]
+fn test_chunk_index_minimal_file() {
+    let data = minimal_png();
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&data).unwrap();
+
+    let index = parser.chunk_index();
+    let types: Vec<&str> = index.iter().map(|info| info.type_code.as_str()).collect();
+    assert_eq!(types, vec!["IHDR", "IDAT", "IEND"]);
+
+    // 签名之后依次排列：长度(4) + 类型(4) + 数据 + CRC(4)
+    assert_eq!(index[0].offset, 8);
+    assert_eq!(index[0].length, 13);
+    assert_eq!(index[1].offset, 8 + 12 + 13);
+    assert_eq!(index[1].length, 11);
+    assert_eq!(index[2].offset, 8 + 12 + 13 + 12 + 11);
+    assert_eq!(index[2].length, 0);
+
+    assert!(index.iter().all(|info| info.crc_ok && info.critical));
+}
+
+#[test]
+fn test_minimal_1x1_image_decodes() {
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&minimal_png()).unwrap();
+    assert_eq!(parser.decode_pixels().unwrap(), vec![0, 0, 0, 0]);
+}
+
+#[test]
+fn test_zero_dimension_and_empty_idat_rejected() {
+    // 宽为0的IHDR
+    let data = build_png(0, 1, 8, COLORTYPE_GRAYSCALE, 0, &[0]);
+    assert!(matches!(PNGChunkParser::new().parse(&data), Err(PNGError::InvalidFormat(_))));
+
+    // 空IDAT能解析chunk结构，解码像素时报错
+    let original = minimal_png();
+    let mut data = original[..8 + 25].to_vec();
+    data.extend_from_slice(&PNGChunk::new(ChunkType::IDAT, Vec::new()).to_bytes());
+    data.extend_from_slice(&PNGChunk::new(ChunkType::IEND, Vec::new()).to_bytes());
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&data).unwrap();
+    assert!(matches!(parser.decode_pixels(), Err(PNGError::InvalidFormat(_))));
+}
+
+/// 在IHDR之后插入一个CRC错误的tEXt chunk
+fn png_with_bad_text_crc() -> Vec<u8> {
+    let data = minimal_png();
+    let mut text = PNGChunk::new(ChunkType::TEXT, b"Comment\0hello".to_vec());
+    text.crc ^= 0xdead_beef;
+
+    let ihdr_end = 8 + 12 + 13;
+    let mut corrupted = data[..ihdr_end].to_vec();
+    corrupted.extend_from_slice(&text.to_bytes());
+    corrupted.extend_from_slice(&data[ihdr_end..]);
+    corrupted
+}
+
+#[test]
+fn test_strict_mode_rejects_bad_crc() {
+    let data = png_with_bad_text_crc();
+    let mut parser = PNGChunkParser::new();
+    assert!(parser.parse(&data).is_err());
+}
+
+#[test]
+fn test_lenient_mode_records_crc_warning() {
+    let data = png_with_bad_text_crc();
+    let mut parser = PNGChunkParser::new().with_lenient(true);
+    parser.parse(&data).unwrap();
+
+    assert_eq!(parser.crc_warnings, vec![ChunkType::TEXT]);
+    // 像素数据仍然完整保留
+    assert!(parser.ihdr.is_some());
+    assert!(parser.has_chunk(&ChunkType::IDAT));
+    assert!(parser.has_chunk(&ChunkType::IEND));
+
+    let index = parser.chunk_index();
+    assert_eq!(index[1].type_code, "tEXt");
+    assert!(!index[1].crc_ok);
+    assert!(!index[1].critical);
+}
+
+#[test]
+fn test_crc_repairs_fixed_on_serialize() {
+    let data = png_with_bad_text_crc();
+    let mut parser = PNGChunkParser::new().with_lenient(true);
+    parser.parse(&data).unwrap();
+    assert_eq!(parser.crc_repairs, vec!["tEXt".to_string()]);
+
+    // 重新序列化后CRC正确，严格模式可以解析且没有需要修复的chunk
+    let repaired = parser.serialize();
+    let mut strict = PNGChunkParser::new();
+    strict.parse(&repaired).unwrap();
+    assert!(strict.crc_repairs.is_empty());
+    assert_eq!(strict.text_chunks[0].text, "hello");
+
+    // 正常文件没有修复记录
+    let mut parser = PNGChunkParser::new().with_lenient(true);
+    parser.parse(&minimal_png()).unwrap();
+    assert!(parser.crc_repairs.is_empty());
+}
+
+#[test]
+fn test_lenient_mode_critical_crc_is_fatal() {
+    let mut data = minimal_png();
+    // 破坏IHDR的CRC
+    let crc_pos = 8 + 8 + 13;
+    data[crc_pos] ^= 0xff;
+
+    let mut parser = PNGChunkParser::new().with_lenient(true);
+    assert!(parser.parse(&data).is_err());
+}
+
+/// 用给定的IHDR参数和原始扫描线数据（含滤镜字节）构造PNG
+fn build_png(width: u32, height: u32, bit_depth: u8, color_type: u8, interlace_method: u8, raw: &[u8]) -> Vec<u8> {
+    use flate2::write::ZlibEncoder;
+    use std::io::Write;
+
+    let ihdr = IHDRData {
+        width,
+        height,
+        bit_depth,
+        color_type,
+        compression_method: 0,
+        filter_method: 0,
+        interlace_method,
+    };
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(raw).unwrap();
+    let idat = encoder.finish().unwrap();
+
+    let mut data = PNG_SIGNATURE.to_vec();
+    data.extend_from_slice(&PNGChunk::new(ChunkType::IHDR, ihdr.to_bytes()).to_bytes());
+    data.extend_from_slice(&PNGChunk::new(ChunkType::IDAT, idat).to_bytes());
+    data.extend_from_slice(&PNGChunk::new(ChunkType::IEND, Vec::new()).to_bytes());
+    data
+}
+
+#[test]
+fn test_decode_pixels_matches_png_crate() {
+    // 使用png crate的自适应滤镜编码，再用自带解码路径解码
+    let (width, height) = (7u32, 6u32);
+    let pixels: Vec<u8> = (0..width * height * 3).map(|i| (i * 37 % 256) as u8).collect();
+
+    let mut encoded = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut encoded, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&pixels).unwrap();
+    }
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    assert_eq!(parser.decode_pixels().unwrap(), pixels);
+}
+
+#[test]
+fn test_decode_pixels_interlaced() {
+    // 3x3 8位灰度Adam7交错图像，像素值为 y*3+x
+    let raw = [
+        0, 0,       // pass 1: (0,0)
+        0, 2,       // pass 4: (2,0)
+        0, 6, 8,    // pass 5: (0,2) (2,2)
+        0, 1,       // pass 6: (1,0)
+        0, 7,       //         (1,2)
+        0, 3, 4, 5, // pass 7: 第1行
+    ];
+    let data = build_png(3, 3, 8, COLORTYPE_GRAYSCALE, 1, &raw);
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&data).unwrap();
+    assert_eq!(parser.decode_pixels().unwrap(), (0..9).collect::<Vec<u8>>());
+}
+
+#[test]
+fn test_decode_pixels_truncated_idat() {
+    // 只有1行数据的2行图像
+    let data = build_png(2, 2, 8, COLORTYPE_GRAYSCALE, 0, &[0, 1, 2]);
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&data).unwrap();
+    assert!(parser.decode_pixels().is_err());
+
+    // IHDR声明的尺寸极大而数据只有1行时，报错前不按声明的尺寸分配内存
+    for interlace_method in [0, 1] {
+        let data = build_png(1, 0x7fff_ffff, 16, COLORTYPE_COLOR_ALPHA, interlace_method, &[0; 9]);
+        let mut parser = PNGChunkParser::new();
+        parser.parse(&data).unwrap();
+        assert!(parser.decode_pixels().is_err());
+    }
+}
+
+#[test]
+fn test_decode_top_rows() {
+    let (width, height) = (5u32, 40u32);
+    let pixels: Vec<u8> = (0..width * height * 3).map(|i| (i * 13 % 256) as u8).collect();
+    let mut encoded = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut encoded, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&pixels).unwrap();
+    }
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    assert_eq!(parser.decode_top_rows(3).unwrap(), pixels[..3 * 15]);
+
+    // 只需要已有的行时，被截断的IDAT同样可以解码
+    let data = build_png(2, 2, 8, COLORTYPE_GRAYSCALE, 0, &[0, 1, 2]);
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&data).unwrap();
+    assert_eq!(parser.decode_top_rows(1).unwrap(), vec![1, 2]);
+    assert!(parser.decode_top_rows(2).is_err());
+}
+
+#[test]
+fn test_decode_pixels_partial_truncated_idat() {
+    // 10行16像素灰度图像，IDAT数据只保留一半
+    let (width, height) = (16usize, 10usize);
+    let mut seed: u32 = 12345;
+    let mut pixels = Vec::new();
+    let mut raw = Vec::new();
+    for _ in 0..height {
+        raw.push(FILTER_NONE);
+        for _ in 0..width {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let value = (seed >> 16) as u8;
+            pixels.push(value);
+            raw.push(value);
+        }
+    }
+    let mut data = build_png(width as u32, height as u32, 8, COLORTYPE_GRAYSCALE, 0, &raw);
+
+    // 签名(8) + IHDR(25) + IDAT长度和类型(8)
+    let idat_start = 8 + 25 + 8;
+    let idat_length = u32::from_be_bytes([data[33], data[34], data[35], data[36]]) as usize;
+    data.truncate(idat_start + idat_length / 2);
+
+    // 严格模式下截断的chunk是错误
+    assert!(PNGChunkParser::new().parse(&data).is_err());
+
+    let mut parser = PNGChunkParser::new().with_lenient(true);
+    parser.parse(&data).unwrap();
+    assert!(parser.decode_pixels().is_err());
+    // 截断单独记录，不混入CRC警告
+    assert_eq!(parser.truncated_chunk, Some(ChunkType::IDAT));
+    assert!(parser.crc_warnings.is_empty());
+
+    let partial = parser.decode_pixels_partial().unwrap();
+    let rows = partial.decoded_rows as usize;
+    assert!((3..=6).contains(&rows), "decoded_rows={}", rows);
+    assert_eq!(partial.pixels.len(), width * height);
+    assert_eq!(&partial.pixels[..rows * width], &pixels[..rows * width]);
+    assert!(partial.pixels[rows * width..].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn test_exif_roundtrip() {
+    // 小端TIFF头 + 一个Orientation条目
+    let exif = vec![
+        0x49, 0x49, 0x2a, 0x00, 0x08, 0x00, 0x00, 0x00,
+        0x01, 0x00, 0x12, 0x01, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+    ];
+
+    // 在IHDR之后插入eXIf
+    let original = minimal_png();
+    let mut data = original[..8 + 25].to_vec();
+    data.extend_from_slice(&PNGChunk::new(ChunkType::EXIF, exif.clone()).to_bytes());
+    data.extend_from_slice(&original[8 + 25..]);
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&data).unwrap();
+    assert_eq!(parser.exif.as_ref().unwrap().raw, exif);
+    assert_eq!(ChunkType::from_u32(TYPE_eXIf), ChunkType::EXIF);
+
+    let mut reparsed = PNGChunkParser::new();
+    reparsed.parse(&parser.serialize()).unwrap();
+    assert_eq!(reparsed.exif.unwrap().raw, exif);
+}
+
+#[test]
+fn test_exif_orientation_tag() {
+    // 大端TIFF头 + Orientation = 3
+    let mut exif = EXIFData {
+        raw: vec![
+            0x4d, 0x4d, 0x00, 0x2a, 0x00, 0x00, 0x00, 0x08,
+            0x00, 0x01, 0x01, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x03, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+        ],
+    };
+    assert_eq!(exif.orientation(), Some(3));
+
+    assert!(exif.set_orientation(1));
+    assert_eq!(exif.orientation(), Some(1));
+
+    // 非TIFF数据没有方向标签
+    let mut invalid = EXIFData { raw: vec![1, 2, 3] };
+    assert_eq!(invalid.orientation(), None);
+    assert!(!invalid.set_orientation(1));
+}
+
+#[test]
+fn test_filter_bytes() {
+    // 3行2像素灰度图像，每行使用Up滤镜
+    let raw = [2, 1, 2, 2, 0, 0, 2, 0, 0];
+    let data = build_png(2, 3, 8, COLORTYPE_GRAYSCALE, 0, &raw);
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&data).unwrap();
+    assert_eq!(parser.filter_bytes().unwrap(), vec![FILTER_UP; 3]);
+}
+
+#[test]
+fn test_filter_bytes_interlaced() {
+    // 3x3交错图像：pass 1/4/5/6/7分别有1/1/1/2/1行
+    let raw = [
+        0, 0,
+        1, 2,
+        2, 6, 8,
+        3, 1,
+        4, 7,
+        0, 3, 4, 5,
+    ];
+    let data = build_png(3, 3, 8, COLORTYPE_GRAYSCALE, 1, &raw);
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&data).unwrap();
+    assert_eq!(parser.filter_bytes().unwrap(), vec![0, 1, 2, 3, 4, 0]);
+}
+
+#[test]
+fn test_raw_scanlines() {
+    // 3行2像素RGB图像，每行 1 + 2*3 字节
+    let raw = [
+        0, 1, 2, 3, 4, 5, 6,
+        1, 1, 1, 1, 1, 1, 1,
+        4, 9, 8, 7, 6, 5, 4,
+    ];
+    let data = build_png(2, 3, 8, COLORTYPE_COLOR, 0, &raw);
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&data).unwrap();
+    let scanlines = parser.raw_scanlines().unwrap();
+
+    assert_eq!(scanlines.len(), 3);
+    assert!(scanlines.iter().all(|line| line.len() == 2 * 3 + 1));
+    // 原样返回，不做反滤镜
+    assert_eq!(scanlines.concat(), raw.to_vec());
+}
+
+/// 在IHDR之后插入给定的辅助chunks
+fn minimal_png_with(chunks: &[PNGChunk]) -> Vec<u8> {
+    let original = minimal_png();
+    let mut data = original[..8 + 25].to_vec();
+    for chunk in chunks {
+        data.extend_from_slice(&chunk.to_bytes());
+    }
+    data.extend_from_slice(&original[8 + 25..]);
+    data
+}
+
+#[test]
+fn test_effective_gamma_prefers_srgb() {
+    // 误导性的gAMA (1.0) 与sRGB同时存在
+    let gama = PNGChunk::new(ChunkType::GAMA, GAMAData { gamma: 100000 }.to_bytes());
+    let srgb = PNGChunk::new(ChunkType::SRGB, SRGBData { rendering_intent: 0 }.to_bytes());
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&minimal_png_with(&[gama.clone(), srgb])).unwrap();
+    assert_eq!(parser.gamma.as_ref().unwrap().gamma, 100000);
+    assert_eq!(parser.effective_gamma(), Some(0.45455));
+
+    // 只有gAMA时使用gAMA，重复的gAMA以第一个为准
+    let second = PNGChunk::new(ChunkType::GAMA, GAMAData { gamma: 45455 }.to_bytes());
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&minimal_png_with(&[gama, second])).unwrap();
+    assert_eq!(parser.effective_gamma(), Some(1.0));
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&minimal_png()).unwrap();
+    assert_eq!(parser.effective_gamma(), None);
+}
+
+#[test]
+fn test_color_bitdepth_combinations_exhaustive() {
+    // PNG规范表：颜色类型 -> 允许的位深度
+    let legal: [(u8, &[u8]); 5] = [
+        (COLORTYPE_GRAYSCALE, &[1, 2, 4, 8, 16]),
+        (COLORTYPE_COLOR, &[8, 16]),
+        (COLORTYPE_PALETTE_COLOR, &[1, 2, 4, 8]),
+        (COLORTYPE_GRAYSCALE_ALPHA, &[8, 16]),
+        (COLORTYPE_COLOR_ALPHA, &[8, 16]),
+    ];
+
+    let mut valid_count = 0;
+    for color_type in 0..=255u8 {
+        for bit_depth in 0..=255u8 {
+            let expected = legal.iter()
+                .any(|&(ct, depths)| ct == color_type && depths.contains(&bit_depth));
+            assert_eq!(is_valid_color_bitdepth(color_type, bit_depth), expected,
+                "color_type={} bit_depth={}", color_type, bit_depth);
+
+            // IHDR解析使用同一规则
+            let ihdr = IHDRData {
+                width: 1,
+                height: 1,
+                bit_depth,
+                color_type,
+                compression_method: 0,
+                filter_method: 0,
+                interlace_method: 0,
+            };
+            assert_eq!(IHDRData::from_bytes(&ihdr.to_bytes()).is_ok(), expected);
+
+            if expected {
+                valid_count += 1;
+            }
+        }
+    }
+    assert_eq!(valid_count, 15);
+}
+
+#[test]
+fn test_parser_limits_chunk_length() {
+    // 声明长度远大于实际数据的chunk：在分配前即被拒绝
+    let mut data = minimal_png()[..8 + 25].to_vec();
+    data.extend_from_slice(&0x7000_0000u32.to_be_bytes());
+    data.extend_from_slice(b"tEXt");
+
+    let limits = ParserLimits { max_chunk_length: 1 << 20, ..Default::default() };
+    let mut parser = PNGChunkParser::new().with_limits(limits);
+    assert!(matches!(parser.parse(&data), Err(PNGError::LimitsExceeded(_))));
+
+    // 限制内的正常文件不受影响
+    let mut parser = PNGChunkParser::new().with_limits(limits);
+    parser.parse(&minimal_png()).unwrap();
+}
+
+#[test]
+fn test_chunk_length_max_u32_rejected() {
+    let mut data = minimal_png()[..8 + 25].to_vec();
+    data.extend_from_slice(&0xFFFF_FFFFu32.to_be_bytes());
+    data.extend_from_slice(b"tEXt");
+    data.extend_from_slice(&[0; 8]);
+
+    // 默认限制先拒绝超过2^31-1的长度
+    assert!(matches!(PNGChunkParser::new().parse(&data), Err(PNGError::LimitsExceeded(_))));
+
+    // 取消长度限制后由边界检查拒绝（32位平台上为溢出），严格和宽松模式都不会panic
+    let limits = ParserLimits { max_chunk_length: u32::MAX, ..Default::default() };
+    assert!(PNGChunkParser::new().with_limits(limits).parse(&data).is_err());
+    let mut lenient = PNGChunkParser::new().with_limits(limits).with_lenient(true);
+    let result = lenient.parse(&data);
+
+    // 64位平台上宽松模式保留截断的chunk，32位平台上偏移量溢出直接报错
+    #[cfg(target_pointer_width = "64")]
+    {
+        result.unwrap();
+        assert_eq!(lenient.get_chunk_data("tEXt"), Some(&[0u8; 8][..]));
+    }
+    #[cfg(not(target_pointer_width = "64"))]
+    assert!(matches!(result, Err(PNGError::InvalidFormat(_))));
+}
+
+#[test]
+fn test_parser_limits_chunk_count() {
+    let text = PNGChunk::new(ChunkType::TEXT, b"k\0v".to_vec());
+    let data = minimal_png_with(&vec![text; 100]);
+    let limits = ParserLimits { max_chunk_count: 50, ..Default::default() };
+
+    let mut parser = PNGChunkParser::new().with_limits(limits);
+    assert!(matches!(parser.parse(&data), Err(PNGError::LimitsExceeded(_))));
+
+    // IHDR + 100个tEXt + IDAT + IEND
+    let limits = ParserLimits { max_chunk_count: 103, ..Default::default() };
+    PNGChunkParser::new().with_limits(limits).parse(&data).unwrap();
+}
+
+#[test]
+fn test_phys_square_pixel_size() {
+    // 只有宽高比（unit=0），像素宽是高的2倍：横向拉伸
+    let wide = PHYSData { ppu_x: 1, ppu_y: 2, unit: 0 };
+    assert_eq!(wide.pixel_aspect_ratio(), Some(2.0));
+    assert_eq!(wide.square_pixel_size(10, 7), Some((20, 7)));
+
+    // 像素高于宽：纵向拉伸，四舍五入
+    let tall = PHYSData { ppu_x: 3000, ppu_y: 2000, unit: 1 };
+    assert_eq!(tall.square_pixel_size(10, 7), Some((10, 11)));
+
+    assert_eq!(PHYSData { ppu_x: 0, ppu_y: 2, unit: 0 }.square_pixel_size(10, 7), None);
+
+    let chunk = PNGChunk::new(ChunkType::PHYS, wide.to_bytes());
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&minimal_png_with(&[chunk])).unwrap();
+    assert_eq!(parser.physical, Some(wide));
+}
+
+#[test]
+fn test_splt_8bit_two_entries() {
+    let mut data = b"web\0".to_vec();
+    data.push(8);
+    data.extend_from_slice(&[255, 0, 0, 255, 0x01, 0x00]);
+    data.extend_from_slice(&[0, 128, 255, 64, 0x00, 0x20]);
+
+    let splt = SPLTData::from_bytes(&data).unwrap();
+    assert_eq!(splt.name, "web");
+    assert_eq!(splt.sample_depth, 8);
+    assert_eq!(splt.entries, vec![(255, 0, 0, 255, 256), (0, 128, 255, 64, 32)]);
+    assert_eq!(splt.to_bytes(), data);
+
+    // 条目长度与深度不符、深度非法
+    assert!(SPLTData::from_bytes(&data[..data.len() - 1]).is_err());
+    let mut bad_depth = data.clone();
+    bad_depth[4] = 4;
+    assert!(SPLTData::from_bytes(&bad_depth).is_err());
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&minimal_png_with(&[PNGChunk::new(ChunkType::SPLT, data)])).unwrap();
+    assert_eq!(parser.suggested_palettes, vec![splt]);
+}
+
+#[test]
+fn test_text_latin1_decoding() {
+    // 0xE9在Latin-1中是é，不是合法的UTF-8
+    let data = b"Caf\xe9\0R\xe9sum\xe9".to_vec();
+    let text = TEXTData::from_bytes(&data).unwrap();
+    assert_eq!(text.keyword, "Café");
+    assert_eq!(text.text, "Résumé");
+    assert_eq!(text.to_bytes(), data);
+
+    // 解析器不再因非UTF-8字节失败
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&minimal_png_with(&[PNGChunk::new(ChunkType::TEXT, data)])).unwrap();
+    assert_eq!(parser.text_chunks[0].text, "Résumé");
+
+    // Latin-1无法表示的字符写为'?'
+    let text = TEXTData { keyword: "Title".to_string(), text: "日本".to_string() };
+    assert_eq!(text.to_bytes(), b"Title\0??".to_vec());
+}
+
+#[test]
+fn test_offs_negative_offset_round_trip() {
+    // 偏移为补码表示的有符号数
+    let offset = OFFSData { x: -120, y: 3000, unit: 0 };
+    let bytes = offset.to_bytes();
+    assert_eq!(bytes, vec![0xff, 0xff, 0xff, 0x88, 0x00, 0x00, 0x0b, 0xb8, 0x00]);
+    assert_eq!(OFFSData::from_bytes(&bytes).unwrap(), offset);
+    assert!(OFFSData::from_bytes(&bytes[..8]).is_err());
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&minimal_png_with(&[PNGChunk::new(ChunkType::OFFS, bytes)])).unwrap();
+    assert_eq!(parser.offset, Some(offset.clone()));
+
+    // 重新序列化后保留
+    let mut reparsed = PNGChunkParser::new();
+    reparsed.parse(&parser.serialize()).unwrap();
+    assert_eq!(reparsed.offset, Some(offset));
+}
+
+#[test]
+fn test_apng_animation_metadata() {
+    // png crate生成的3帧APNG，循环2次
+    let mut encoded = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut encoded, 2, 2);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(3, 2).unwrap();
+        let mut writer = encoder.write_header().unwrap();
+        for (i, (num, den)) in [(1u16, 10u16), (50, 1000), (3, 0)].into_iter().enumerate() {
+            writer.set_frame_delay(num, den).unwrap();
+            writer.write_image_data(&[i as u8 * 80; 16]).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    assert_eq!(parser.animation_control, Some(ACTLData { num_frames: 3, num_plays: 2 }));
+    assert_eq!(parser.frame_controls.len(), 3);
+    // delay_den为0时按100计算
+    let delays: Vec<f64> = parser.frame_controls.iter().map(FCTLData::delay_ms).collect();
+    assert_eq!(delays, vec![100.0, 50.0, 30.0]);
+    assert_eq!(FCTLData::from_bytes(&parser.frame_controls[1].to_bytes()).unwrap(), parser.frame_controls[1]);
+
+    // 普通PNG没有动画元数据
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&minimal_png()).unwrap();
+    assert!(parser.animation_control.is_none() && parser.frame_controls.is_empty());
+}
+
+#[test]
+fn test_strip_animation_keeps_default_image() {
+    let default_image = [10u8, 20, 30, 255].repeat(4);
+    let frames = [[200u8; 16], [100u8; 16]];
+
+    // separate_default为true时默认图像不属于动画（IDAT之前没有fcTL）
+    for separate_default in [false, true] {
+        let mut encoded = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut encoded, 2, 2);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let num_frames = if separate_default { 2 } else { 3 };
+            encoder.set_animated(num_frames, 0).unwrap();
+            encoder.set_sep_def_img(separate_default).unwrap();
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&default_image).unwrap();
+            for frame in &frames {
+                writer.write_image_data(frame).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+
+        let mut parser = PNGChunkParser::new();
+        parser.parse(&encoded).unwrap();
+        assert!(parser.get_chunks(&ChunkType::FDAT).is_some());
+        parser.strip_animation();
+        let still = parser.serialize();
+
+        let mut reader = png::Decoder::new(&still[..]).read_info().unwrap();
+        assert!(reader.info().animation_control.is_none(), "separate_default={}", separate_default);
+        assert!(reader.info().frame_control.is_none());
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(pixels, default_image);
+
+        let mut reparsed = PNGChunkParser::new();
+        reparsed.parse(&still).unwrap();
+        assert!(reparsed.get_chunks(&ChunkType::FCTL).is_none() && reparsed.get_chunks(&ChunkType::FDAT).is_none());
+    }
+}
+
+#[test]
+fn test_decode_pixels_single_row_and_column() {
+    use rust_png::filter::filter_scanline;
+
+    // 1x5 和 5x1 RGB图像，每种滤镜编码后分别用png crate和自带解码路径还原
+    for (width, height) in [(1usize, 5usize), (5, 1)] {
+        let stride = width * 3;
+        let pixels: Vec<u8> = (0..stride * height).map(|i| (i * 83 % 256) as u8).collect();
+        for filter_type in [FILTER_NONE, FILTER_SUB, FILTER_UP, FILTER_AVERAGE, FILTER_PAETH] {
+            let mut raw = Vec::new();
+            let mut out = Vec::new();
+            for y in 0..height {
+                let row = &pixels[y * stride..(y + 1) * stride];
+                let prev_row = if y > 0 { Some(&pixels[(y - 1) * stride..y * stride]) } else { None };
+                filter_scanline(filter_type, row, prev_row, 3, &mut out);
+                raw.push(filter_type);
+                raw.extend_from_slice(&out);
+            }
+            let data = build_png(width as u32, height as u32, 8, COLORTYPE_COLOR, 0, &raw);
+
+            let decoder = png::Decoder::new(data.as_slice());
+            let mut reader = decoder.read_info().unwrap();
+            let mut expected = vec![0; reader.output_buffer_size()];
+            reader.next_frame(&mut expected).unwrap();
+            assert_eq!(expected, pixels);
+
+            let mut parser = PNGChunkParser::new();
+            parser.parse(&data).unwrap();
+            assert_eq!(parser.decode_pixels().unwrap(), pixels, "{}x{} filter_type={}", width, height, filter_type);
+        }
+    }
+}
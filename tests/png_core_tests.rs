@@ -0,0 +1,87 @@
+//! no_std核心解码逻辑测试
+//! no_std下运行: cargo test --no-default-features --test png_core_tests
+
+use rust_png::png_core::*;
+
+/// 拼出一个带正确CRC的chunk
+fn chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut out = (data.len() as u32).to_be_bytes().to_vec();
+    out.extend_from_slice(chunk_type);
+    out.extend_from_slice(data);
+    out.extend_from_slice(&crc32(&out[4..]).to_be_bytes());
+    out
+}
+
+#[test]
+fn test_core_defilter_image() {
+    // 3x2 单通道：第1行Sub，第2行Paeth，原始 [10, 20, 30, 15, 25, 40]
+    let mut data = vec![10, 10, 10, 5, 5, 10];
+    defilter_image(&mut data, 3, 2, 1, &[1, 4]).unwrap();
+    assert_eq!(data, vec![10, 20, 30, 15, 25, 40]);
+
+    assert!(defilter_image(&mut data, 3, 2, 1, &[1, 7]).is_err());
+    assert_eq!(paeth_predictor(10, 20, 10), 20);
+}
+
+#[test]
+fn test_core_split_chunks() {
+    assert_eq!(crc32(b"IEND"), 0xAE426082);
+
+    let mut png = vec![0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a];
+    png.extend(chunk(b"tEXt", b"a\0b"));
+    png.extend(chunk(b"IEND", &[]));
+    // IEND之后的数据被忽略
+    png.extend_from_slice(&[1, 2, 3]);
+
+    let chunks = split_chunks(&png).unwrap();
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(&chunks[0].chunk_type, b"tEXt");
+    assert_eq!(chunks[0].data, b"a\0b");
+    assert!(chunks.iter().all(|c| c.crc_ok));
+
+    // 损坏的CRC只做标记，截断和签名错误返回错误
+    png[8 + 8] ^= 0xff;
+    assert!(!split_chunks(&png).unwrap()[0].crc_ok);
+    assert!(split_chunks(&png[..20]).is_err());
+    assert!(split_chunks(&png[1..]).is_err());
+}
+
+#[test]
+fn test_incremental_crc_matches_crc32() {
+    let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 % 256) as u8).collect();
+
+    // 类型码开头，数据分三段输入
+    let mut crc = Crc32::new();
+    crc.update(b"IDAT");
+    crc.update(&data[..10]);
+    crc.update(&data[10..600]);
+    crc.update(&data[600..]);
+
+    let mut whole = b"IDAT".to_vec();
+    whole.extend_from_slice(&data);
+    assert_eq!(crc.finalize(), crc32(&whole));
+
+    // 空输入
+    assert_eq!(Crc32::new().finalize(), crc32(&[]));
+}
+
+#[test]
+fn test_adler32() {
+    assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+    assert_eq!(adler32(&[]), 1);
+
+    // 分段输入与一次输入结果相同，长数据跨过取模分段
+    let data: Vec<u8> = (0..20000u32).map(|_| 0xff).collect();
+    let mut adler = Adler32::new();
+    adler.update(&data[..7]);
+    adler.update(&data[7..12000]);
+    adler.update(&data[12000..]);
+    assert_eq!(adler.finalize(), adler32(&data));
+
+    // 与flate2生成的zlib数据流末尾校验值一致
+    use std::io::Write;
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&data).unwrap();
+    let zlib = encoder.finish().unwrap();
+    assert_eq!(zlib[zlib.len() - 4..], adler32(&data).to_be_bytes());
+}
@@ -0,0 +1,930 @@
+//! PNG打包器测试用例
+
+use rust_png::constants::*;
+use rust_png::png_packer::*;
+use rust_png::png_chunks::*;
+use rust_png::png_core::defilter_image;
+
+/// 生成纯色RGBA图像
+fn solid_rgba(width: u32, height: u32, color: [u8; 4]) -> Vec<u8> {
+    (0..width * height).flat_map(|_| color).collect()
+}
+
+/// 使用png crate解码，返回(宽, 高, 像素数据)
+fn decode(data: &[u8]) -> (u32, u32, Vec<u8>) {
+    let decoder = png::Decoder::new(data);
+    let mut reader = decoder.read_info().expect("valid PNG header");
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).expect("valid PNG frame");
+    buffer.truncate(info.buffer_size());
+    (info.width, info.height, buffer)
+}
+
+fn pack_with_strategy(data: &[u8], width: u32, height: u32, strategy: u8) -> Vec<u8> {
+    let options = PackerOptions {
+        width,
+        height,
+        deflate_strategy: strategy,
+        ..Default::default()
+    };
+    PNGPacker::new(options).pack(data).unwrap()
+}
+
+#[test]
+fn test_rle_strategy_on_flat_image() {
+    // 纯色图像上RLE策略的输出应小于默认策略
+    let (width, height) = (64, 64);
+    let data = solid_rgba(width, height, [200, 40, 90, 255]);
+
+    let rle = pack_with_strategy(&data, width, height, DEFLATE_STRATEGY_RLE);
+    let default = pack_with_strategy(&data, width, height, DEFLATE_STRATEGY_DEFAULT);
+
+    assert!(rle.len() < default.len(), "rle={} default={}", rle.len(), default.len());
+}
+
+#[test]
+fn test_all_strategies_roundtrip() {
+    // 每种策略的输出都应能正确解码
+    let (width, height) = (13, 7);
+    let data: Vec<u8> = (0..width * height * 4).map(|i| (i * 7 % 251) as u8).collect();
+
+    for strategy in [
+        DEFLATE_STRATEGY_DEFAULT,
+        DEFLATE_STRATEGY_FILTERED,
+        DEFLATE_STRATEGY_HUFFMAN_ONLY,
+        DEFLATE_STRATEGY_RLE,
+        DEFLATE_STRATEGY_FIXED,
+    ] {
+        let encoded = pack_with_strategy(&data, width, height, strategy);
+        let (w, h, pixels) = decode(&encoded);
+        assert_eq!((w, h), (width, height));
+        assert_eq!(pixels, data, "strategy {}", strategy);
+    }
+}
+
+#[test]
+fn test_strategy_filter_mapping() {
+    let mut options = PackerOptions::default();
+
+    options.deflate_strategy = DEFLATE_STRATEGY_RLE;
+    assert_eq!(options.strategy_filters(), &[FILTER_NONE, FILTER_UP]);
+
+    options.deflate_strategy = DEFLATE_STRATEGY_HUFFMAN_ONLY;
+    assert_eq!(options.strategy_filters(), &[FILTER_NONE]);
+
+    options.deflate_strategy = DEFLATE_STRATEGY_DEFAULT;
+    assert_eq!(options.strategy_filters().len(), 5);
+}
+
+#[test]
+fn test_fast_encoder_decodes_correctly() {
+    // 快速模式输出仍应解码为原始像素
+    let (width, height) = (256, 256);
+    let data = solid_rgba(width, height, [12, 34, 56, 255]);
+
+    let encoder = PngEncoder::new(width, height).fast();
+    assert_eq!(encoder.options().filter_type, Some(FILTER_NONE));
+
+    let encoded = encoder.encode(&data).unwrap();
+    let (w, h, pixels) = decode(&encoded);
+    assert_eq!((w, h), (width, height));
+    assert_eq!(pixels, data);
+}
+
+#[test]
+fn test_fixed_filter_type_roundtrip() {
+    // 指定固定滤镜时每行都使用该滤镜
+    let (width, height) = (9, 5);
+    let data: Vec<u8> = (0..width * height * 4).map(|i| (i * 13 % 256) as u8).collect();
+
+    for filter_type in [FILTER_SUB, FILTER_UP, FILTER_AVERAGE, FILTER_PAETH] {
+        let options = PackerOptions {
+            width,
+            height,
+            filter_type: Some(filter_type),
+            ..Default::default()
+        };
+        let encoded = PNGPacker::new(options).pack(&data).unwrap();
+        let (_, _, pixels) = decode(&encoded);
+        assert_eq!(pixels, data, "filter {}", filter_type);
+    }
+}
+
+#[test]
+fn test_grayscale_alpha_stride() {
+    // 灰度+Alpha每像素2字节，alpha必须原样保留
+    let (width, height) = (5, 3);
+    let data: Vec<u8> = (0..width * height).flat_map(|i| [(i * 10) as u8, 255 - i as u8]).collect();
+
+    let encoded = PngEncoder::new(width, height)
+        .with_color_type(COLORTYPE_GRAYSCALE_ALPHA, 8)
+        .encode(&data)
+        .unwrap();
+    let (_, _, pixels) = decode(&encoded);
+    assert_eq!(pixels, data);
+}
+
+#[test]
+fn test_with_srgb_writes_color_chunks() {
+    let (width, height) = (4, 4);
+    let data = solid_rgba(width, height, [10, 20, 30, 255]);
+
+    let encoded = PngEncoder::new(width, height).with_srgb().encode(&data).unwrap();
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    assert_eq!(parser.srgb.as_ref().unwrap().rendering_intent, 0);
+    assert_eq!(parser.gamma.as_ref().unwrap().gamma, 45455);
+    assert_eq!(parser.chroma.as_ref().unwrap().white_point_x, 31270);
+
+    // 色彩chunks必须位于IDAT之前
+    let index = parser.chunk_index();
+    let position = |code: &str| index.iter().position(|info| info.type_code == code).unwrap();
+    assert!(position("sRGB") < position("IDAT"));
+    assert!(position("gAMA") < position("IDAT"));
+    assert!(position("cHRM") < position("IDAT"));
+
+    let (_, _, pixels) = decode(&encoded);
+    assert_eq!(pixels, data);
+}
+
+#[test]
+fn test_with_srgb_intent() {
+    let data = solid_rgba(2, 2, [10, 20, 30, 255]);
+    let encoded = PngEncoder::new(2, 2).with_srgb_intent(SRGB_INTENT_RELATIVE).encode(&data).unwrap();
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    assert_eq!(parser.srgb.as_ref().unwrap().rendering_intent, SRGB_INTENT_RELATIVE);
+    assert_eq!(parser.gamma.as_ref().unwrap().gamma, SRGB_GAMMA);
+
+    assert!(PngEncoder::new(2, 2).with_srgb_intent(4).encode(&data).is_err());
+}
+
+#[test]
+fn test_srgb_and_iccp_are_exclusive() {
+    let options = PackerOptions {
+        width: 1,
+        height: 1,
+        chunks: vec![
+            PNGChunk::new(ChunkType::ICCP, b"custom\0\0".to_vec()),
+            PNGChunk::new(ChunkType::SRGB, vec![0]),
+        ],
+        ..Default::default()
+    };
+    assert!(PNGPacker::new(options).pack(&[0, 0, 0, 255]).is_err());
+}
+
+#[test]
+fn test_idat_chunk_size() {
+    let (width, height) = (32, 32);
+    let data: Vec<u8> = (0..width * height * 4).map(|i| (i * 31 % 253) as u8).collect();
+    let idat_count = |encoded: &[u8]| {
+        let mut parser = PNGChunkParser::new();
+        parser.parse(encoded).unwrap();
+        parser.chunk_index().iter().filter(|info| info.type_code == "IDAT").count()
+    };
+
+    // None：只写一个IDAT
+    let single = PNGPacker::new(PackerOptions {
+        width,
+        height,
+        idat_chunk_size: None,
+        ..Default::default()
+    }).pack(&data).unwrap();
+    assert_eq!(idat_count(&single), 1);
+
+    // 很小的chunk尺寸：拆分为多个IDAT
+    let split = PNGPacker::new(PackerOptions {
+        width,
+        height,
+        idat_chunk_size: Some(64),
+        ..Default::default()
+    }).pack(&data).unwrap();
+    assert!(idat_count(&split) > 1);
+
+    assert_eq!(decode(&single).2, data);
+    assert_eq!(decode(&split).2, data);
+}
+
+#[test]
+fn test_auto_histogram_roundtrip() {
+    // 16色调色板，第k个条目使用k+1次
+    let palette: Vec<u8> = (0..16u8).flat_map(|k| [k * 16, 255 - k * 16, k]).collect();
+    let indices: Vec<u8> = (0..16u8).flat_map(|k| std::iter::repeat(k).take(k as usize + 1)).collect();
+    assert_eq!(indices.len(), 17 * 8);
+
+    let encoded = PngEncoder::new(17, 8)
+        .with_color_type(COLORTYPE_PALETTE_COLOR, 8)
+        .with_palette(&palette)
+        .with_histogram()
+        .encode(&indices)
+        .unwrap();
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    let expected: Vec<u16> = (1..=16).collect();
+    assert_eq!(parser.histogram.as_ref().unwrap().frequencies, expected);
+
+    // hIST位于PLTE之后、IDAT之前
+    let index = parser.chunk_index();
+    let position = |code: &str| index.iter().position(|info| info.type_code == code).unwrap();
+    assert!(position("PLTE") < position("hIST"));
+    assert!(position("hIST") < position("IDAT"));
+
+    // 解析后重新序列化保持不变
+    let mut reparsed = PNGChunkParser::new();
+    reparsed.parse(&parser.serialize()).unwrap();
+    assert_eq!(reparsed.histogram.unwrap().frequencies, expected);
+}
+
+#[test]
+fn test_histogram_requires_palette() {
+    let result = PngEncoder::new(1, 1)
+        .with_color_type(COLORTYPE_PALETTE_COLOR, 8)
+        .with_histogram()
+        .encode(&[0]);
+    assert!(result.is_err());
+
+    // 条目数必须与调色板一致
+    assert!(HISTData::from_bytes(&[0, 1, 0, 2], 3).is_err());
+    assert_eq!(HISTData::from_bytes(&[0, 1, 0, 2], 2).unwrap().frequencies, vec![1, 2]);
+}
+
+#[test]
+fn test_solid_image_filter_bytes() {
+    // 纯色图像（DEFAULT策略）：首行所有像素相同用None，其余行与上一行相同用Up
+    let (width, height) = (64, 64);
+    let data = solid_rgba(width, height, [90, 160, 30, 255]);
+    let encoded = PngEncoder::new(width, height).deterministic().encode(&data).unwrap();
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    let raw = rust_png::sync_inflate::inflate_zlib(&parser.idat_data()).unwrap();
+    let stride = width as usize * 4 + 1;
+    let filters: Vec<u8> = raw.chunks_exact(stride).map(|line| line[0]).collect();
+
+    assert_eq!(filters[0], FILTER_NONE);
+    assert!(filters[1..].iter().all(|&f| f == FILTER_UP));
+    assert!(encoded.len() < 200, "encoded {} bytes", encoded.len());
+    assert_eq!(decode(&encoded).2, data);
+}
+
+#[test]
+fn test_auto_color_type_selection() {
+    // 两种颜色的不透明图像：调色板
+    let two_colors: Vec<u8> = (0..16u32).flat_map(|i| if i % 2 == 0 { [255, 0, 0, 255] } else { [0, 0, 255, 255] }).collect();
+    assert_eq!(select_color_type(&two_colors), COLORTYPE_PALETTE_COLOR);
+
+    let encoded = PngEncoder::new(4, 4).auto_color_type().encode(&two_colors).unwrap();
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    assert_eq!(parser.ihdr.as_ref().unwrap().color_type, COLORTYPE_PALETTE_COLOR);
+    assert_eq!(parser.palette.as_ref().unwrap().palette.len(), 2);
+    assert_eq!(parser.palette.as_ref().unwrap().palette, vec![[255, 0, 0], [0, 0, 255]]);
+    assert_eq!(decode(&encoded).2, (0..16).map(|i| (i % 2) as u8).collect::<Vec<u8>>());
+
+    // 平滑alpha的彩色图像（超过256种颜色）：RGBA
+    let smooth: Vec<u8> = (0..32 * 32u32).flat_map(|i| [(i % 32 * 8) as u8, (i / 32 * 8) as u8, 100, (i % 256) as u8]).collect();
+    assert_eq!(select_color_type(&smooth), COLORTYPE_COLOR_ALPHA);
+    let encoded = PngEncoder::new(32, 32).auto_color_type().encode(&smooth).unwrap();
+    assert_eq!(decode(&encoded).2, smooth);
+}
+
+#[test]
+fn test_auto_color_type_grayscale() {
+    // 不透明灰度：灰度
+    let gray: Vec<u8> = (0..300u32).flat_map(|i| { let v = (i % 256) as u8; [v, v, v, 255] }).collect();
+    assert_eq!(select_color_type(&gray), COLORTYPE_GRAYSCALE);
+
+    // 灰度带alpha且超过256种组合：灰度+Alpha
+    let gray_alpha: Vec<u8> = (0..300u32).flat_map(|i| { let v = (i % 256) as u8; [v, v, v, (i / 2) as u8] }).collect();
+    assert_eq!(select_color_type(&gray_alpha), COLORTYPE_GRAYSCALE_ALPHA);
+
+    let encoded = PngEncoder::new(20, 15).auto_color_type().encode(&gray_alpha).unwrap();
+    let expected: Vec<u8> = gray_alpha.chunks_exact(4).flat_map(|p| [p[0], p[3]]).collect();
+    assert_eq!(decode(&encoded).2, expected);
+
+    // 不透明彩色且颜色很多：RGB
+    let color: Vec<u8> = (0..300u32).flat_map(|i| [(i % 256) as u8, (i / 2) as u8, 7, 255]).collect();
+    assert_eq!(select_color_type(&color), COLORTYPE_COLOR);
+}
+
+#[test]
+fn test_deterministic_output() {
+    let (width, height) = (13u32, 9u32);
+    let data: Vec<u8> = (0..width * height * 4).map(|i| (i * 31 % 251) as u8).collect();
+
+    let options = PackerOptions { width, height, ..PackerOptions::deterministic() };
+    let first = PNGPacker::new(options.clone()).pack(&data).unwrap();
+    let second = PNGPacker::new(options).pack(&data).unwrap();
+    // 相同输入两次编码逐字节一致
+    assert_eq!(first, second);
+
+    let encoded = PngEncoder::new(width, height).deterministic().encode(&data).unwrap();
+    assert_eq!(encoded, first);
+    assert_eq!(decode(&encoded).2, data);
+}
+
+#[test]
+fn test_encoder_rejects_invalid_color_bitdepth() {
+    // 调色板图像不允许16位
+    let result = PngEncoder::new(2, 2)
+        .with_color_type(COLORTYPE_PALETTE_COLOR, 16)
+        .encode(&[0; 16]);
+    let error = result.unwrap_err();
+    assert!(error.contains("bit depth 16") && error.contains("color type 3"), "{}", error);
+
+    let options = PackerOptions { width: 1, height: 1, color_type: COLORTYPE_COLOR, bit_depth: 4, ..Default::default() };
+    assert!(PNGPacker::new(options).pack(&[0; 4]).is_err());
+}
+
+#[test]
+fn test_minimal_bit_depth_selection() {
+    use rust_png::bitmapper::*;
+
+    let palette = |count: usize| PaletteProcessor::new((0..count).map(|i| [i as u8, 0, 0]).collect());
+    assert_eq!(palette(2).minimal_bit_depth(), 1);
+    assert_eq!(palette(3).minimal_bit_depth(), 2);
+    assert_eq!(palette(12).minimal_bit_depth(), 4);
+    assert_eq!(palette(17).minimal_bit_depth(), 8);
+    assert_eq!(minimal_index_depth(&[0, 3, 1]), 2);
+
+    // 只有纯黑和纯白时可用1位
+    assert_eq!(minimal_grayscale_depth(&[0, 255, 255, 0]), 1);
+    assert_eq!(minimal_grayscale_depth(&[0, 85, 170, 255]), 2);
+    assert_eq!(minimal_grayscale_depth(&[17, 34, 238]), 4);
+    assert_eq!(minimal_grayscale_depth(&[0, 128, 255]), 8);
+}
+
+#[test]
+fn test_quantize_palette_alpha() {
+    use rust_png::bitmapper::*;
+
+    let mut processor = PaletteProcessor::new((0..6).map(|i| [i as u8 * 40, 0, 0]).collect());
+    processor.set_trans_color(vec![255, 250, 3, 160, 254, 255]);
+    let before = processor.process_palette_data(&[0, 1, 2, 3, 4, 5]).unwrap();
+
+    // 两级时只剩0和255，末尾的255被去掉
+    processor.quantize_alpha(2).unwrap();
+    assert_eq!(processor.trans_color(), Some(&[255, 255, 0][..]));
+
+    // RGB不变，alpha误差不超过半个量化间隔
+    let after = processor.process_palette_data(&[0, 1, 2, 3, 4, 5]).unwrap();
+    for (old, new) in before.chunks_exact(4).zip(after.chunks_exact(4)) {
+        assert_eq!(old[..3], new[..3]);
+        assert!(old[3].abs_diff(new[3]) <= 128);
+    }
+
+    // 五级：0, 64, 128, 191, 255
+    let mut processor = PaletteProcessor::new(vec![[0, 0, 0]; 4]);
+    processor.set_trans_color(vec![30, 100, 180, 250]);
+    processor.quantize_alpha(5).unwrap();
+    assert_eq!(processor.trans_color(), Some(&[0, 128, 191][..]));
+
+    // 全部接近不透明时不再需要tRNS
+    processor.set_trans_color(vec![254, 253]);
+    processor.quantize_alpha(4).unwrap();
+    assert_eq!(processor.trans_color(), None);
+    assert!(processor.quantize_alpha(1).is_err());
+}
+
+#[test]
+fn test_encoder_minimal_bit_depth() {
+    use rust_png::bitmap::unpack_indices;
+
+    // 黑白灰度图像编码为1位
+    let gray = [0u8, 255, 255, 0, 255, 0, 0, 255];
+    let encoded = PngEncoder::new(4, 2)
+        .with_color_type(COLORTYPE_GRAYSCALE, 8)
+        .minimal_bit_depth()
+        .encode(&gray)
+        .unwrap();
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    assert_eq!(parser.ihdr.as_ref().unwrap().bit_depth, 1);
+    let samples = unpack_indices(&parser.decode_pixels().unwrap(), 4, 2, 1);
+    assert_eq!(samples, gray.iter().map(|&v| v / 255).collect::<Vec<u8>>());
+
+    // 3种颜色的调色板图像编码为2位
+    let colors = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]];
+    let rgba: Vec<u8> = (0..10).flat_map(|i| colors[i % 3]).collect();
+    let encoded = PngEncoder::new(5, 2).auto_color_type().minimal_bit_depth().encode(&rgba).unwrap();
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    let ihdr = parser.ihdr.as_ref().unwrap();
+    assert_eq!((ihdr.color_type, ihdr.bit_depth), (COLORTYPE_PALETTE_COLOR, 2));
+    let indices = unpack_indices(&parser.decode_pixels().unwrap(), 5, 2, 2);
+    assert_eq!(indices, (0..10).map(|i| (i % 3) as u8).collect::<Vec<u8>>());
+}
+
+#[test]
+fn test_pack_to_matches_pack() {
+    let (width, height) = (64u32, 48u32);
+    // 伪随机数据，压缩后仍远大于单个IDAT
+    let mut seed = 1u32;
+    let data: Vec<u8> = (0..width * height * 4)
+        .map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as u8
+        })
+        .collect();
+    let options = PackerOptions { width, height, idat_chunk_size: Some(1000), ..Default::default() };
+    let packer = PNGPacker::new(options);
+
+    let mut streamed = Vec::new();
+    packer.pack_to(&data, &mut streamed).unwrap();
+    assert_eq!(streamed, packer.pack(&data).unwrap());
+
+    // 压缩输出按chunk大小切分为多个IDAT
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&streamed).unwrap();
+    let idats = parser.get_chunks(&ChunkType::IDAT).unwrap();
+    assert!(idats.len() > 1);
+    assert!(idats[..idats.len() - 1].iter().all(|chunk| chunk.data.len() == 1000));
+    assert_eq!(decode(&streamed).2, data);
+}
+
+#[test]
+fn test_binarize_alpha_uses_rgb_with_trns() {
+    // 硬边遮罩：左半透明、右半不透明；不透明部分占用了 0x000000 和 0x000001
+    let (width, height) = (8u32, 4u32);
+    let rgba: Vec<u8> = (0..width * height)
+        .flat_map(|i| match i % width {
+            0..=3 => [200, 100, 50, 0],
+            4 | 5 => [0, 0, 0, 255],
+            _ => [0, 0, 1, 255],
+        })
+        .collect();
+
+    let encoded = PngEncoder::new(width, height).binarize_alpha(128).encode(&rgba).unwrap();
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    assert_eq!(parser.ihdr.as_ref().unwrap().color_type, COLORTYPE_COLOR);
+    // 关键色必须是图像中未使用的颜色
+    let trns = &parser.get_chunks(&ChunkType::TRNS).unwrap()[0].data;
+    assert_eq!(trns, &vec![0, 0, 0, 0, 0, 2]);
+
+    // 展开tRNS后解码结果与原图一致（全透明像素的颜色不保留）
+    let mut decoder = png::Decoder::new(encoded.as_slice());
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().unwrap();
+    let mut decoded = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut decoded).unwrap();
+    for (decoded, original) in decoded.chunks_exact(4).zip(rgba.chunks_exact(4)) {
+        assert_eq!(decoded[3], original[3]);
+        if original[3] == 255 {
+            assert_eq!(decoded, original);
+        }
+    }
+
+    // 没有透明像素时只去掉alpha通道
+    let opaque = solid_rgba(4, 4, [1, 2, 3, 255]);
+    let encoded = PngEncoder::new(4, 4).binarize_alpha(128).encode(&opaque).unwrap();
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    assert!(parser.get_chunks(&ChunkType::TRNS).is_none());
+    assert_eq!(decode(&encoded).2, vec![1, 2, 3].repeat(16));
+}
+
+#[test]
+fn test_encode_two_bit_palette_indices() {
+    // 4色调色板，每像素一字节索引输入，以2位深度编码
+    let (width, height) = (5u32, 3u32);
+    let indices: Vec<u8> = (0..width * height).map(|i| (i % 4) as u8).collect();
+    let palette = [255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+
+    let encoded = PngEncoder::new(width, height)
+        .with_color_type(COLORTYPE_PALETTE_COLOR, 2)
+        .with_palette(&palette)
+        .encode(&indices)
+        .unwrap();
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    assert_eq!(parser.ihdr.as_ref().unwrap().bit_depth, 2);
+    // 每行5个像素打包为2字节
+    assert_eq!(parser.decode_pixels().unwrap().len(), 2 * height as usize);
+
+    // png crate展开为每像素一字节后得到原始索引
+    let mut decoder = png::Decoder::new(encoded.as_slice());
+    decoder.set_transformations(png::Transformations::IDENTITY);
+    let mut reader = decoder.read_info().unwrap();
+    let mut packed = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut packed).unwrap();
+    let unpacked: Vec<u8> = packed
+        .chunks_exact(2)
+        .flat_map(|row| (0..width as usize).map(move |x| (row[x / 4] >> (6 - 2 * (x % 4))) & 0x03))
+        .collect();
+    assert_eq!(unpacked, indices);
+
+    // 超出位深度的索引被拒绝
+    assert!(PngEncoder::new(width, height)
+        .with_color_type(COLORTYPE_PALETTE_COLOR, 2)
+        .with_palette(&palette)
+        .encode(&vec![4; 15])
+        .is_err());
+}
+
+#[test]
+fn test_candidate_filters_restrict_selection() {
+    let (width, height) = (32u32, 24u32);
+    let data: Vec<u8> = (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = (i % width, i / width);
+            [(x * 8) as u8, (y * 10) as u8, ((x * y) % 256) as u8, 255]
+        })
+        .collect();
+    let candidates = [FILTER_NONE, FILTER_PAETH];
+
+    let encoded = PngEncoder::new(width, height)
+        .deterministic()
+        .with_candidate_filters(&candidates)
+        .encode(&data)
+        .unwrap();
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    assert!(parser.filter_bytes().unwrap().iter().all(|filter_type| candidates.contains(filter_type)));
+    assert_eq!(decode(&encoded).2, data);
+
+    // 与策略取交集：RLE只允许None/Up
+    let options = PackerOptions { candidate_filters: vec![FILTER_UP, FILTER_PAETH], ..Default::default() };
+    assert_eq!(options.adaptive_filters().unwrap(), vec![FILTER_UP]);
+
+    // 空集合、非法类型、与策略没有交集时报错
+    for candidates in [vec![], vec![FILTER_NONE, 5], vec![FILTER_PAETH]] {
+        let options = PackerOptions { width, height, candidate_filters: candidates, ..Default::default() };
+        assert!(PNGPacker::new(options).pack(&data).is_err());
+    }
+}
+
+#[test]
+fn test_filtered_scanlines_defilter_to_original() {
+    let (width, height) = (20u32, 16u32);
+    let data: Vec<u8> = (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = (i % width, i / width);
+            [(x * 12) as u8, (y * 15) as u8, ((x ^ y) * 9) as u8]
+        })
+        .collect();
+    let options = PackerOptions {
+        width,
+        height,
+        color_type: COLORTYPE_COLOR,
+        input_color_type: COLORTYPE_COLOR,
+        deflate_strategy: DEFLATE_STRATEGY_DEFAULT,
+        ..Default::default()
+    };
+    let stream = PNGPacker::new(options).filtered_scanlines(&data).unwrap();
+
+    // 每行一个滤镜字节加60字节滤镜数据
+    let stride = width as usize * 3;
+    assert_eq!(stream.len(), (stride + 1) * height as usize);
+    let filter_bytes: Vec<u8> = stream.chunks_exact(stride + 1).map(|row| row[0]).collect();
+    let mut pixels: Vec<u8> = stream.chunks_exact(stride + 1).flat_map(|row| row[1..].to_vec()).collect();
+    assert!(filter_bytes.iter().any(|&filter_type| filter_type != FILTER_NONE));
+
+    defilter_image(&mut pixels, width as usize, height as usize, 3, &filter_bytes).unwrap();
+    assert_eq!(pixels, data);
+}
+
+#[test]
+fn test_add_raw_chunk_round_trip() {
+    let data = solid_rgba(2, 2, [1, 2, 3, 255]);
+    let encoded = PngEncoder::new(2, 2)
+        .with_srgb()
+        .add_raw_chunk("myAp", b"app settings")
+        .encode(&data)
+        .unwrap();
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    assert_eq!(parser.get_chunk_data("myAp"), Some(&b"app settings"[..]));
+    assert_eq!(parser.get_chunk_data("miss"), None);
+
+    // 写在色彩chunks之后、IDAT之前
+    let position = |name: &[u8]| encoded.windows(4).position(|w| w == name).unwrap();
+    assert!(position(b"sRGB") < position(b"myAp"));
+    assert!(position(b"myAp") < position(b"IDAT"));
+    assert_eq!(decode(&encoded).2, data);
+
+    let chunk_type = ChunkType::from_name("myAp").unwrap();
+    assert!(chunk_type.is_ancillary() && chunk_type.is_private());
+
+    // 关键chunk、保留位小写、长度或字符非法时编码失败
+    for name in ["MYAP", "myap", "myA", "my4p"] {
+        assert!(PngEncoder::new(2, 2).add_raw_chunk(name, &[]).encode(&data).is_err());
+    }
+}
+
+#[test]
+fn test_estimate_compressed_size_tracks_real_size() {
+    // 模拟照片：平滑的明暗变化叠加伪随机噪声
+    let (width, height) = (200u32, 150u32);
+    let mut state = 7u32;
+    let data: Vec<u8> = (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = ((i % width) as f64 / width as f64, (i / width) as f64 / height as f64);
+            let base = [
+                120.0 + 80.0 * (x * 6.0).sin() * (y * 4.0).cos(),
+                90.0 + 60.0 * y + 40.0 * ((x + y) * 9.0).sin(),
+                60.0 + 100.0 * x * y,
+            ];
+            base.map(|value| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                (value as i32 + ((state >> 16) % 13) as i32 - 6).clamp(0, 255) as u8
+            })
+        })
+        .collect();
+
+    let options = PackerOptions {
+        width,
+        height,
+        color_type: COLORTYPE_COLOR,
+        input_color_type: COLORTYPE_COLOR,
+        deflate_strategy: DEFLATE_STRATEGY_DEFAULT,
+        ..Default::default()
+    };
+    let packer = PNGPacker::new(options);
+    let estimate = packer.estimate_compressed_size(&data).unwrap() as f64;
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&packer.pack(&data).unwrap()).unwrap();
+    let real: usize = parser.get_chunks(&ChunkType::IDAT).unwrap().iter().map(|chunk| chunk.data.len()).sum();
+    let ratio = estimate / real as f64;
+    assert!((0.75..=1.25).contains(&ratio), "estimate {} vs real {}", estimate, real);
+
+    // 重复内容能被匹配，估算远小于原始大小
+    let repeated = solid_rgba(64, 64, [10, 20, 30, 255]);
+    assert!(estimate_deflate_size(&repeated) < 200);
+}
+
+#[test]
+fn test_chunk_placement_after_idat_round_trips() {
+    // 私有chunk "prVa" 在IDAT之前，"prVb" 在IDAT之后
+    let data = solid_rgba(2, 2, [40, 50, 60, 255]);
+    let mut file = PngEncoder::new(2, 2).add_raw_chunk("prVa", b"before").encode(&data).unwrap();
+    let iend = file.len() - 12;
+    file.splice(iend..iend, PNGChunk::new(ChunkType::from_name("prVb").unwrap(), b"after".to_vec()).to_bytes());
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&file).unwrap();
+    let chunk = |name: &str| parser.get_chunks(&ChunkType::from_name(name).unwrap()).unwrap()[0].clone();
+    assert!(!chunk("prVa").after_idat);
+    assert!(chunk("prVb").after_idat);
+
+    let order = |png: &[u8]| {
+        let position = |name: &[u8]| png.windows(4).position(|w| w == name).unwrap();
+        assert!(position(b"prVa") < position(b"IDAT"));
+        assert!(position(b"IDAT") < position(b"prVb"));
+        assert!(position(b"prVb") < position(b"IEND"));
+    };
+
+    // 原样序列化和重新编码都保持相对IDAT的位置
+    order(&parser.serialize());
+    let options = PackerOptions { width: 2, height: 2, chunks: parser.passthrough_chunks(), ..Default::default() };
+    let repacked = PNGPacker::new(options).pack(&data).unwrap();
+    order(&repacked);
+    assert_eq!(decode(&repacked).2, data);
+}
+
+#[test]
+fn test_apng_frames_round_trip() {
+    // 3帧APNG：默认图像为第1帧，fcTL/fdAT交替出现，序列号必须保持递增
+    let frames: Vec<Vec<u8>> = (0..3u8).map(|i| solid_rgba(2, 2, [i * 80, 20, 200 - i * 60, 255])).collect();
+    let mut file = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut file, 2, 2);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(3, 0).unwrap();
+        let mut writer = encoder.write_header().unwrap();
+        for frame in &frames {
+            writer.write_image_data(frame).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&file).unwrap();
+    let types: Vec<[u8; 4]> = parser.passthrough_chunks().iter().map(|chunk| chunk.chunk_type.to_u32().to_be_bytes()).collect();
+    assert_eq!(types, [*b"acTL", *b"fcTL", *b"fcTL", *b"fdAT", *b"fcTL", *b"fdAT"]);
+
+    // 原样序列化和重新编码（reoptimize的路径）后所有帧都能解码
+    let options = PackerOptions { width: 2, height: 2, chunks: parser.passthrough_chunks(), ..Default::default() };
+    for png in [parser.serialize(), PNGPacker::new(options).pack(&frames[0]).unwrap()] {
+        let mut reader = png::Decoder::new(&png[..]).read_info().unwrap();
+        assert_eq!(reader.info().animation_control.unwrap().num_frames, 3);
+        for frame in &frames {
+            let mut pixels = vec![0; reader.output_buffer_size()];
+            reader.next_frame(&mut pixels).unwrap();
+            assert_eq!(&pixels, frame);
+        }
+    }
+}
+
+#[test]
+fn test_png16_from_f32_range_mapping() {
+    // 范围[-10, 30]，超出范围的值截断
+    let data = [-10.0f32, 30.0, 10.0, -50.0, 100.0, f32::NAN];
+    let image = Png16::from_f32(&data, 3, 2, -10.0, 30.0).unwrap();
+    assert_eq!(image.samples(), &[0, 65535, 32768, 0, 65535, 0]);
+
+    // 编码为16位灰度，像素按大端存储
+    let encoded = image.encode().unwrap();
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    let ihdr = parser.ihdr.as_ref().unwrap();
+    assert_eq!((ihdr.color_type, ihdr.bit_depth), (COLORTYPE_GRAYSCALE, 16));
+    let (width, height, pixels) = decode(&encoded);
+    assert_eq!((width, height), (3, 2));
+    assert_eq!(pixels, vec![0, 0, 255, 255, 128, 0, 0, 0, 255, 255, 0, 0]);
+
+    // 样本数不符或范围无效时报错
+    assert!(Png16::from_f32(&data, 2, 2, -10.0, 30.0).is_err());
+    assert!(Png16::from_f32(&data, 3, 2, 5.0, 5.0).is_err());
+}
+
+#[test]
+fn test_encode_16bit_grayscale_transparency() {
+    // 16位灰度，关键值0x1234占满2字节；低字节相同的0x0034不透明
+    let samples = [0x1234u16, 0x0034, 0xffff, 0x1234];
+    let data: Vec<u8> = samples.iter().flat_map(|sample| sample.to_be_bytes()).collect();
+    let encoded = PngEncoder::new(2, 2)
+        .with_color_type(COLORTYPE_GRAYSCALE, 16)
+        .with_transparency(TRNSData::Grayscale { value: 0x1234 })
+        .encode(&data)
+        .unwrap();
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    assert_eq!(parser.get_chunks(&ChunkType::TRNS).unwrap()[0].data, vec![0x12, 0x34]);
+
+    // 展开tRNS后为16位灰度+Alpha，只有关键值像素透明
+    let mut decoder = png::Decoder::new(encoded.as_slice());
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().unwrap();
+    let mut decoded = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut decoded).unwrap();
+    let alpha: Vec<u16> = decoded.chunks_exact(4).map(|pixel| u16::from_be_bytes([pixel[2], pixel[3]])).collect();
+    assert_eq!(alpha, vec![0, 0xffff, 0xffff, 0]);
+
+    // 关键值超出位深度范围、与颜色类型不符时编码失败
+    let gray4 = PngEncoder::new(2, 2).with_color_type(COLORTYPE_GRAYSCALE, 4);
+    assert!(gray4.clone().with_transparency(TRNSData::Grayscale { value: 16 }).encode(&[0; 4]).is_err());
+    assert!(gray4.with_transparency(TRNSData::RGB { r: 0, g: 0, b: 0 }).encode(&[0; 4]).is_err());
+}
+
+#[test]
+fn test_encode_grayscale_alpha_input() {
+    // 灰度+Alpha输入直接编码为灰度+Alpha PNG
+    let data: Vec<u8> = (0..12u8).flat_map(|i| [i * 20, 255 - i * 10]).collect();
+    let encoded = PngEncoder::new(4, 3)
+        .with_input_color_type(COLORTYPE_GRAYSCALE_ALPHA)
+        .encode(&data)
+        .unwrap();
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    let ihdr = parser.ihdr.as_ref().unwrap();
+    assert_eq!((ihdr.color_type, ihdr.bit_depth), (COLORTYPE_GRAYSCALE_ALPHA, 8));
+
+    // 不做转换解码回原始样本
+    let mut reader = png::Decoder::new(encoded.as_slice()).read_info().unwrap();
+    let mut decoded = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut decoded).unwrap();
+    assert_eq!(decoded, data);
+
+    // 输出RGBA时灰度复制到RGB三个通道，alpha保留
+    let normalizer = FormatNormalizer::new(COLORTYPE_GRAYSCALE_ALPHA, COLORTYPE_COLOR_ALPHA, 8, 8);
+    assert_eq!(normalizer.normalize(&[10, 20, 30, 40], 2, 1).unwrap(), vec![10, 10, 10, 20, 30, 30, 30, 40]);
+}
+
+#[test]
+fn test_encode_bilevel_mask() {
+    // 展开为8位灰度读取：0 → 0，1 → 255
+    let decode_gray = |encoded: &[u8]| {
+        let mut decoder = png::Decoder::new(encoded);
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!((info.color_type, info.bit_depth), (png::ColorType::Grayscale, png::BitDepth::One));
+        let mut decoded = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut decoded).unwrap();
+        decoded
+    };
+
+    // 8x8对角线，非0值都视为白色
+    let mask: Vec<u8> = (0..64).map(|i| if i % 9 == 0 { 7 } else { 0 }).collect();
+    let encoded = PngEncoder::encode_bilevel(&mask, 8, 8).unwrap();
+    let expected: Vec<u8> = mask.iter().map(|&value| if value != 0 { 255 } else { 0 }).collect();
+    assert_eq!(decode_gray(&encoded), expected);
+
+    // 宽度不是8的倍数：每行单独补齐
+    let mask: Vec<u8> = (0..30).map(|i| (i % 10 == 9 || i % 7 == 0) as u8).collect();
+    let encoded = PngEncoder::encode_bilevel(&mask, 10, 3).unwrap();
+    let expected: Vec<u8> = mask.iter().map(|&value| value * 255).collect();
+    assert_eq!(decode_gray(&encoded), expected);
+
+    assert!(PngEncoder::encode_bilevel(&mask, 8, 8).is_err());
+}
+
+#[test]
+fn test_max_compression_not_larger() {
+    // 类似照片的图像：平滑渐变叠加少量噪声
+    let (width, height) = (64u32, 48u32);
+    let mut seed = 7u32;
+    let data: Vec<u8> = (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = (i % width, i / width);
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let noise = (seed >> 16) % 8;
+            [(x * 3 + noise) as u8, (y * 4 + noise) as u8, ((x + y) * 2) as u8]
+        })
+        .collect();
+
+    let encoder = PngEncoder::new(width, height).with_color_type(COLORTYPE_COLOR, 8);
+    let default = encoder.encode(&data).unwrap();
+    let smallest = encoder.clone().with_max_compression(8).encode(&data).unwrap();
+
+    assert!(smallest.len() <= default.len(), "{} > {}", smallest.len(), default.len());
+    assert_eq!(decode(&smallest), decode(&default));
+    assert_eq!(decode(&smallest).2, data);
+
+    // 0次额外尝试等同于默认配置
+    assert_eq!(encoder.with_max_compression(0).encode(&data).unwrap(), default);
+}
+
+#[test]
+fn test_palette_trns_truncated() {
+    // 4色调色板中只有条目1半透明：tRNS只写条目0和1
+    let colors = [[255, 0, 0, 255], [0, 255, 0, 128], [0, 0, 255, 255], [9, 9, 9, 255]];
+    let rgba: Vec<u8> = (0..16).flat_map(|i| colors[i % 4]).collect();
+    let encoded = PngEncoder::new(4, 4).auto_color_type().encode(&rgba).unwrap();
+
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encoded).unwrap();
+    assert_eq!(parser.ihdr.as_ref().unwrap().color_type, COLORTYPE_PALETTE_COLOR);
+    assert_eq!(parser.get_chunks(&ChunkType::TRNS).unwrap()[0].data, vec![255, 128]);
+
+    // 显式指定的调色板alpha同样截断，全部不透明时不写tRNS
+    let palette = [255, 0, 0, 0, 255, 0];
+    let encode = |alpha: Vec<u8>| PngEncoder::new(2, 1)
+        .with_color_type(COLORTYPE_PALETTE_COLOR, 8)
+        .with_palette(&palette)
+        .with_transparency(TRNSData::Palette { alpha })
+        .encode(&[0, 1])
+        .unwrap();
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encode(vec![0, 255])).unwrap();
+    assert_eq!(parser.get_chunks(&ChunkType::TRNS).unwrap()[0].data, vec![0]);
+    let mut parser = PNGChunkParser::new();
+    parser.parse(&encode(vec![255, 255])).unwrap();
+    assert!(!parser.has_chunk(&ChunkType::TRNS));
+}
+
+#[test]
+fn test_recommend_format() {
+    // 两种颜色的不透明灰度：1位灰度
+    let two_gray: Vec<u8> = (0..16).flat_map(|i| if i % 3 == 0 { [0, 0, 0, 255] } else { [255, 255, 255, 255] }).collect();
+    let recommendation = recommend_format(&two_gray);
+    assert_eq!((recommendation.color_type, recommendation.bit_depth), (COLORTYPE_GRAYSCALE, 1));
+
+    // 灰度级数少但无法降低灰度位深度：4位调色板更小
+    let odd_gray: Vec<u8> = (0..16u8).flat_map(|i| [i * 3 + 1, i * 3 + 1, i * 3 + 1, 255]).collect();
+    let recommendation = recommend_format(&odd_gray);
+    assert_eq!((recommendation.color_type, recommendation.bit_depth), (COLORTYPE_PALETTE_COLOR, 4));
+
+    // 三种彩色：2位调色板
+    let colors: Vec<u8> = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 128]].concat();
+    let recommendation = recommend_format(&colors);
+    assert_eq!((recommendation.color_type, recommendation.bit_depth), (COLORTYPE_PALETTE_COLOR, 2));
+
+    // 超过256种颜色
+    let opaque: Vec<u8> = (0..300u32).flat_map(|i| [i as u8, (i >> 8) as u8, 7, 255]).collect();
+    assert_eq!(recommend_format(&opaque).color_type, COLORTYPE_COLOR);
+    let translucent: Vec<u8> = (0..300u32).flat_map(|i| [i as u8, (i >> 8) as u8, 7, if i % 2 == 0 { 0 } else { 255 }]).collect();
+    let recommendation = recommend_format(&translucent);
+    assert_eq!((recommendation.color_type, recommendation.bit_depth), (COLORTYPE_COLOR_ALPHA, 8));
+    assert!(recommendation.reason.contains("binary"));
+}
+
+#[test]
+fn test_pack_with_progress_is_bounded() {
+    // 1000行的细长图像，回调次数有上限且进度单调递增到1.0
+    let (width, height) = (3u32, 1000u32);
+    let data: Vec<u8> = (0..width * height * 4).map(|i| (i % 251) as u8).collect();
+
+    for filter_type in [None, Some(FILTER_NONE), Some(FILTER_PAETH)] {
+        let options = PackerOptions { width, height, filter_type, ..Default::default() };
+        let packer = PNGPacker::new(options);
+
+        let mut reports = Vec::new();
+        let encoded = packer.pack_with_progress(&data, |fraction| reports.push(fraction)).unwrap();
+        assert_eq!(encoded, packer.pack(&data).unwrap());
+
+        assert!(reports.len() >= 10 && reports.len() <= PROGRESS_STEPS + 1, "{} reports", reports.len());
+        assert!(reports.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(reports.last(), Some(&1.0));
+    }
+}
@@ -67,6 +67,34 @@ fn test_palette_color_info() {
     assert!(color_info.is_indexed());
 }
 
+#[test]
+fn test_bytes_per_pixel_all_combinations() {
+    // (颜色类型, 位深度, 每像素字节数)
+    let cases = [
+        (0, 1, 1), (0, 2, 1), (0, 4, 1), (0, 8, 1), (0, 16, 2),
+        (2, 8, 3), (2, 16, 6),
+        (3, 1, 1), (3, 2, 1), (3, 4, 1), (3, 8, 1),
+        (4, 8, 2), (4, 16, 4),
+        (6, 8, 4), (6, 16, 8),
+    ];
+
+    for (color_type, bit_depth, expected) in cases {
+        let color_info = ColorInfo::new(color_type, bit_depth);
+        assert_eq!(color_info.bytes_per_pixel, expected, "color_type={} bit_depth={}", color_type, bit_depth);
+    }
+}
+
+#[test]
+fn test_palette_color_info_rejects_16bit() {
+    // 调色板图像不存在16位，按8位处理
+    let color_info = ColorInfo::new(3, 16);
+
+    assert!(color_info.is_indexed());
+    assert!(matches!(color_info.bit_depth, BitDepth::Eight));
+    assert_eq!(color_info.channels, 1);
+    assert_eq!(color_info.bytes_per_pixel, 1);
+}
+
 #[test]
 fn test_compression_info() {
     let comp_info = CompressionInfo::new(0, 4); // Deflate + Paeth
@@ -0,0 +1,21 @@
+//! 同步解压缩测试用例
+
+use rust_png::sync_inflate::*;
+
+#[test]
+fn test_inflate_zlib_known_blob() {
+    // zlib.compress(b"hello png hello png", 9)
+    let compressed = [
+        0x78, 0xda, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0x28, 0xc8,
+        0x4b, 0x57, 0xc8, 0x80, 0xb1, 0x00, 0x47, 0x21, 0x07, 0x13,
+    ];
+
+    let inflated = inflate_zlib(&compressed).unwrap();
+    assert_eq!(inflated, b"hello png hello png");
+}
+
+#[test]
+fn test_inflate_zlib_rejects_raw_deflate() {
+    // 缺少zlib头的数据应返回错误
+    assert!(inflate_zlib(&[0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00]).is_err());
+}